@@ -0,0 +1,60 @@
+//! Compares `VectorClock` and `CompactVectorClock` across clock sizes, to
+//! find where the sorted small-vector storage stops paying off.
+
+#[macro_use]
+extern crate criterion;
+extern crate vectorclock;
+
+use criterion::{BenchmarkId, Criterion};
+use std::hint::black_box;
+
+use vectorclock::{VectorClock, CompactVectorClock};
+
+const SIZES: &[u32] = &[2, 4, 8, 16, 32, 64, 128];
+
+/// Two clocks over `size` hosts that differ in a single entry, so that
+/// comparisons have to look at every entry.
+fn clocks(size: u32) -> (VectorClock<u32>, VectorClock<u32>) {
+    let a = (0..size).map(|h| (h, u64::from(h) + 1)).collect::<VectorClock<_>>();
+    let b = a.incremented(size / 2);
+    (a, b)
+}
+
+fn bench_temporal_relation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("temporal_relation");
+
+    for &size in SIZES {
+        let (a, b) = clocks(size);
+        let (ca, cb) = (CompactVectorClock::from(&a), CompactVectorClock::from(&b));
+
+        group.bench_with_input(BenchmarkId::new("hash_map", size), &size, |bench, _| {
+            bench.iter(|| black_box(&a).temporal_relation(black_box(&b)))
+        });
+        group.bench_with_input(BenchmarkId::new("compact", size), &size, |bench, _| {
+            bench.iter(|| black_box(&ca).temporal_relation(black_box(&cb)))
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_merge(c: &mut Criterion) {
+    let mut group = c.benchmark_group("merge");
+
+    for &size in SIZES {
+        let (a, b) = clocks(size);
+        let (ca, cb) = (CompactVectorClock::from(&a), CompactVectorClock::from(&b));
+
+        group.bench_with_input(BenchmarkId::new("hash_map", size), &size, |bench, _| {
+            bench.iter(|| black_box(&a).merge_with(black_box(&b)))
+        });
+        group.bench_with_input(BenchmarkId::new("compact", size), &size, |bench, _| {
+            bench.iter(|| black_box(&ca).merge_with(black_box(&cb)))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_temporal_relation, bench_merge);
+criterion_main!(benches);
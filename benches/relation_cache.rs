@@ -0,0 +1,45 @@
+//! Compares `RelationCache` hits with computing `temporal_relation`
+//! directly, to check that the cache pays off at every clock size.
+
+#[macro_use]
+extern crate criterion;
+extern crate vectorclock;
+
+use criterion::{BenchmarkId, Criterion};
+use std::hint::black_box;
+
+use vectorclock::{VectorClock, DigestedClock, RelationCache};
+
+const SIZES: &[u32] = &[2, 8, 32, 128, 512];
+
+/// Two clocks over `size` hosts that differ in a single entry, so that
+/// comparisons have to look at every entry.
+fn clocks(size: u32) -> (VectorClock<u32>, VectorClock<u32>) {
+    let a = (0..size).map(|h| (h, u64::from(h) + 1)).collect::<VectorClock<_>>();
+    let b = a.incremented(size / 2);
+    (a, b)
+}
+
+fn bench_relation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("relation");
+
+    for &size in SIZES {
+        let (a, b) = clocks(size);
+        let (da, db) = (DigestedClock::new(a.clone()), DigestedClock::new(b.clone()));
+
+        let mut cache = RelationCache::new(16);
+        cache.relation(&da, &db);
+
+        group.bench_with_input(BenchmarkId::new("direct", size), &size, |bench, _| {
+            bench.iter(|| black_box(&a).temporal_relation(black_box(&b)))
+        });
+        group.bench_with_input(BenchmarkId::new("cache_hit", size), &size, |bench, _| {
+            bench.iter(|| cache.relation(black_box(&da), black_box(&db)))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_relation);
+criterion_main!(benches);
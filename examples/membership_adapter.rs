@@ -0,0 +1,93 @@
+//! Adapting a callback-based membership library, in the style of
+//! memberlist's `EventDelegate` or chitchat's live-node watchers, to
+//! `MembershipView`, and keeping clocks free of departed nodes.
+//!
+//! `Gossip` stands in for the library: it reports nodes together with the
+//! incarnation number it tracks for them.
+
+extern crate vectorclock;
+
+use std::sync::{Arc, Mutex};
+
+use vectorclock::{AntiEntropyScheduler, Incarnation, LocalMembership, MembershipChange, MembershipView,
+                  SchedulerConfig, VectorClock, prune_departed};
+
+/// The callbacks a membership library invokes, like memberlist's
+/// `EventDelegate`.
+trait NodeEvents {
+    fn node_joined(&self, name: &str, incarnation: u64);
+    fn node_left(&self, name: &str);
+}
+
+/// The adapter: forwards the library's callbacks into a `LocalMembership`
+/// shared with the components that read it.
+#[derive(Clone, Default)]
+struct MembershipAdapter {
+    view: Arc<Mutex<LocalMembership<String>>>,
+}
+
+impl NodeEvents for MembershipAdapter {
+    fn node_joined(&self, name: &str, incarnation: u64) {
+        self.view.lock().unwrap().join_at(name.to_owned(), incarnation);
+    }
+
+    fn node_left(&self, name: &str) {
+        self.view.lock().unwrap().leave(&name.to_owned());
+    }
+}
+
+impl MembershipView for MembershipAdapter {
+    type Peer = String;
+
+    fn peers(&self) -> Vec<String> {
+        self.view.lock().unwrap().peers()
+    }
+
+    fn epoch(&self, peer: &String) -> Option<u64> {
+        self.view.lock().unwrap().epoch(peer)
+    }
+
+    fn changes(&mut self) -> Vec<MembershipChange<String>> {
+        self.view.lock().unwrap().changes()
+    }
+}
+
+/// A stand-in for the membership library.
+struct Gossip<Events> {
+    events: Events,
+}
+
+impl<Events: NodeEvents> Gossip<Events> {
+    fn run(&self) {
+        self.events.node_joined("a", 1);
+        self.events.node_joined("b", 1);
+        self.events.node_left("a");
+        // b restarts with a fresh disk and a bumped incarnation.
+        self.events.node_joined("b", 2);
+    }
+}
+
+fn main() {
+    let mut adapter = MembershipAdapter::default();
+    let mut scheduler = AntiEntropyScheduler::<String, Incarnation<String>>::new(SchedulerConfig::default());
+
+    let mut clock = VectorClock::new()
+        .incremented(Incarnation::new("a".to_owned(), 1))
+        .incremented(Incarnation::new("b".to_owned(), 1));
+
+    Gossip { events: adapter.clone() }.run();
+    scheduler.follow(&mut adapter, std::time::Instant::now());
+
+    // Once the departed incarnations' events are known to be stable, their
+    // entries can be dropped.
+    for incarnation in prune_departed(&mut clock, &adapter) {
+        println!("pruned {}", incarnation);
+    }
+
+    // b's new incarnation counts from zero, so its events can't be
+    // confused with those of its lost state.
+    let b = "b".to_owned();
+    clock.increment(Incarnation::new(b.clone(), adapter.epoch(&b).unwrap()));
+
+    println!("members: {:?}, clock: {}", adapter.peers(), clock);
+}
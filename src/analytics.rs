@@ -0,0 +1,230 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::time::{Duration, SystemTime};
+
+use clock::{VectorClock, TemporalRelation};
+use version_set::VersionSet;
+
+/// Conflict statistics for one time window.
+#[derive(Debug, PartialEq, Clone)]
+pub struct WindowReport<Key: Hash + Eq, HostType> {
+    pub start: SystemTime,
+    pub operations: u64,
+    /// Pairs of operations on the same key that were compared.
+    pub pairs: u64,
+    /// Compared pairs that turned out to be concurrent.
+    pub concurrent_pairs: u64,
+    /// The largest number of concurrent versions seen for each key.
+    pub siblings: HashMap<Key, usize>,
+    /// Host pairs that wrote concurrently to the same key, most frequent
+    /// first.
+    pub host_pairs: Vec<((HostType, HostType), u64)>,
+}
+
+impl<Key: Hash + Eq, HostType> WindowReport<Key, HostType> {
+    /// The fraction of compared pairs that were concurrent.
+    pub fn concurrency_rate(&self) -> f64 {
+        if self.pairs == 0 { 0.0 } else { self.concurrent_pairs as f64 / self.pairs as f64 }
+    }
+}
+
+#[derive(Debug)]
+struct Window<Key: Hash + Eq, HostType: Hash + Eq> {
+    start: SystemTime,
+    operations: u64,
+    pairs: u64,
+    concurrent_pairs: u64,
+    writes: HashMap<Key, Vec<(HostType, VectorClock<HostType>)>>,
+    frontiers: HashMap<Key, VersionSet<HostType, ()>>,
+    siblings: HashMap<Key, usize>,
+    host_pairs: HashMap<(HostType, HostType), u64>,
+}
+
+/// Collects conflict statistics over a stream of operations stamped with
+/// the key they touched, the host that made them and their clock.
+///
+/// Operations are grouped into consecutive windows of fixed length, and a
+/// `WindowReport` is produced for every window that has ended. Operations
+/// should arrive roughly in time order; a late operation is counted in the
+/// current window.
+#[derive(Debug)]
+pub struct ConflictAnalyzer<Key: Hash + Eq, HostType: Hash + Eq> {
+    window: Duration,
+    current: Option<Window<Key, HostType>>,
+    reports: Vec<WindowReport<Key, HostType>>,
+}
+
+impl<Key, HostType> ConflictAnalyzer<Key, HostType>
+    where Key: Clone + Hash + Eq, HostType: Clone + Hash + Eq + Ord
+{
+    /// Panics if `window` is zero.
+    pub fn new(window: Duration) -> ConflictAnalyzer<Key, HostType> {
+        assert!(!window.is_zero(), "conflict analysis window must not be zero");
+
+        ConflictAnalyzer {
+            window,
+            current: None,
+            reports: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, key: Key, host: HostType, clock: VectorClock<HostType>, at: SystemTime) {
+        let expired = self.current.as_ref().is_some_and(|w| at >= w.start + self.window);
+
+        if expired {
+            let start = self.current.as_ref().map(|w| w.start).unwrap_or(at);
+            self.flush();
+
+            let mut next = start;
+            while at >= next + self.window {
+                next += self.window;
+            }
+            self.current = Some(Window::new(next));
+        }
+
+        let window = self.current.get_or_insert_with(|| Window::new(at));
+        window.record(key, host, clock);
+    }
+
+    /// End the current window early and return its report.
+    pub fn flush(&mut self) -> Option<&WindowReport<Key, HostType>> {
+        let window = self.current.take()?;
+        self.reports.push(window.into_report());
+        self.reports.last()
+    }
+
+    /// Take the reports of all windows that have ended.
+    pub fn reports(&mut self) -> Vec<WindowReport<Key, HostType>> {
+        self.reports.drain(..).collect()
+    }
+}
+
+impl<Key, HostType> Window<Key, HostType>
+    where Key: Clone + Hash + Eq, HostType: Clone + Hash + Eq + Ord
+{
+    fn new(start: SystemTime) -> Window<Key, HostType> {
+        Window {
+            start,
+            operations: 0,
+            pairs: 0,
+            concurrent_pairs: 0,
+            writes: HashMap::new(),
+            frontiers: HashMap::new(),
+            siblings: HashMap::new(),
+            host_pairs: HashMap::new(),
+        }
+    }
+
+    fn record(&mut self, key: Key, host: HostType, clock: VectorClock<HostType>) {
+        let writes = self.writes.entry(key.clone()).or_default();
+
+        for (other_host, other) in writes.iter() {
+            self.pairs += 1;
+
+            if clock.temporal_relation(other) == TemporalRelation::Concurrent {
+                self.concurrent_pairs += 1;
+
+                let pair = if host <= *other_host {
+                    (host.clone(), other_host.clone())
+                } else {
+                    (other_host.clone(), host.clone())
+                };
+                *self.host_pairs.entry(pair).or_insert(0) += 1;
+            }
+        }
+
+        let frontier = self.frontiers.entry(key.clone()).or_default();
+        frontier.insert(clock.clone(), ());
+
+        writes.push((host, clock));
+        self.operations += 1;
+
+        let live = frontier.len();
+        let siblings = self.siblings.entry(key).or_insert(0);
+        *siblings = ::std::cmp::max(*siblings, live);
+    }
+
+    fn into_report(self) -> WindowReport<Key, HostType> {
+        let mut host_pairs: Vec<_> = self.host_pairs.into_iter().collect();
+        host_pairs.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        WindowReport {
+            start: self.start,
+            operations: self.operations,
+            pairs: self.pairs,
+            concurrent_pairs: self.concurrent_pairs,
+            siblings: self.siblings,
+            host_pairs,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::ConflictAnalyzer;
+    use clock::VectorClock;
+
+    #[test]
+    fn test_window_report() {
+        let t = UNIX_EPOCH + Duration::from_secs(1000);
+        let mut a = ConflictAnalyzer::new(Duration::from_secs(60));
+
+        let base = VectorClock::new().incremented("A");
+
+        a.record("k1", "A", base.clone(), t);
+        a.record("k1", "B", base.incremented("B"), t);
+        a.record("k1", "C", base.incremented("C"), t);
+        a.record("k2", "A", base.clone(), t);
+
+        let report = a.flush().unwrap().clone();
+
+        assert_eq!(4, report.operations);
+        assert_eq!(3, report.pairs);
+        assert_eq!(1, report.concurrent_pairs);
+        assert_eq!(Some(&2), report.siblings.get("k1"));
+        assert_eq!(Some(&1), report.siblings.get("k2"));
+        assert_eq!(vec![(("B", "C"), 1)], report.host_pairs);
+        assert!((report.concurrency_rate() - 1.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_siblings_follow_frontier() {
+        let t = UNIX_EPOCH;
+        let mut a = ConflictAnalyzer::new(Duration::from_secs(60));
+
+        let b = VectorClock::new().incremented("B");
+        let c = VectorClock::new().incremented("C");
+
+        a.record("k", "B", b.clone(), t);
+        a.record("k", "C", c.clone(), t);
+        a.record("k", "A", b.merge_with(&c).incremented("A"), t);
+        a.record("k", "B", b.incremented("B"), t);
+
+        assert_eq!(Some(&2), a.flush().unwrap().siblings.get("k"));
+    }
+
+    #[test]
+    #[should_panic(expected = "window must not be zero")]
+    fn test_rejects_zero_window() {
+        ConflictAnalyzer::<&str, &str>::new(Duration::ZERO);
+    }
+
+    #[test]
+    fn test_windows_roll_over() {
+        let t = UNIX_EPOCH + Duration::from_secs(1000);
+        let mut a = ConflictAnalyzer::new(Duration::from_secs(60));
+
+        a.record("k", "A", VectorClock::new().incremented("A"), t);
+        a.record("k", "B", VectorClock::new().incremented("B"), t + Duration::from_secs(150));
+
+        let reports = a.reports();
+
+        assert_eq!(1, reports.len());
+        assert_eq!(t, reports[0].start);
+        assert_eq!(0, reports[0].pairs);
+
+        assert_eq!(t + Duration::from_secs(120), a.flush().unwrap().start);
+    }
+}
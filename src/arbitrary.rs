@@ -0,0 +1,220 @@
+//! Generators of clocks for property tests, with the `proptest` and
+//! `quickcheck` features.
+//!
+//! Besides `Arbitrary` implementations for `VectorClock` and
+//! `TemporalRelation`, this module generates pairs of clocks in a given
+//! relation: `clock_pairs` for proptest and `RelatedClocks` for quickcheck.
+//! Concurrent pairs need two distinct hosts, so they can't be generated for
+//! host types with a single value.
+
+use std::hash::Hash;
+
+use clock::{VectorClock, TemporalRelation};
+
+const RELATIONS: [TemporalRelation; 4] = [
+    TemporalRelation::Equal,
+    TemporalRelation::Caused,
+    TemporalRelation::EffectOf,
+    TemporalRelation::Concurrent,
+];
+
+/// Shape two clocks into a pair `(a, b)` with `a.temporal_relation(&b) ==
+/// relation`. `first` and `second` must differ for concurrent pairs.
+fn relate<HostType>(relation: TemporalRelation, a: VectorClock<HostType>, b: VectorClock<HostType>,
+                    first: HostType, second: HostType)
+    -> (VectorClock<HostType>, VectorClock<HostType>)
+    where HostType: Clone + Hash + Eq
+{
+    match relation {
+        TemporalRelation::Equal => (a.clone(), a),
+        TemporalRelation::Caused => {
+            let later = a.merge_with(&b).incremented(first);
+            (a, later)
+        },
+        TemporalRelation::EffectOf => {
+            let later = a.merge_with(&b).incremented(first);
+            (later, a)
+        },
+        TemporalRelation::Concurrent => {
+            // Each side gets an entry the other hasn't reached.
+            let n = a.get(&first).max(b.get(&first)) + 1;
+            let a = a.merge_with(&Some((first, n)).into_iter().collect());
+
+            let n = a.get(&second).max(b.get(&second)) + 1;
+            let b = b.merge_with(&Some((second, n)).into_iter().collect());
+
+            (a, b)
+        },
+    }
+}
+
+/// Clocks with small counters, leaving room to increment them.
+fn small_clock<HostType: Clone + Hash + Eq>(entries: Vec<(HostType, u16)>) -> VectorClock<HostType> {
+    entries.into_iter().map(|(host, n)| (host, u64::from(n))).collect()
+}
+
+#[cfg(feature = "proptest")]
+pub use self::proptest_impls::clock_pairs;
+
+#[cfg(feature = "proptest")]
+mod proptest_impls {
+    use std::fmt::Debug;
+    use std::hash::Hash;
+
+    use proptest::arbitrary::{any, Arbitrary};
+    use proptest::collection::vec;
+    use proptest::sample::select;
+    use proptest::strategy::{BoxedStrategy, Strategy};
+
+    use clock::{VectorClock, TemporalRelation};
+    use counter::Counter;
+
+    use super::{RELATIONS, relate, small_clock};
+
+    impl<HostType, CounterType> Arbitrary for VectorClock<HostType, CounterType>
+        where HostType: Arbitrary + Clone + Hash + Eq + 'static,
+              CounterType: Arbitrary + Counter + 'static
+    {
+        type Parameters = ();
+        type Strategy = BoxedStrategy<VectorClock<HostType, CounterType>>;
+
+        fn arbitrary_with(_: ()) -> Self::Strategy {
+            vec((any::<HostType>(), any::<CounterType>()), 0..8)
+                .prop_map(|entries| entries.into_iter().collect())
+                .boxed()
+        }
+    }
+
+    impl Arbitrary for TemporalRelation {
+        type Parameters = ();
+        type Strategy = BoxedStrategy<TemporalRelation>;
+
+        fn arbitrary_with(_: ()) -> Self::Strategy {
+            select(&RELATIONS[..]).boxed()
+        }
+    }
+
+    /// Pairs of clocks `(a, b)` with `a.temporal_relation(&b) == relation`.
+    pub fn clock_pairs<HostType>(relation: TemporalRelation)
+        -> BoxedStrategy<(VectorClock<HostType>, VectorClock<HostType>)>
+        where HostType: Arbitrary + Clone + Hash + Eq + Debug + 'static
+    {
+        let entries = || vec((any::<HostType>(), any::<u16>()), 0..8).prop_map(small_clock);
+        let hosts = (any::<HostType>(), any::<HostType>())
+            .prop_filter("concurrent clocks need two distinct hosts",
+                         move |(first, second)| relation != TemporalRelation::Concurrent || first != second);
+
+        (entries(), entries(), hosts)
+            .prop_map(move |(a, b, (first, second))| relate(relation, a, b, first, second))
+            .boxed()
+    }
+}
+
+#[cfg(feature = "quickcheck")]
+pub use self::quickcheck_impls::RelatedClocks;
+
+#[cfg(feature = "quickcheck")]
+mod quickcheck_impls {
+    use std::hash::Hash;
+
+    use quickcheck::{Arbitrary, Gen};
+
+    use clock::{VectorClock, TemporalRelation};
+    use counter::Counter;
+
+    use super::{RELATIONS, relate, small_clock};
+
+    impl<HostType, CounterType> Arbitrary for VectorClock<HostType, CounterType>
+        where HostType: Arbitrary + Clone + Hash + Eq,
+              CounterType: Arbitrary + Counter
+    {
+        fn arbitrary(g: &mut Gen) -> VectorClock<HostType, CounterType> {
+            Vec::<(HostType, CounterType)>::arbitrary(g).into_iter().collect()
+        }
+
+        fn shrink(&self) -> Box<dyn Iterator<Item=VectorClock<HostType, CounterType>>> {
+            let entries: Vec<_> = self.clone().into_iter().collect();
+            Box::new(entries.shrink().map(|entries| entries.into_iter().collect()))
+        }
+    }
+
+    impl Arbitrary for TemporalRelation {
+        fn arbitrary(g: &mut Gen) -> TemporalRelation {
+            *g.choose(&RELATIONS).unwrap()
+        }
+    }
+
+    /// Two clocks generated to be in a random `relation`, i.e. with
+    /// `a.temporal_relation(&b) == relation`.
+    #[derive(Debug, Clone)]
+    pub struct RelatedClocks<HostType: Hash + Eq> {
+        pub a: VectorClock<HostType>,
+        pub b: VectorClock<HostType>,
+        pub relation: TemporalRelation,
+    }
+
+    impl<HostType: Arbitrary + Clone + Hash + Eq> Arbitrary for RelatedClocks<HostType> {
+        fn arbitrary(g: &mut Gen) -> RelatedClocks<HostType> {
+            let relation = TemporalRelation::arbitrary(g);
+            let first = HostType::arbitrary(g);
+
+            let second = (0..100).map(|_| HostType::arbitrary(g))
+                .find(|second| relation != TemporalRelation::Concurrent || *second != first)
+                .expect("concurrent clocks need two distinct hosts");
+
+            let a = small_clock(Arbitrary::arbitrary(g));
+            let b = small_clock(Arbitrary::arbitrary(g));
+            let (a, b) = relate(relation, a, b, first, second);
+
+            RelatedClocks {
+                a,
+                b,
+                relation,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::RELATIONS;
+
+    #[cfg(feature = "proptest")]
+    #[test]
+    fn test_clock_pairs() {
+        use proptest::strategy::{Strategy, ValueTree};
+        use proptest::test_runner::TestRunner;
+
+        use super::clock_pairs;
+
+        let mut runner = TestRunner::deterministic();
+
+        for &relation in &RELATIONS {
+            let strategy = clock_pairs::<u8>(relation);
+
+            for _ in 0..100 {
+                let (a, b) = strategy.new_tree(&mut runner).unwrap().current();
+                assert_eq!(relation, a.temporal_relation(&b));
+            }
+        }
+    }
+
+    #[cfg(feature = "quickcheck")]
+    #[test]
+    fn test_related_clocks() {
+        use quickcheck::{Arbitrary, Gen};
+
+        use super::RelatedClocks;
+
+        let mut g = Gen::new(10);
+        let mut seen = Vec::new();
+
+        for _ in 0..200 {
+            let pair = RelatedClocks::<u8>::arbitrary(&mut g);
+            assert_eq!(pair.relation, pair.a.temporal_relation(&pair.b));
+            seen.push(pair.relation);
+        }
+
+        assert!(RELATIONS.iter().all(|r| seen.contains(r)));
+    }
+}
@@ -0,0 +1,166 @@
+use std::fmt::Display;
+use std::hash::Hash;
+#[cfg(feature = "parquet")]
+use std::io::Write;
+use std::sync::Arc;
+
+use arrow_array::{ArrayRef, RecordBatch, StringArray, UInt64Array};
+use arrow_array::builder::{MapBuilder, StringBuilder, UInt64Builder};
+use arrow_schema::ArrowError;
+#[cfg(feature = "parquet")]
+use parquet::arrow::ArrowWriter;
+#[cfg(feature = "parquet")]
+use parquet::errors::ParquetError;
+
+use stamped::Stamped;
+
+/// Explode the clocks of `events` into one row per clock entry, with the
+/// columns `event` (the index into `events`), `value`, `host` and
+/// `counter`. Values are written with their `Display`.
+///
+/// Entries are ordered by event and then by host. Events with an empty
+/// clock have no rows, see `clocks_batch` for a row for every event.
+pub fn entries_batch<T, HostType>(events: &[Stamped<T, HostType>]) -> Result<RecordBatch, ArrowError>
+    where T: Display,
+          HostType: Clone + Hash + Eq + Display
+{
+    let mut event = Vec::new();
+    let mut value = Vec::new();
+    let mut host = Vec::new();
+    let mut counter = Vec::new();
+
+    for (i, stamped) in events.iter().enumerate() {
+        for (h, n) in sorted_entries(stamped) {
+            event.push(i as u64);
+            value.push(stamped.value.to_string());
+            host.push(h);
+            counter.push(n);
+        }
+    }
+
+    RecordBatch::try_from_iter(vec![
+        ("event", Arc::new(UInt64Array::from(event)) as ArrayRef),
+        ("value", Arc::new(StringArray::from(value)) as ArrayRef),
+        ("host", Arc::new(StringArray::from(host)) as ArrayRef),
+        ("counter", Arc::new(UInt64Array::from(counter)) as ArrayRef),
+    ])
+}
+
+/// One row per event, with the columns `event`, `value` and `clock`, a
+/// map from host to counter. Rows line up with `events`, so further
+/// columns can be added alongside.
+pub fn clocks_batch<T, HostType>(events: &[Stamped<T, HostType>]) -> Result<RecordBatch, ArrowError>
+    where T: Display,
+          HostType: Clone + Hash + Eq + Display
+{
+    let mut clocks = MapBuilder::new(None, StringBuilder::new(), UInt64Builder::new());
+
+    for stamped in events {
+        for (h, n) in sorted_entries(stamped) {
+            clocks.keys().append_value(h);
+            clocks.values().append_value(n);
+        }
+        clocks.append(true)?;
+    }
+
+    RecordBatch::try_from_iter(vec![
+        ("event", Arc::new(UInt64Array::from_iter_values(0..events.len() as u64)) as ArrayRef),
+        ("value", Arc::new(StringArray::from_iter_values(events.iter().map(|e| e.value.to_string()))) as ArrayRef),
+        ("clock", Arc::new(clocks.finish()) as ArrayRef),
+    ])
+}
+
+/// Write `events` to `writer` as a Parquet file with the columns of
+/// `clocks_batch`.
+#[cfg(feature = "parquet")]
+pub fn write_parquet<T, HostType, W>(events: &[Stamped<T, HostType>], writer: W) -> Result<(), ParquetError>
+    where T: Display,
+          HostType: Clone + Hash + Eq + Display,
+          W: Write + Send
+{
+    let batch = clocks_batch(events)?;
+    let mut writer = ArrowWriter::try_new(writer, batch.schema(), None)?;
+
+    writer.write(&batch)?;
+    writer.close()?;
+    Ok(())
+}
+
+fn sorted_entries<T, HostType>(stamped: &Stamped<T, HostType>) -> Vec<(String, u64)>
+    where HostType: Clone + Hash + Eq + Display
+{
+    let mut entries: Vec<_> = stamped.clock.iter().map(|(h, &n)| (h.to_string(), n)).collect();
+    entries.sort();
+    entries
+}
+
+#[cfg(test)]
+mod test {
+    use arrow_array::{Array, MapArray, StringArray, UInt64Array};
+
+    use super::{entries_batch, clocks_batch};
+    use clock::VectorClock;
+    use stamped::Stamped;
+
+    fn events() -> Vec<Stamped<&'static str, &'static str>> {
+        let a = VectorClock::new().incremented("A");
+
+        vec![
+            Stamped::new("first", a.clone()),
+            Stamped::new("second", a.incremented("B")),
+        ]
+    }
+
+    #[test]
+    fn test_entries_batch() {
+        let batch = entries_batch(&events()).unwrap();
+
+        assert_eq!(3, batch.num_rows());
+
+        let event = batch.column(0).as_any().downcast_ref::<UInt64Array>().unwrap();
+        let value = batch.column(1).as_any().downcast_ref::<StringArray>().unwrap();
+        let host = batch.column(2).as_any().downcast_ref::<StringArray>().unwrap();
+        let counter = batch.column(3).as_any().downcast_ref::<UInt64Array>().unwrap();
+
+        assert_eq!(vec![Some(0), Some(1), Some(1)], event.iter().collect::<Vec<_>>());
+        assert_eq!(vec![Some("first"), Some("second"), Some("second")], value.iter().collect::<Vec<_>>());
+        assert_eq!(vec![Some("A"), Some("A"), Some("B")], host.iter().collect::<Vec<_>>());
+        assert_eq!(vec![Some(1), Some(1), Some(1)], counter.iter().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_clocks_batch() {
+        let batch = clocks_batch(&events()).unwrap();
+
+        assert_eq!(2, batch.num_rows());
+
+        let value = batch.column(1).as_any().downcast_ref::<StringArray>().unwrap();
+        let clocks = batch.column(2).as_any().downcast_ref::<MapArray>().unwrap();
+
+        assert_eq!(vec![Some("first"), Some("second")], value.iter().collect::<Vec<_>>());
+        assert_eq!(1, clocks.value(0).len());
+        assert_eq!(2, clocks.value(1).len());
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn test_write_parquet() {
+        use std::env;
+        use std::fs::{self, File};
+        use std::process;
+
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+        use super::write_parquet;
+
+        let path = env::temp_dir().join(format!("vectorclock-{}.parquet", process::id()));
+        write_parquet(&events(), File::create(&path).unwrap()).unwrap();
+
+        let batches = ParquetRecordBatchReaderBuilder::try_new(File::open(&path).unwrap()).unwrap()
+            .build().unwrap()
+            .collect::<Result<Vec<_>, _>>().unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(vec![clocks_batch(&events()).unwrap()], batches);
+    }
+}
@@ -0,0 +1,107 @@
+use std::prelude::v1::*;
+
+#[cfg(feature = "riak")]
+pub(crate) const STANDARD: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+pub(crate) const URL_SAFE: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Append the base64 encoding of `bytes` to `out`, padded with `=` to a
+/// multiple of four characters if `padded` is set.
+pub(crate) fn encode(bytes: &[u8], alphabet: &[u8; 64], padded: bool, out: &mut String) {
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, &b)| n | ((b as u32) << (16 - 8 * i)));
+
+        for i in 0..chunk.len() + 1 {
+            out.push(alphabet[(n >> (18 - 6 * i)) as usize & 63] as char);
+        }
+
+        if padded {
+            for _ in chunk.len()..3 {
+                out.push('=');
+            }
+        }
+    }
+}
+
+/// Decode base64 written by `encode` with the same settings.
+///
+/// Only the exact output of `encode` is accepted, so each byte string has
+/// only one accepted encoding.
+pub(crate) fn decode(input: &str, alphabet: &[u8; 64], padded: bool) -> Option<Vec<u8>> {
+    let input = if padded {
+        if !input.len().is_multiple_of(4) {
+            return None;
+        }
+
+        let trimmed = input.trim_end_matches('=');
+
+        if input.len() - trimmed.len() > 2 {
+            return None;
+        }
+
+        trimmed
+    }
+    else {
+        input
+    };
+
+    if input.len() % 4 == 1 {
+        return None;
+    }
+
+    let mut out = Vec::with_capacity(input.len() * 3 / 4);
+    let mut acc = 0u32;
+    let mut bits = 0;
+
+    for b in input.bytes() {
+        let v = alphabet.iter().position(|&a| a == b)? as u32;
+
+        acc = (acc << 6) | v;
+        bits += 6;
+
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+        }
+    }
+
+    // Leftover bits must be zero padding.
+    if acc & ((1 << bits) - 1) != 0 {
+        return None;
+    }
+
+    Some(out)
+}
+
+#[cfg(test)]
+mod test {
+    use std::prelude::v1::*;
+    use super::{encode, decode, URL_SAFE};
+
+    #[test]
+    fn test_round_trip() {
+        for len in 0..8 {
+            let bytes: Vec<u8> = (0..len).map(|i| 0xF0 | i as u8).collect();
+
+            for &padded in &[false, true] {
+                let mut s = String::new();
+                encode(&bytes, URL_SAFE, padded, &mut s);
+
+                assert!(if padded { s.len().is_multiple_of(4) } else { !s.contains('=') });
+                assert_eq!(Some(bytes.clone()), decode(&s, URL_SAFE, padded));
+            }
+        }
+    }
+
+    #[test]
+    fn test_known_values() {
+        let mut s = String::new();
+        encode(b"\xfb\xff", URL_SAFE, true, &mut s);
+        assert_eq!("-_8=", s);
+
+        assert_eq!(Some(b"\xfb\xff".to_vec()), decode("-_8", URL_SAFE, false));
+        assert_eq!(None, decode("-_8", URL_SAFE, true));
+        assert_eq!(None, decode("-_8=", URL_SAFE, false));
+        assert_eq!(None, decode("+/8=", URL_SAFE, true));
+        assert_eq!(None, decode("-_9=", URL_SAFE, true));
+    }
+}
@@ -0,0 +1,377 @@
+extern crate serde_json;
+extern crate vectorclock;
+
+use std::env;
+use std::fs;
+use std::io::{self, Read};
+use std::process;
+
+use serde_json::Value;
+use vectorclock::{VectorClock, TemporalRelation, Expectation, LogChecker};
+
+type Clock = VectorClock<String>;
+
+const USAGE: &str = "\
+usage: vclock show <clock>
+       vclock compare <clock> <clock>
+       vclock merge < clocks
+       vclock frontier < clocks
+       vclock sort [--field <name>] < records
+       vclock check <rules> <log>...
+
+merge and frontier read one clock per line from stdin, and print the merged
+clock or the clocks not dominated by any other. sort reads JSON records with
+a clock in the given field (default \"clock\") and prints them in an order
+consistent with causality.
+
+check reads log files whose lines carry op=<label> and vclock=<clock> tokens,
+with clocks as in the x-vector-clock header, and verifies the rules file. Each
+rule is a line \"<b> follows <a>\" or \"<a> ordered <b>\". Violations, and
+lines whose clock doesn't decode, are printed and make the command fail.
+
+Clocks are given as JSON, either an object {\"host\": counter, ...} or a list
+of [host, counter] pairs. The JSON may also be hex or base64 encoded, as is
+common when clocks are carried in headers. Clocks as printed in logs, e.g.
+A:3,B:1, are accepted too. Pass - to read a clock from stdin.";
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(|s| &s[..]).collect();
+
+    let result = match &args[..] {
+        ["show", clock] => read_clock(clock).map(|c| show(&c)),
+        ["compare", a, b] => read_clock(a).and_then(|a| read_clock(b).map(|b| compare(&a, &b))),
+        ["merge"] => read_stdin().and_then(|input| merge(&input)),
+        ["frontier"] => read_stdin().and_then(|input| frontier(&input)),
+        ["sort"] => read_stdin().and_then(|input| sort(&input, "clock")),
+        ["sort", "--field", field] => read_stdin().and_then(|input| sort(&input, field)),
+        ["check", rules, logs @ ..] if !logs.is_empty() => check_files(rules, logs),
+        _ => Err(USAGE.to_owned()),
+    };
+
+    match result {
+        Ok(output) => print!("{}", output),
+        Err(msg) => {
+            eprintln!("{}", msg);
+            process::exit(1);
+        },
+    }
+}
+
+fn read_stdin() -> Result<String, String> {
+    let mut input = String::new();
+    io::stdin().read_to_string(&mut input).map_err(|e| e.to_string())?;
+    Ok(input)
+}
+
+fn read_clock(arg: &str) -> Result<Clock, String> {
+    if arg == "-" {
+        parse_clock(&read_stdin()?)
+    }
+    else {
+        parse_clock(arg)
+    }
+}
+
+fn lines(input: &str) -> impl Iterator<Item=(usize, &str)> {
+    input.lines().enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|&(_, line)| !line.is_empty())
+}
+
+fn parse_lines(input: &str) -> Result<Vec<Clock>, String> {
+    lines(input)
+        .map(|(i, line)| parse_clock(line).map_err(|e| format!("line {}: {}", i, e)))
+        .collect()
+}
+
+fn parse_clock(input: &str) -> Result<Clock, String> {
+    let input = input.trim();
+
+    if input.starts_with('{') || input.starts_with('[') {
+        return parse_json(input.as_bytes());
+    }
+
+    decode_hex(input).and_then(|bytes| parse_json(&bytes).ok())
+        .or_else(|| decode_base64(input).and_then(|bytes| parse_json(&bytes).ok()))
+        .or_else(|| input.parse().ok())
+        .ok_or_else(|| "clock is neither JSON, hex or base64 encoded JSON, nor host:counter text".to_owned())
+}
+
+fn parse_json(input: &[u8]) -> Result<Clock, String> {
+    serde_json::from_slice(input)
+        .map_err(|e| format!("invalid JSON: {}", e))
+        .and_then(clock_from_value)
+}
+
+fn clock_from_value(value: Value) -> Result<Clock, String> {
+    let entries = match value {
+        Value::Object(map) => map.into_iter()
+            .map(|(host, n)| counter(&host, &n).map(|n| (host, n)))
+            .collect::<Result<Vec<_>, _>>()?,
+        Value::Array(pairs) => pairs.iter()
+            .map(|pair| match pair.as_array().map(|p| &p[..]) {
+                Some([Value::String(host), n]) => counter(host, n).map(|n| (host.clone(), n)),
+                _ => Err(format!("expected a [host, counter] pair, found {}", pair)),
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+        other => return Err(format!("expected an object or a list of pairs, found {}", other)),
+    };
+
+    Ok(entries.into_iter().collect())
+}
+
+fn counter(host: &str, n: &Value) -> Result<u64, String> {
+    n.as_u64().ok_or_else(|| format!("counter for host {:?} is not a non-negative integer", host))
+}
+
+fn decode_hex(input: &str) -> Option<Vec<u8>> {
+    if !input.len().is_multiple_of(2) || !input.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    (0..input.len()).step_by(2)
+        .map(|i| u8::from_str_radix(&input[i..i + 2], 16).ok())
+        .collect()
+}
+
+fn decode_base64(input: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(input.len() * 3 / 4);
+    let mut acc = 0u32;
+    let mut bits = 0;
+
+    for b in input.trim_end_matches('=').bytes() {
+        let v = match b {
+            b'A'..=b'Z' => b - b'A',
+            b'a'..=b'z' => b - b'a' + 26,
+            b'0'..=b'9' => b - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            _ => return None,
+        };
+
+        acc = (acc << 6) | v as u32;
+        bits += 6;
+
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+        }
+    }
+
+    Some(out)
+}
+
+fn sorted(clock: &Clock) -> Vec<(String, u64)> {
+    let mut entries: Vec<_> = clock.iter().map(|(host, &n)| (host.clone(), n)).collect();
+    entries.sort();
+    entries
+}
+
+fn to_json(clock: &Clock) -> Value {
+    Value::Object(clock.iter().map(|(host, &n)| (host.clone(), Value::from(n))).collect())
+}
+
+fn merge(input: &str) -> Result<String, String> {
+    let merged = parse_lines(input)?.iter().fold(VectorClock::new(), |acc, c| acc.merge_with(c));
+
+    Ok(format!("{}\n", to_json(&merged)))
+}
+
+fn frontier(input: &str) -> Result<String, String> {
+    let clocks = parse_lines(input)?;
+    let mut out = String::new();
+
+    for (i, c) in clocks.iter().enumerate() {
+        let dominated = clocks.iter().enumerate().any(|(j, other)| match c.temporal_relation(other) {
+            TemporalRelation::Caused => true,
+            TemporalRelation::Equal => j < i,
+            _ => false,
+        });
+
+        if !dominated {
+            out.push_str(&format!("{}\n", to_json(c)));
+        }
+    }
+
+    Ok(out)
+}
+
+fn sort(input: &str, field: &str) -> Result<String, String> {
+    let mut records = lines(input)
+        .map(|(i, line)| {
+            let record: Value = serde_json::from_str(line).map_err(|e| format!("line {}: invalid JSON: {}", i, e))?;
+            let clock = record.get(field).cloned()
+                .ok_or_else(|| format!("line {}: no field {:?}", i, field))
+                .and_then(|v| clock_from_value(v).map_err(|e| format!("line {}: {}", i, e)))?;
+
+            Ok((clock.iter().map(|(_, &n)| n as u128).sum::<u128>(), line))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    // If a happened before b, every counter of a is at most the counter of b
+    // and at least one is smaller, so ordering by the sum of counters is a
+    // valid causal order. The stable sort keeps unrelated records in input
+    // order where their sums tie.
+    records.sort_by_key(|&(sum, _)| sum);
+
+    Ok(records.iter().map(|&(_, line)| format!("{}\n", line)).collect())
+}
+
+fn check_files(rules: &str, logs: &[&str]) -> Result<String, String> {
+    let read = |path: &str| fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e));
+
+    let logs = logs.iter().map(|&path| read(path).map(|log| (path, log))).collect::<Result<Vec<_>, _>>()?;
+
+    check(&read(rules)?, &logs)
+}
+
+fn check(rules: &str, logs: &[(&str, String)]) -> Result<String, String> {
+    let mut checker = LogChecker::new();
+
+    for (i, line) in lines(rules) {
+        let expectation = match line.split_whitespace().collect::<Vec<_>>()[..] {
+            [after, "follows", before] => Expectation::Follows { before: before.to_owned(), after: after.to_owned() },
+            [a, "ordered", b] => Expectation::Ordered { a: a.to_owned(), b: b.to_owned() },
+            _ => return Err(format!("line {}: expected \"<b> follows <a>\" or \"<a> ordered <b>\"", i)),
+        };
+
+        checker.expect(expectation);
+    }
+
+    for &(process, ref log) in logs {
+        checker.ingest(process, log);
+    }
+
+    let violations = checker.check();
+    let mut out = String::new();
+
+    for m in checker.malformed() {
+        out.push_str(&format!("{}:{}: malformed vclock: {}\n", m.process, m.line, m.error));
+    }
+
+    for v in &violations {
+        out.push_str(&format!("{}: {}:{} and {}:{} are {:?}\n", v.expectation,
+                              v.first.process, v.first.line, v.second.process, v.second.line, v.relation));
+    }
+
+    if out.is_empty() {
+        Ok(format!("{} operations checked\n", checker.ops().len()))
+    }
+    else {
+        Err(out.trim_end().to_owned())
+    }
+}
+
+fn show(clock: &Clock) -> String {
+    let entries = sorted(clock);
+    let width = entries.iter().map(|(h, _)| h.len()).max().unwrap_or(0);
+
+    entries.iter()
+        .map(|(host, n)| format!("{:width$}  {}\n", host, n, width = width))
+        .collect()
+}
+
+fn compare(a: &Clock, b: &Clock) -> String {
+    let hosts: Vec<String> = sorted(&a.merge_with(b)).into_iter().map(|(h, _)| h).collect();
+
+    let width = hosts.iter().map(|h| h.len()).max().unwrap_or(0);
+    let mut out = format!("{:?}\n", a.temporal_relation(b));
+
+    for host in &hosts {
+        let na = a.get(host);
+        let nb = b.get(host);
+
+        out.push_str(&format!("{:width$}  {}  {}  {:+}\n", host, na, nb,
+                              na as i128 - nb as i128, width = width));
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_clock, show, compare, merge, frontier, sort, check};
+
+    #[test]
+    fn test_parse_object_and_pairs() {
+        let a = parse_clock(r#"{"A": 2, "B": 1}"#).unwrap();
+        let b = parse_clock(r#"[["A", 2], ["B", 1]]"#).unwrap();
+
+        assert_eq!(a, b);
+        assert!(parse_clock(r#"{"A": -1}"#).is_err());
+        assert!(parse_clock("3").is_err());
+    }
+
+    #[test]
+    fn test_parse_encoded() {
+        let json = parse_clock(r#"{"A":2}"#).unwrap();
+
+        assert_eq!(json, parse_clock("7b2241223a327d").unwrap());
+        assert_eq!(json, parse_clock("eyJBIjoyfQ==").unwrap());
+        assert_eq!(json, parse_clock("eyJBIjoyfQ").unwrap());
+        assert_eq!(json, parse_clock("A:2").unwrap());
+    }
+
+    #[test]
+    fn test_show_sorted() {
+        let c = parse_clock(r#"{"host-b": 1, "a": 12}"#).unwrap();
+
+        assert_eq!("a       12\nhost-b  1\n", show(&c));
+    }
+
+    #[test]
+    fn test_compare() {
+        let a = parse_clock(r#"{"A": 2}"#).unwrap();
+        let b = parse_clock(r#"{"A": 1, "B": 1}"#).unwrap();
+
+        assert_eq!("Concurrent\nA  2  1  +1\nB  0  1  -1\n", compare(&a, &b));
+    }
+
+    #[test]
+    fn test_check() {
+        let logs = vec![
+            ("p1", "op=write vclock=p1:1\n".to_owned()),
+            ("p2", "op=read vclock=p2:1\n".to_owned()),
+        ];
+
+        assert_eq!("read must follow write: p1:1 and p2:1 are Concurrent",
+                   check("read follows write\n", &logs).unwrap_err());
+        assert_eq!("2 operations checked\n", check("write ordered write\n", &logs).unwrap());
+        assert!(check("read after write\n", &logs).unwrap_err().starts_with("line 1:"));
+
+        let logs = vec![("p1", "op=write vclock=p1:1\nop=read vclock=p1:x\n".to_owned())];
+
+        assert!(check("write ordered write\n", &logs).unwrap_err().starts_with("p1:2: malformed vclock:"));
+    }
+
+    #[test]
+    fn test_merge() {
+        let input = "{\"A\": 2}\n\n[[\"B\", 1]]\n{\"A\": 1}\n";
+
+        assert_eq!("{\"A\":2,\"B\":1}\n", merge(input).unwrap());
+        assert!(merge("{\"A\": 1}\nnope\n").unwrap_err().starts_with("line 2:"));
+    }
+
+    #[test]
+    fn test_frontier() {
+        let input = "{\"A\": 1}\n{\"A\": 2}\n{\"B\": 1}\n{\"B\": 1}\n";
+
+        assert_eq!("{\"A\":2}\n{\"B\":1}\n", frontier(input).unwrap());
+    }
+
+    #[test]
+    fn test_sort() {
+        let input = "\
+{\"op\": 3, \"vc\": {\"A\": 2, \"B\": 1}}
+{\"op\": 2, \"vc\": {\"B\": 1}}
+{\"op\": 1, \"vc\": {\"A\": 1}}
+";
+
+        let sorted = sort(input, "vc").unwrap();
+        let ops: Vec<_> = sorted.lines().map(|l| &l[7..8]).collect();
+
+        assert_eq!(vec!["2", "1", "3"], ops);
+        assert!(sort(input, "clock").is_err());
+    }
+}
@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::time::SystemTime;
+
+use clock::{VectorClock, TemporalRelation};
+use events;
+
+/// Which entry a `BoundedVectorClock` evicts when it grows too large.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum EvictionStrategy {
+    /// The entry with the smallest counter, usually a host that did little
+    /// work or stopped long ago.
+    SmallestCounter,
+    /// The entry updated least recently, using the timestamp recorded with
+    /// each entry as Riak does.
+    OldestTimestamp,
+}
+
+/// A clock that holds at most a fixed number of entries.
+///
+/// Once an entry has been evicted the clock no longer knows its full
+/// history, so comparisons err towards concurrency: a pruned clock is never
+/// reported as older than or equal to another clock, since the evicted
+/// entries might have made it newer. Conflicts may be reported spuriously
+/// but causality is never reported falsely.
+#[derive(Debug, Clone)]
+pub struct BoundedVectorClock<HostType: Hash + Eq> {
+    clock: VectorClock<HostType>,
+    updated: HashMap<HostType, SystemTime>,
+    max_entries: usize,
+    strategy: EvictionStrategy,
+    pruned: bool,
+}
+
+impl<HostType: Clone + Hash + Eq> BoundedVectorClock<HostType> {
+    pub fn new(max_entries: usize, strategy: EvictionStrategy) -> BoundedVectorClock<HostType> {
+        BoundedVectorClock {
+            clock: VectorClock::new(),
+            updated: HashMap::new(),
+            max_entries,
+            strategy,
+            pruned: false,
+        }
+    }
+
+    pub fn clock(&self) -> &VectorClock<HostType> {
+        &self.clock
+    }
+
+    /// Whether any entry has been evicted from this clock or from a clock
+    /// merged into it.
+    pub fn is_pruned(&self) -> bool {
+        self.pruned
+    }
+
+    /// The time the entry for `host` was last updated.
+    pub fn updated_at(&self, host: &HostType) -> Option<SystemTime> {
+        self.updated.get(host).cloned()
+    }
+
+    pub fn increment(&mut self, host: HostType) {
+        self.increment_at(host, SystemTime::now());
+    }
+
+    /// Increment the entry for `host`, recording `at` as its update time.
+    pub fn increment_at(&mut self, host: HostType, at: SystemTime) {
+        self.clock.increment(host.clone());
+        self.updated.insert(host, at);
+        self.evict();
+    }
+
+    /// Merge another bounded clock into this one, keeping the later update
+    /// time of each entry.
+    pub fn merge(&mut self, other: &BoundedVectorClock<HostType>) {
+        self.clock.merge(&other.clock);
+
+        for (host, &at) in &other.updated {
+            let entry = self.updated.entry(host.clone()).or_insert(at);
+
+            if at > *entry {
+                *entry = at;
+            }
+        }
+
+        self.pruned = self.pruned || other.pruned;
+        self.evict();
+    }
+
+    pub fn temporal_relation(&self, other: &BoundedVectorClock<HostType>) -> TemporalRelation {
+        match self.clock.temporal_relation(&other.clock) {
+            TemporalRelation::Equal if self.pruned || other.pruned => TemporalRelation::Concurrent,
+            TemporalRelation::Caused if self.pruned => TemporalRelation::Concurrent,
+            TemporalRelation::EffectOf if other.pruned => TemporalRelation::Concurrent,
+            relation => relation,
+        }
+    }
+
+    fn evict(&mut self) {
+        while self.clock.len() > self.max_entries {
+            let victim = {
+                let updated = &self.updated;
+
+                let victim = match self.strategy {
+                    EvictionStrategy::SmallestCounter => self.clock.iter().min_by_key(|&(_, &n)| n),
+                    EvictionStrategy::OldestTimestamp => self.clock.iter().min_by_key(|&(h, _)| updated.get(h)),
+                };
+
+                match victim {
+                    Some((host, _)) => host.clone(),
+                    None => return,
+                }
+            };
+
+            if let Some(n) = self.clock.remove_host(&victim) {
+                events::evicted(events::host_id(&victim), n);
+            }
+
+            self.updated.remove(&victim);
+            self.pruned = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::{BoundedVectorClock, EvictionStrategy};
+    use clock::TemporalRelation;
+
+    #[test]
+    fn test_evict_smallest_counter() {
+        let mut c = BoundedVectorClock::new(2, EvictionStrategy::SmallestCounter);
+
+        c.increment("A");
+        c.increment("A");
+        c.increment("B");
+        c.increment("B");
+        c.increment("B");
+        assert!(!c.is_pruned());
+
+        c.increment("C");
+
+        assert!(c.is_pruned());
+        assert_eq!(vec![("A", 2), ("B", 3)], {
+            let mut v = c.clock().clone().into_iter().collect::<Vec<_>>();
+            v.sort();
+            v
+        });
+    }
+
+    #[test]
+    fn test_evict_oldest_timestamp() {
+        let at = |secs| UNIX_EPOCH + Duration::from_secs(secs);
+        let mut c = BoundedVectorClock::new(2, EvictionStrategy::OldestTimestamp);
+
+        c.increment_at("A", at(30));
+        c.increment_at("B", at(10));
+        c.increment_at("B", at(20));
+        c.increment_at("C", at(40));
+
+        assert_eq!(None, c.updated_at(&"B"));
+        assert_eq!(0, c.clock().get(&"B"));
+        assert_eq!(Some(at(40)), c.updated_at(&"C"));
+    }
+
+    #[test]
+    fn test_pruned_clocks_are_never_older() {
+        let mut a = BoundedVectorClock::new(1, EvictionStrategy::SmallestCounter);
+        a.increment("A");
+
+        let mut b = BoundedVectorClock::new(1, EvictionStrategy::SmallestCounter);
+        b.increment("B");
+        b.increment("B");
+
+        a.merge(&b);
+
+        // The "A" entry was evicted, so `a` now looks equal to `b` although
+        // it has seen more.
+        assert_eq!(b.clock(), a.clock());
+        assert_eq!(TemporalRelation::Concurrent, a.temporal_relation(&b));
+        assert_eq!(TemporalRelation::Concurrent, a.temporal_relation(&a.clone()));
+        assert_eq!(TemporalRelation::EffectOf, a.temporal_relation(&BoundedVectorClock::new(1, EvictionStrategy::SmallestCounter)));
+    }
+}
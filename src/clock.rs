@@ -1,6 +1,12 @@
-use std::hash::Hash;
+use std::prelude::v1::*;
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
 use std::collections::HashMap;
-use std::collections::hash_map::Entry;
+use std::iter::FromIterator;
+
+use counter::Counter;
+use error::{Error, Result};
+use events;
 
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub enum TemporalRelation {
@@ -10,31 +16,135 @@ pub enum TemporalRelation {
     Concurrent,
 }
 
-#[derive(PartialEq, Eq, Debug)]
-pub struct VectorClock<HostType: Hash + Eq> {
-    entries: HashMap<HostType, u64>,
+impl TemporalRelation {
+    /// The relation seen from the other clock's side.
+    #[cfg(feature = "std")]
+    pub(crate) fn inverse(self) -> TemporalRelation {
+        match self {
+            TemporalRelation::Caused => TemporalRelation::EffectOf,
+            TemporalRelation::EffectOf => TemporalRelation::Caused,
+            other => other,
+        }
+    }
+}
+
+/// A single operation for `VectorClock::apply_events`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ClockEvent<HostType: Hash + Eq, CounterType = u64> {
+    /// Increment the entry for the host.
+    Tick(HostType),
+    /// Raise the entry for the host to at least the given counter.
+    Witness(HostType, CounterType),
+    /// Merge with another clock.
+    Merge(VectorClock<HostType, CounterType>),
+}
+
+/// A vector clock over hosts of type `HostType`.
+///
+/// Counters are `u64` unless another `Counter` type is given, e.g.
+/// `VectorClock::<String, u32>::default()` for a clock with 32-bit counters.
+///
+/// Clocks are kept in a canonical form without zero entries, so a host
+/// that was never seen and one set to zero are indistinguishable, both for
+/// equality and for `Hash`.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct VectorClock<HostType: Hash + Eq, CounterType = u64> {
+    entries: HashMap<HostType, CounterType>,
 }
 
 impl<HostType: Clone + Hash + Eq> VectorClock<HostType> {
     pub fn new() -> VectorClock<HostType> {
-        VectorClock {
-            entries: HashMap::new(),
-        }
+        VectorClock::default()
     }
 
+    #[deprecated(note = "collect an iterator of entries into a `VectorClock` instead")]
+    pub fn from_vec(v: Vec<(HostType, u64)>) -> VectorClock<HostType> {
+        v.into_iter().collect()
+    }
+}
+
+impl<HostType: Clone + Hash + Eq, CounterType: Counter> VectorClock<HostType, CounterType> {
     pub fn incremented(&self, host: HostType) -> Self {
-        let mut entries = self.entries.clone();
-
-        match entries.entry(host) {
-            Entry::Vacant(e) => { e.insert(1); },
-            Entry::Occupied(mut e) => {
-                let v = *e.get();
-                e.insert(v + 1);
-            },
-        };
+        let mut clock = self.clone();
+        clock.increment(host);
+        clock
+    }
 
-        VectorClock {
-            entries: entries,
+    /// Increment the entry for `host` in place.
+    ///
+    /// Panics if the counter would overflow; see `checked_increment` and
+    /// `saturating_increment` for alternatives.
+    pub fn increment(&mut self, host: HostType) {
+        self.checked_increment(host).expect("vector clock counter overflow");
+    }
+
+    /// Increment the entry for `host` in place, or fail with
+    /// `Error::Overflow` and leave the clock unchanged if the counter is
+    /// already at its maximum.
+    pub fn checked_increment(&mut self, host: HostType) -> Result<()> {
+        let n = self.entries.entry(host).or_insert(CounterType::ZERO);
+        *n = n.checked_add(CounterType::ONE).ok_or(Error::Overflow)?;
+        Ok(())
+    }
+
+    /// Increment the entry for `host` in place, leaving it unchanged if it
+    /// is already at its maximum.
+    ///
+    /// A saturated entry no longer advances, so later events from `host`
+    /// will compare as equal to earlier ones.
+    pub fn saturating_increment(&mut self, host: HostType) {
+        if events::enabled() && self.get(&host).checked_add(CounterType::ONE).is_none() {
+            events::saturated(events::host_id(&host), self.get(&host).to_u64());
+        }
+
+        let n = self.entries.entry(host).or_insert(CounterType::ZERO);
+        *n = n.saturating_add(CounterType::ONE);
+    }
+
+    /// Set the entry for `host` to `n`, removing it if `n` is zero.
+    pub fn set(&mut self, host: HostType, n: CounterType) {
+        if n == CounterType::ZERO {
+            self.entries.remove(&host);
+        }
+        else {
+            self.entries.insert(host, n);
+        }
+    }
+
+    /// Remove the entry for `host`, returning its counter if there was one.
+    ///
+    /// Removing entries loses causal information: afterwards the clock may
+    /// compare as older than clocks it actually happened after.
+    pub fn remove_host(&mut self, host: &HostType) -> Option<CounterType> {
+        let removed = self.entries.remove(host);
+
+        if let Some(n) = removed {
+            if events::enabled() {
+                events::pruned(&[(events::host_id(host), n.to_u64())]);
+            }
+        }
+
+        removed
+    }
+
+    /// Keep only the entries for which `f` returns `true`, with the same
+    /// caveat as `remove_host`.
+    pub fn retain<F: FnMut(&HostType, CounterType) -> bool>(&mut self, mut f: F) {
+        let tracing = events::enabled();
+        let mut removed = Vec::new();
+
+        self.entries.retain(|host, n| {
+            let keep = f(host, *n);
+
+            if !keep && tracing {
+                removed.push((events::host_id(host), n.to_u64()));
+            }
+
+            keep
+        });
+
+        if !removed.is_empty() {
+            events::pruned(&removed);
         }
     }
 
@@ -57,7 +167,7 @@ impl<HostType: Clone + Hash + Eq> VectorClock<HostType> {
         let mut has_smaller = false;
 
         for (host, &self_n) in self.entries.iter() {
-            let other_n = *other.entries.get(host).unwrap_or(&0);
+            let other_n = other.get(host);
 
             if self_n > other_n {
                 return false;
@@ -67,7 +177,7 @@ impl<HostType: Clone + Hash + Eq> VectorClock<HostType> {
         }
 
         for (host, &other_n) in other.entries.iter() {
-            let self_n = *self.entries.get(host).unwrap_or(&0);
+            let self_n = self.get(host);
 
             if self_n > other_n {
                 return false;
@@ -80,40 +190,301 @@ impl<HostType: Clone + Hash + Eq> VectorClock<HostType> {
     }
 
     pub fn merge_with(&self, other: &Self) -> Self {
-        let mut entries = self.entries.clone();
+        let mut clock = self.clone();
+        clock.merge(other);
+        clock
+    }
+
+    /// Merge `other` into this clock in place. Host keys are only cloned
+    /// for hosts this clock has not seen yet.
+    pub fn merge(&mut self, other: &Self) {
+        if events::enabled() {
+            self.trace_merge(other);
+        }
 
         for (host, &other_n) in other.entries.iter() {
-            match entries.entry(host.clone()) {
-                Entry::Vacant(e) => { e.insert(other_n); },
-                Entry::Occupied(mut e) => {
-                    let self_n = *e.get();
+            match self.entries.get_mut(host) {
+                Some(self_n) => {
+                    if other_n > *self_n {
+                        *self_n = other_n;
+                    }
+                },
+                None => { self.entries.insert(host.clone(), other_n); },
+            }
+        }
+    }
+
+    /// The greatest lower bound of the two clocks: the per-host minimum,
+    /// i.e. the history the two clocks have in common. For two versions of
+    /// a value this is the clock of their latest common ancestor.
+    pub fn intersect_with(&self, other: &Self) -> Self {
+        let mut clock = self.clone();
+        clock.intersect(other);
+        clock
+    }
 
-                    if other_n > self_n {
-                        e.insert(other_n);
+    /// Intersect `other` into this clock in place.
+    pub fn intersect(&mut self, other: &Self) {
+        self.entries.retain(|host, n| {
+            let other_n = other.get(host);
+
+            if other_n < *n {
+                *n = other_n;
+            }
+
+            *n != CounterType::ZERO
+        });
+    }
+
+    /// Whether this clock has seen everything `other` has, including when
+    /// the two are equal.
+    pub fn dominates(&self, other: &Self) -> bool {
+        self >= other
+    }
+
+    /// Whether this clock has seen everything `other` has, and more.
+    pub fn strictly_dominates(&self, other: &Self) -> bool {
+        self.temporal_relation(other) == TemporalRelation::EffectOf
+    }
+
+    pub fn concurrent_with(&self, other: &Self) -> bool {
+        self.temporal_relation(other) == TemporalRelation::Concurrent
+    }
+
+    /// Apply a batch of events in order, updating the clock in place.
+    pub fn apply_events<I>(&mut self, events: I)
+        where I: IntoIterator<Item=ClockEvent<HostType, CounterType>>
+    {
+        for event in events {
+            match event {
+                ClockEvent::Tick(host) => self.increment(host),
+                ClockEvent::Witness(host, n) => self.witness(host, n),
+                ClockEvent::Merge(other) => {
+                    if events::enabled() {
+                        self.trace_merge(&other);
+                    }
+
+                    for (host, other_n) in other.entries {
+                        self.witness(host, other_n);
                     }
-                }
+                },
             }
         }
+    }
 
-        VectorClock {
-            entries: entries,
+    fn witness(&mut self, host: HostType, n: CounterType) {
+        if n == CounterType::ZERO {
+            return;
+        }
+
+        let e = self.entries.entry(host).or_insert(CounterType::ZERO);
+
+        if n > *e {
+            *e = n;
         }
     }
 
-    pub fn to_vec(&self) -> Vec<(HostType, u64)> {
-        self.entries.iter().map(| (host, &n) | (host.clone(), n) ).collect()
+    fn trace_merge(&self, other: &Self) {
+        let ahead = |a: &Self, b: &Self| -> Vec<(u64, u64, u64)> {
+            a.entries.iter()
+                .filter(|&(h, &n)| n > b.get(h))
+                .map(|(h, &n)| (events::host_id(h), n.to_u64(), b.get(h).to_u64()))
+                .collect()
+        };
+
+        let local_ahead = ahead(self, other);
+        let remote_ahead: Vec<_> = ahead(other, self).into_iter()
+            .map(|(h, remote, local)| (h, local, remote))
+            .collect();
+
+        if !local_ahead.is_empty() && !remote_ahead.is_empty() {
+            events::concurrent_merge(&local_ahead, &remote_ahead);
+        }
     }
 
-    pub fn from_vec(v: Vec<(HostType, u64)>) -> VectorClock<HostType> {
+    /// The number of events seen by exactly one of the two clocks.
+    pub fn distance(&self, other: &Self) -> u64 {
+        let mut d = 0;
+
+        for (host, &self_n) in self.entries.iter() {
+            d += self_n.to_u64().abs_diff(other.get(host).to_u64());
+        }
+
+        for (host, &other_n) in other.entries.iter() {
+            if !self.entries.contains_key(host) {
+                d += other_n.to_u64();
+            }
+        }
+
+        d
+    }
+
+    /// The entries of the clock, in no particular order.
+    pub fn iter(&self) -> Iter<'_, HostType, CounterType> {
+        Iter(self.entries.iter())
+    }
+
+    /// The number of hosts with an entry in the clock.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The counter for `host`, or zero if the clock has no entry for it.
+    pub fn get(&self, host: &HostType) -> CounterType {
+        *self.entries.get(host).unwrap_or(&CounterType::ZERO)
+    }
+
+    pub fn contains_host(&self, host: &HostType) -> bool {
+        self.entries.contains_key(host)
+    }
+
+    #[deprecated(note = "use `iter` or `into_iter` instead")]
+    pub fn to_vec(&self) -> Vec<(HostType, CounterType)> {
+        self.iter().map(| (host, &n) | (host.clone(), n) ).collect()
+    }
+}
+
+/// A borrowing iterator over the entries of a `VectorClock`.
+#[derive(Debug, Clone)]
+pub struct Iter<'a, HostType: 'a, CounterType: 'a>(::std::collections::hash_map::Iter<'a, HostType, CounterType>);
+
+impl<'a, HostType, CounterType> Iterator for Iter<'a, HostType, CounterType> {
+    type Item = (&'a HostType, &'a CounterType);
+
+    fn next(&mut self) -> Option<(&'a HostType, &'a CounterType)> {
+        self.0.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<'a, HostType, CounterType> ExactSizeIterator for Iter<'a, HostType, CounterType> {}
+
+/// An owning iterator over the entries of a `VectorClock`.
+#[derive(Debug)]
+pub struct IntoIter<HostType, CounterType>(::std::collections::hash_map::IntoIter<HostType, CounterType>);
+
+impl<HostType, CounterType> Iterator for IntoIter<HostType, CounterType> {
+    type Item = (HostType, CounterType);
+
+    fn next(&mut self) -> Option<(HostType, CounterType)> {
+        self.0.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<HostType, CounterType> ExactSizeIterator for IntoIter<HostType, CounterType> {}
+
+impl<HostType: Hash + Eq, CounterType> IntoIterator for VectorClock<HostType, CounterType> {
+    type Item = (HostType, CounterType);
+    type IntoIter = IntoIter<HostType, CounterType>;
+
+    fn into_iter(self) -> IntoIter<HostType, CounterType> {
+        IntoIter(self.entries.into_iter())
+    }
+}
+
+impl<'a, HostType: Clone + Hash + Eq, CounterType: Counter> IntoIterator for &'a VectorClock<HostType, CounterType> {
+    type Item = (&'a HostType, &'a CounterType);
+    type IntoIter = Iter<'a, HostType, CounterType>;
+
+    fn into_iter(self) -> Iter<'a, HostType, CounterType> {
+        self.iter()
+    }
+}
+
+/// Raises each entry to the given counter, as if witnessing it. An entry
+/// that appears several times ends up with the largest of its counters.
+impl<HostType: Clone + Hash + Eq, CounterType: Counter> Extend<(HostType, CounterType)> for VectorClock<HostType, CounterType> {
+    fn extend<I: IntoIterator<Item=(HostType, CounterType)>>(&mut self, iter: I) {
+        for (host, n) in iter {
+            self.witness(host, n);
+        }
+    }
+}
+
+/// Builds a clock from its entries, as with `Extend`.
+impl<HostType: Clone + Hash + Eq, CounterType: Counter> FromIterator<(HostType, CounterType)> for VectorClock<HostType, CounterType> {
+    fn from_iter<I: IntoIterator<Item=(HostType, CounterType)>>(iter: I) -> VectorClock<HostType, CounterType> {
+        let mut clock = VectorClock::default();
+        clock.extend(iter);
+        clock
+    }
+}
+
+/// Hashes the entries independently of their order, consistently with
+/// `Eq`.
+impl<HostType: Hash + Eq, CounterType: Hash> Hash for VectorClock<HostType, CounterType> {
+    fn hash<StateType: Hasher>(&self, state: &mut StateType) {
+        let mut sum = 0u64;
+
+        for entry in self.entries.iter() {
+            let mut hasher = EntryHasher::new();
+            entry.hash(&mut hasher);
+            sum = sum.wrapping_add(hasher.finish());
+        }
+
+        state.write_usize(self.entries.len());
+        state.write_u64(sum);
+    }
+}
+
+/// FNV-1a, used to hash single entries before combining them.
+pub(crate) struct EntryHasher(u64);
+
+impl EntryHasher {
+    pub(crate) fn new() -> EntryHasher {
+        EntryHasher(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for EntryHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 = (self.0 ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+/// Clocks are ordered by causality: `a < b` if `a` happened before `b`,
+/// and concurrent clocks are incomparable.
+impl<HostType: Clone + Hash + Eq, CounterType: Counter> PartialOrd for VectorClock<HostType, CounterType> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match self.temporal_relation(other) {
+            TemporalRelation::Equal => Some(Ordering::Equal),
+            TemporalRelation::Caused => Some(Ordering::Less),
+            TemporalRelation::EffectOf => Some(Ordering::Greater),
+            TemporalRelation::Concurrent => None,
+        }
+    }
+}
+
+impl<HostType: Clone + Hash + Eq, CounterType> Default for VectorClock<HostType, CounterType> {
+    fn default() -> VectorClock<HostType, CounterType> {
         VectorClock {
-            entries: v.iter().cloned().collect(),
+            entries: HashMap::new(),
         }
     }
 }
 
 #[cfg(test)]
 mod test {
-    use super::{VectorClock, TemporalRelation};
+    use std::prelude::v1::*;
+    use super::{VectorClock, TemporalRelation, ClockEvent};
+    use error::Error;
 
     type StrVectorClock = VectorClock<&'static str>;
 
@@ -167,6 +538,105 @@ mod test {
     }
 
     #[test]
+    fn test_in_place_mutation() {
+        let mut c = StrVectorClock::new();
+
+        c.increment("A");
+        c.increment("A");
+        c.merge(&StrVectorClock::new().incremented("A").incremented("B"));
+
+        assert_eq!(StrVectorClock::new().incremented("A").incremented("A").incremented("B"), c);
+
+        c.set("B", 5);
+        c.set("A", 0);
+
+        assert_eq!(vec![("B", 5)], c.clone().into_iter().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_pruning() {
+        let mut c = StrVectorClock::new().incremented("A").incremented("B").incremented("B").incremented("C");
+
+        assert_eq!(Some(1), c.remove_host(&"A"));
+        assert_eq!(None, c.remove_host(&"A"));
+
+        c.retain(|_, n| n > 1);
+
+        assert_eq!(vec![("B", 2)], c.clone().into_iter().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_overflow() {
+        let mut c = VectorClock::<&str, u8>::default();
+        c.set("A", 254);
+
+        assert_eq!(Ok(()), c.checked_increment("A"));
+        assert_eq!(Err(Error::Overflow), c.checked_increment("A"));
+        assert_eq!(255, c.get(&"A"));
+
+        c.saturating_increment("A");
+        c.saturating_increment("B");
+        assert_eq!(vec![("A", 255), ("B", 1)], {
+            let mut v = c.clone().into_iter().collect::<Vec<_>>();
+            v.sort();
+            v
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "overflow")]
+    fn test_increment_overflow_panics() {
+        let mut c = VectorClock::<&str, u8>::default();
+        c.set("A", 255);
+        c.increment("A");
+    }
+
+    #[test]
+    fn test_partial_ord() {
+        let base = StrVectorClock::new().incremented("A");
+        let a = base.incremented("A");
+        let b = base.incremented("B");
+
+        assert!(base < a);
+        assert!(base <= base);
+        assert!(a > base);
+        assert_eq!(None, a.partial_cmp(&b));
+        assert_eq!(None, b.partial_cmp(&a));
+    }
+
+    #[test]
+    fn test_apply_events() {
+        let mut c = StrVectorClock::new();
+        let other = StrVectorClock::new().incremented("C").incremented("A");
+
+        c.apply_events(vec![
+            ClockEvent::Tick("A"),
+            ClockEvent::Tick("A"),
+            ClockEvent::Witness("B", 3),
+            ClockEvent::Witness("A", 1),
+            ClockEvent::Merge(other),
+        ]);
+
+        let mut v = c.clone().into_iter().collect::<Vec<_>>();
+        v.sort();
+
+        assert_eq!(vec![("A", 2), ("B", 3), ("C", 1)], v);
+    }
+
+    #[test]
+    fn test_distance() {
+        let base = StrVectorClock::new();
+        let c1 = base.incremented("A").incremented("A");
+        let c2 = base.incremented("B");
+
+        assert_eq!(0, c1.distance(&c1));
+        assert_eq!(3, c1.distance(&c2));
+        assert_eq!(3, c2.distance(&c1));
+        assert_eq!(1, c1.distance(&c1.merge_with(&c2)));
+    }
+
+    #[test]
+    #[allow(deprecated)]
     fn test_to_vec() {
         let c = StrVectorClock::new();
         let e : Vec<(&'static str, u64)> = vec![];
@@ -183,4 +653,65 @@ mod test {
 
         assert_eq!(vec![("A", 1), ("B", 1)], v);
     }
+
+    #[test]
+    fn test_intersect_and_dominance() {
+        let base = StrVectorClock::new().incremented("A").incremented("B");
+        let left = base.incremented("A").incremented("C");
+        let right = base.incremented("B").incremented("B");
+
+        assert_eq!(base, left.intersect_with(&right));
+        assert_eq!(base, right.intersect_with(&left));
+        assert_eq!(left, left.intersect_with(&left.merge_with(&right)));
+
+        assert!(left.dominates(&base) && left.strictly_dominates(&base));
+        assert!(base.dominates(&base) && !base.strictly_dominates(&base));
+        assert!(!base.dominates(&left));
+        assert!(left.concurrent_with(&right) && !left.concurrent_with(&base));
+    }
+
+    #[test]
+    fn test_canonical_form_and_hash() {
+        use std::collections::HashSet;
+
+        let explicit: StrVectorClock = vec![("A", 1), ("B", 0)].into_iter().collect();
+        let implicit = StrVectorClock::new().incremented("A");
+
+        assert_eq!(implicit, explicit);
+        assert!(!explicit.contains_host(&"B"));
+
+        let mut witnessed = implicit.clone();
+        witnessed.apply_events(vec![ClockEvent::Witness("C", 0)]);
+        assert_eq!(implicit, witnessed);
+
+        let mut set = HashSet::new();
+        set.insert(explicit);
+        set.insert(implicit.incremented("B"));
+
+        assert!(set.contains(&implicit));
+        assert!(set.contains(&vec![("B", 1), ("A", 1)].into_iter().collect::<StrVectorClock>()));
+        assert!(!set.contains(&implicit.incremented("A")));
+    }
+
+    #[test]
+    fn test_accessors_and_iterators() {
+        let mut c: StrVectorClock = vec![("A", 2), ("B", 1)].into_iter().collect();
+
+        assert_eq!(2, c.len());
+        assert!(!c.is_empty() && StrVectorClock::new().is_empty());
+        assert_eq!(2, c.get(&"A"));
+        assert_eq!(0, c.get(&"C"));
+        assert!(c.contains_host(&"B") && !c.contains_host(&"C"));
+
+        c.extend(vec![("A", 1), ("C", 4)]);
+        assert_eq!(7, c.iter().map(|(_, &n)| n).sum::<u64>());
+        assert_eq!(3, (&c).into_iter().len());
+
+        let mut v: Vec<_> = c.into_iter().collect();
+        v.sort();
+        assert_eq!(vec![("A", 2), ("B", 1), ("C", 4)], v);
+
+        let merged: StrVectorClock = vec![("A", 1), ("A", 3)].into_iter().collect();
+        assert_eq!(3, merged.get(&"A"));
+    }
 }
@@ -0,0 +1,171 @@
+use std::hash::Hash;
+
+use clock::{VectorClock, TemporalRelation};
+
+/// Identifies a clock inside a `ClockSet`. IDs of removed clocks are reused.
+pub type ClockId = usize;
+
+/// A collection of clocks that keeps the pairwise relations between its
+/// members up to date.
+///
+/// Inserting a clock compares it once against every member, and removing
+/// one costs nothing beyond bookkeeping, so relation and frontier queries
+/// never recompute comparisons.
+#[derive(Debug, Clone)]
+pub struct ClockSet<HostType: Hash + Eq> {
+    clocks: Vec<Option<VectorClock<HostType>>>,
+    relations: Vec<Vec<TemporalRelation>>,
+    // For each slot, the number of members that strictly dominate it.
+    dominated_by: Vec<usize>,
+    free: Vec<ClockId>,
+    len: usize,
+}
+
+impl<HostType: Clone + Hash + Eq> ClockSet<HostType> {
+    pub fn new() -> ClockSet<HostType> {
+        ClockSet {
+            clocks: Vec::new(),
+            relations: Vec::new(),
+            dominated_by: Vec::new(),
+            free: Vec::new(),
+            len: 0,
+        }
+    }
+
+    pub fn insert(&mut self, clock: VectorClock<HostType>) -> ClockId {
+        let id = match self.free.pop() {
+            Some(id) => id,
+            None => {
+                let id = self.clocks.len();
+                self.clocks.push(None);
+                self.dominated_by.push(0);
+                self.relations.push(Vec::new());
+                for row in self.relations.iter_mut() {
+                    row.resize(id + 1, TemporalRelation::Concurrent);
+                }
+                id
+            },
+        };
+
+        self.dominated_by[id] = 0;
+
+        for other in 0..self.clocks.len() {
+            let relation = match self.clocks[other] {
+                Some(ref c) => clock.temporal_relation(c),
+                None => continue,
+            };
+
+            self.relations[id][other] = relation;
+            self.relations[other][id] = relation.inverse();
+
+            match relation {
+                TemporalRelation::Caused => self.dominated_by[id] += 1,
+                TemporalRelation::EffectOf => self.dominated_by[other] += 1,
+                _ => {},
+            }
+        }
+
+        self.relations[id][id] = TemporalRelation::Equal;
+        self.clocks[id] = Some(clock);
+        self.len += 1;
+
+        id
+    }
+
+    pub fn remove(&mut self, id: ClockId) -> Option<VectorClock<HostType>> {
+        let clock = self.clocks.get_mut(id).and_then(|c| c.take())?;
+
+        for other in 0..self.clocks.len() {
+            if self.clocks[other].is_some() && self.relations[id][other] == TemporalRelation::EffectOf {
+                self.dominated_by[other] -= 1;
+            }
+        }
+
+        self.free.push(id);
+        self.len -= 1;
+
+        Some(clock)
+    }
+
+    pub fn get(&self, id: ClockId) -> Option<&VectorClock<HostType>> {
+        self.clocks.get(id).and_then(|c| c.as_ref())
+    }
+
+    /// The relation of clock `a` to clock `b`, as by `temporal_relation`.
+    pub fn relation(&self, a: ClockId, b: ClockId) -> Option<TemporalRelation> {
+        match (self.get(a), self.get(b)) {
+            (Some(_), Some(_)) => Some(self.relations[a][b]),
+            _ => None,
+        }
+    }
+
+    /// The members not strictly dominated by any other member.
+    pub fn frontier(&self) -> Vec<ClockId> {
+        self.ids().filter(|&id| self.dominated_by[id] == 0).collect()
+    }
+
+    /// Whether `id` is strictly dominated by some other member.
+    pub fn is_dominated(&self, id: ClockId) -> bool {
+        self.get(id).is_some() && self.dominated_by[id] > 0
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item=ClockId> + '_ {
+        (0..self.clocks.len()).filter(move |&id| self.clocks[id].is_some())
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl<HostType: Clone + Hash + Eq> Default for ClockSet<HostType> {
+    fn default() -> ClockSet<HostType> {
+        ClockSet::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ClockSet;
+    use clock::{VectorClock, TemporalRelation};
+
+    #[test]
+    fn test_relations_and_frontier() {
+        let base = VectorClock::new().incremented("A");
+        let mut set = ClockSet::new();
+
+        let a = set.insert(base.clone());
+        let b = set.insert(base.incremented("B"));
+        let c = set.insert(base.incremented("C"));
+
+        assert_eq!(Some(TemporalRelation::Caused), set.relation(a, b));
+        assert_eq!(Some(TemporalRelation::EffectOf), set.relation(b, a));
+        assert_eq!(Some(TemporalRelation::Concurrent), set.relation(b, c));
+        assert_eq!(vec![b, c], set.frontier());
+        assert!(set.is_dominated(a));
+    }
+
+    #[test]
+    fn test_remove_updates_frontier() {
+        let base = VectorClock::new().incremented("A");
+        let mut set = ClockSet::new();
+
+        let a = set.insert(base.clone());
+        let b = set.insert(base.incremented("B"));
+
+        assert_eq!(vec![b], set.frontier());
+        assert_eq!(Some(base.incremented("B")), set.remove(b));
+        assert_eq!(vec![a], set.frontier());
+        assert_eq!(None, set.relation(a, b));
+
+        let c = set.insert(base.incremented("C"));
+
+        assert_eq!(b, c);
+        assert_eq!(2, set.len());
+        assert_eq!(vec![c], set.frontier());
+    }
+}
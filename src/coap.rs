@@ -0,0 +1,84 @@
+use std::fmt::Display;
+use std::hash::Hash;
+use std::str::FromStr;
+
+#[cfg(feature = "coap-lite")]
+use coap_lite::{CoapOption, Packet};
+
+use clock::VectorClock;
+use error::Result;
+
+/// The CoAP option number used for clocks, from the experimental range.
+///
+/// It is elective and safe to forward, so devices and proxies that don't
+/// know it pass it through or ignore it.
+pub const CLOCK_OPTION: u16 = 65000;
+
+/// Encode a clock as a CoAP option value, using the packed encoding.
+pub fn encode_option<HostType>(clock: &VectorClock<HostType>) -> Vec<u8>
+    where HostType: Clone + Hash + Eq + Display
+{
+    clock.to_packed()
+}
+
+pub fn decode_option<HostType>(value: &[u8]) -> Result<VectorClock<HostType>>
+    where HostType: Clone + Hash + Eq + FromStr
+{
+    VectorClock::from_packed(value)
+}
+
+/// Set the clock option on a coap-lite packet, replacing any earlier one.
+#[cfg(feature = "coap-lite")]
+pub fn set_clock_option<HostType>(packet: &mut Packet, clock: &VectorClock<HostType>)
+    where HostType: Clone + Hash + Eq + Display
+{
+    packet.clear_option(CoapOption::Unknown(CLOCK_OPTION));
+    packet.add_option(CoapOption::Unknown(CLOCK_OPTION), encode_option(clock));
+}
+
+/// The clock carried by a coap-lite packet, if it has one.
+#[cfg(feature = "coap-lite")]
+pub fn clock_option<HostType>(packet: &Packet) -> Option<Result<VectorClock<HostType>>>
+    where HostType: Clone + Hash + Eq + FromStr
+{
+    packet.get_first_option(CoapOption::Unknown(CLOCK_OPTION)).map(|v| decode_option(v))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{encode_option, decode_option, CLOCK_OPTION};
+    use clock::VectorClock;
+
+    #[test]
+    fn test_option_number_is_elective_and_safe() {
+        assert_eq!(0, CLOCK_OPTION & 1);
+        assert_eq!(0, CLOCK_OPTION & 2);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let clock = VectorClock::new().incremented("n1".to_owned()).incremented("n2".to_owned());
+        let value = encode_option(&clock);
+
+        assert_eq!(1 + 6 + 1 + 2, value.len());
+        assert_eq!(clock, decode_option(&value).unwrap());
+    }
+
+    #[cfg(feature = "coap-lite")]
+    #[test]
+    fn test_packet_option() {
+        use coap_lite::Packet;
+        use super::{set_clock_option, clock_option};
+
+        let mut packet = Packet::new();
+        assert!(clock_option::<String>(&packet).is_none());
+
+        set_clock_option(&mut packet, &VectorClock::new().incremented("a".to_owned()));
+        let clock = VectorClock::new().incremented("b".to_owned());
+        set_clock_option(&mut packet, &clock);
+
+        let packet = Packet::from_bytes(&packet.to_bytes().unwrap()).unwrap();
+
+        assert_eq!(clock, clock_option(&packet).unwrap().unwrap());
+    }
+}
@@ -0,0 +1,262 @@
+use std::prelude::v1::*;
+use std::cmp::Ordering;
+use std::hash::Hash;
+
+use smallvec::SmallVec;
+
+use clock::{VectorClock, TemporalRelation};
+use error::{Error, Result};
+
+/// A vector clock stored as a vector of entries sorted by host, kept inline
+/// for up to eight hosts.
+///
+/// Comparisons and merges are single passes over both sorted vectors, with
+/// no hashing and, for small clocks, no heap allocation. In the `compact`
+/// benchmark comparisons are several times faster than `VectorClock`'s at
+/// every size up to 128 hosts, while merges break even at around eight
+/// hosts. Looking up or adding a single host costs a binary search and a
+/// shift, so `VectorClock` remains the better choice for large clocks that
+/// are mostly updated one entry at a time.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct CompactVectorClock<HostType> {
+    entries: SmallVec<[(HostType, u64); 8]>,
+}
+
+impl<HostType: Clone + Ord> CompactVectorClock<HostType> {
+    pub fn new() -> CompactVectorClock<HostType> {
+        CompactVectorClock {
+            entries: SmallVec::new(),
+        }
+    }
+
+    pub fn incremented(&self, host: HostType) -> Self {
+        let mut clock = self.clone();
+        clock.increment(host);
+        clock
+    }
+
+    /// Increment the entry for `host` in place.
+    ///
+    /// Panics if the counter would overflow; see `checked_increment`.
+    pub fn increment(&mut self, host: HostType) {
+        self.checked_increment(host).expect("vector clock counter overflow");
+    }
+
+    /// Increment the entry for `host` in place, or fail with
+    /// `Error::Overflow` and leave the clock unchanged if the counter is
+    /// already at its maximum.
+    pub fn checked_increment(&mut self, host: HostType) -> Result<()> {
+        match self.position(&host) {
+            Ok(i) => self.entries[i].1 = self.entries[i].1.checked_add(1).ok_or(Error::Overflow)?,
+            Err(i) => self.entries.insert(i, (host, 1)),
+        }
+
+        Ok(())
+    }
+
+    /// Set the entry for `host` to `n`, removing it if `n` is zero.
+    pub fn set(&mut self, host: HostType, n: u64) {
+        match (self.position(&host), n) {
+            (Ok(i), 0) => { self.entries.remove(i); },
+            (Ok(i), n) => self.entries[i].1 = n,
+            (Err(_), 0) => {},
+            (Err(i), n) => self.entries.insert(i, (host, n)),
+        }
+    }
+
+    pub fn get(&self, host: &HostType) -> u64 {
+        self.position(host).map(|i| self.entries[i].1).unwrap_or(0)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The entries in ascending host order.
+    pub fn iter(&self) -> ::std::slice::Iter<'_, (HostType, u64)> {
+        self.entries.iter()
+    }
+
+    pub fn temporal_relation(&self, other: &Self) -> TemporalRelation {
+        let (mut self_ahead, mut other_ahead) = (false, false);
+        let (mut a, mut b) = (self.entries.iter().peekable(), other.entries.iter().peekable());
+
+        loop {
+            let order = match (a.peek(), b.peek()) {
+                (Some(x), Some(y)) => x.0.cmp(&y.0),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => break,
+            };
+
+            match order {
+                Ordering::Less => { a.next(); self_ahead = true; },
+                Ordering::Greater => { b.next(); other_ahead = true; },
+                Ordering::Equal => {
+                    let (x, y) = (a.next().unwrap().1, b.next().unwrap().1);
+                    self_ahead = self_ahead || x > y;
+                    other_ahead = other_ahead || y > x;
+                },
+            }
+
+            if self_ahead && other_ahead {
+                break;
+            }
+        }
+
+        match (self_ahead, other_ahead) {
+            (false, false) => TemporalRelation::Equal,
+            (false, true) => TemporalRelation::Caused,
+            (true, false) => TemporalRelation::EffectOf,
+            (true, true) => TemporalRelation::Concurrent,
+        }
+    }
+
+    pub fn merge_with(&self, other: &Self) -> Self {
+        let mut clock = self.clone();
+        clock.merge(other);
+        clock
+    }
+
+    pub fn merge(&mut self, other: &Self) {
+        if other.entries.is_empty() {
+            return;
+        }
+
+        let mut merged = SmallVec::with_capacity(self.entries.len().max(other.entries.len()));
+
+        {
+            let (mut a, mut b) = (self.entries.drain(..).peekable(), other.entries.iter().peekable());
+
+            loop {
+                let order = match (a.peek(), b.peek()) {
+                    (Some(x), Some(y)) => x.0.cmp(&y.0),
+                    (Some(_), None) => Ordering::Less,
+                    (None, Some(_)) => Ordering::Greater,
+                    (None, None) => break,
+                };
+
+                match order {
+                    Ordering::Less => merged.push(a.next().unwrap()),
+                    Ordering::Greater => merged.push(b.next().unwrap().clone()),
+                    Ordering::Equal => {
+                        let (host, x) = a.next().unwrap();
+                        let y = b.next().unwrap().1;
+                        merged.push((host, x.max(y)));
+                    },
+                }
+            }
+        }
+
+        self.entries = merged;
+    }
+
+    fn position(&self, host: &HostType) -> ::std::result::Result<usize, usize> {
+        self.entries.binary_search_by(|entry| entry.0.cmp(host))
+    }
+}
+
+impl<HostType: Clone + Ord + Hash> CompactVectorClock<HostType> {
+    pub fn to_vector_clock(&self) -> VectorClock<HostType> {
+        self.entries.iter().cloned().collect()
+    }
+}
+
+impl<'a, HostType: Clone + Ord + Hash> From<&'a VectorClock<HostType>> for CompactVectorClock<HostType> {
+    fn from(clock: &'a VectorClock<HostType>) -> CompactVectorClock<HostType> {
+        let mut entries: SmallVec<[(HostType, u64); 8]> = clock.iter()
+            .filter(|&(_, &n)| n > 0)
+            .map(|(host, &n)| (host.clone(), n))
+            .collect();
+
+        entries.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+
+        CompactVectorClock {
+            entries,
+        }
+    }
+}
+
+/// Ordered by causality, as for `VectorClock`.
+impl<HostType: Clone + Ord> PartialOrd for CompactVectorClock<HostType> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match self.temporal_relation(other) {
+            TemporalRelation::Equal => Some(Ordering::Equal),
+            TemporalRelation::Caused => Some(Ordering::Less),
+            TemporalRelation::EffectOf => Some(Ordering::Greater),
+            TemporalRelation::Concurrent => None,
+        }
+    }
+}
+
+impl<HostType: Clone + Ord> Default for CompactVectorClock<HostType> {
+    fn default() -> CompactVectorClock<HostType> {
+        CompactVectorClock::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::prelude::v1::*;
+    use super::CompactVectorClock;
+    use clock::{VectorClock, TemporalRelation};
+    use error::Error;
+
+    #[test]
+    fn test_matches_vector_clock() {
+        let hosts = ["a", "b", "c", "d", "e", "f", "g", "h", "i", "j"];
+        let mut clocks = vec![VectorClock::new()];
+
+        for (i, host) in hosts.iter().enumerate() {
+            let next = clocks[i * 7 % clocks.len()].incremented(*host);
+            let merged = next.merge_with(&clocks[i * 3 % clocks.len()]);
+            clocks.push(next);
+            clocks.push(merged);
+        }
+
+        for a in &clocks {
+            for b in &clocks {
+                let (ca, cb) = (CompactVectorClock::from(a), CompactVectorClock::from(b));
+
+                assert_eq!(a.temporal_relation(b), ca.temporal_relation(&cb));
+                assert_eq!(a.merge_with(b), ca.merge_with(&cb).to_vector_clock());
+            }
+        }
+    }
+
+    #[test]
+    fn test_set_and_increment() {
+        let mut c = CompactVectorClock::new();
+        c.increment("b");
+        c.increment("a");
+        c.increment("b");
+        c.set("c", 5);
+        c.set("a", 0);
+
+        assert_eq!(vec![("b", 2), ("c", 5)], c.iter().cloned().collect::<Vec<_>>());
+        assert_eq!(TemporalRelation::Caused, c.temporal_relation(&c.incremented("a")));
+        assert!(c < c.incremented("a"));
+    }
+
+    #[test]
+    fn test_overflow() {
+        let mut c = CompactVectorClock::new();
+        c.set("a", u64::MAX);
+
+        assert_eq!(Err(Error::Overflow), c.checked_increment("a"));
+        assert_eq!(u64::MAX, c.get(&"a"));
+        assert_eq!(Ok(()), c.checked_increment("b"));
+    }
+
+    #[test]
+    #[should_panic(expected = "vector clock counter overflow")]
+    fn test_increment_overflow_panics() {
+        let mut c = CompactVectorClock::new();
+        c.set("a", u64::MAX);
+        c.increment("a");
+    }
+}
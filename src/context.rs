@@ -0,0 +1,137 @@
+//! Carry a causal context across HTTP or RPC calls as an opaque string.
+//!
+//! A context is the clock's canonical wire encoding (see
+//! `VectorClock::encode`) in unpadded URL-safe base64, behind a version
+//! prefix, e.g. `v1.AQFBAw`. The value can be put in headers, query
+//! strings and cookies as is. Clients are expected to treat it as opaque
+//! and hand it back unchanged, the way Riak exposes its vclocks.
+//!
+//! This is the format of `CLOCK_HEADER` and of the axum, reqwest and MQTT
+//! integrations. Values in the older `host:counter` text form of the
+//! clock's `Display`, which never look like a versioned context, are still
+//! accepted when decoding.
+
+use std::prelude::v1::*;
+use std::fmt::Display;
+use std::hash::Hash;
+use std::str::FromStr;
+
+use base64;
+use clock::VectorClock;
+use error::{Error, Result};
+use process::ProcessClock;
+
+/// The prefix of contexts written by this version of the crate.
+pub const VERSION_PREFIX: &str = "v1.";
+
+pub fn to_header_value<HostType>(clock: &VectorClock<HostType>) -> String
+    where HostType: Clone + Hash + Eq + Display
+{
+    let mut out = VERSION_PREFIX.to_owned();
+    base64::encode(&clock.encode(), base64::URL_SAFE, false, &mut out);
+    out
+}
+
+pub fn from_header_value<HostType>(value: &str) -> Result<VectorClock<HostType>>
+    where HostType: Clone + Hash + Eq + FromStr
+{
+    let value = value.trim();
+
+    // Text clocks always have a `:` in each entry, which base64 never has.
+    if value.is_empty() || value.contains(':') {
+        return VectorClock::try_parse(value);
+    }
+
+    let encoded = match value.strip_prefix(VERSION_PREFIX) {
+        Some(encoded) => encoded,
+        None => return Err(Error::Decode(format!("unsupported causal context version in {:?}", value))),
+    };
+
+    let bytes = base64::decode(encoded, base64::URL_SAFE, false)
+        .ok_or_else(|| Error::Decode("causal context is not URL-safe base64".to_owned()))?;
+
+    VectorClock::decode(&bytes)
+}
+
+/// Merge the context received with a request or response into `clock`.
+pub fn merge_header_value<HostType>(clock: &mut VectorClock<HostType>, value: &str) -> Result<()>
+    where HostType: Clone + Hash + Eq + FromStr
+{
+    clock.merge(&from_header_value(value)?);
+    Ok(())
+}
+
+/// Record receiving a message that carried the context `value`.
+pub fn observe_header_value<HostType>(process: &mut ProcessClock<HostType>, value: &str) -> Result<()>
+    where HostType: Clone + Hash + Eq + FromStr
+{
+    process.observe_receive(&from_header_value(value)?);
+    Ok(())
+}
+
+/// Record sending a message, returning the context to attach to it.
+pub fn prepare_header_value<HostType>(process: &mut ProcessClock<HostType>) -> String
+    where HostType: Clone + Hash + Eq + Display
+{
+    to_header_value(&process.prepare_send())
+}
+
+#[cfg(test)]
+mod test {
+    use std::prelude::v1::*;
+    use super::{to_header_value, from_header_value, merge_header_value, observe_header_value,
+                prepare_header_value};
+    use clock::{VectorClock, TemporalRelation};
+    use process::ProcessClock;
+
+    #[test]
+    fn test_round_trip() {
+        let empty = VectorClock::<String>::new();
+        let clock = vec![("web-1".to_owned(), 3), ("db".to_owned(), 300)].into_iter().collect();
+
+        assert_eq!("v1.AA", to_header_value(&empty));
+        assert_eq!(empty, from_header_value(&to_header_value(&empty)).unwrap());
+
+        let value = to_header_value(&clock);
+
+        assert!(value.bytes().skip(3).all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_'));
+        assert_eq!(clock, from_header_value::<String>(&value).unwrap());
+    }
+
+    #[test]
+    fn test_rejects_invalid() {
+        assert_eq!(VectorClock::new().incremented("A".to_owned()).incremented("A".to_owned())
+                       .incremented("A".to_owned()),
+                   from_header_value("v1.AQFBAw").unwrap());
+
+        assert!(from_header_value::<String>("AQFBAw").is_err());
+        assert!(from_header_value::<String>("v2.AQFBAw").is_err());
+        assert!(from_header_value::<String>("v1.AQFBAw==").is_err());
+        assert!(from_header_value::<String>("v1.AQFBAx").is_err());
+        assert!(from_header_value::<String>("v1.A").is_err());
+    }
+
+    #[test]
+    fn test_accepts_text_form() {
+        let clock = vec![("a".to_owned(), 2), ("b:c".to_owned(), 1)].into_iter().collect::<VectorClock<_>>();
+
+        assert_eq!(clock, from_header_value("a:2,b%3Ac:1").unwrap());
+        assert_eq!(VectorClock::<String>::new(), from_header_value("").unwrap());
+        assert!(from_header_value::<String>("a:x").is_err());
+    }
+
+    #[test]
+    fn test_propagation() {
+        let mut client = ProcessClock::new("client".to_owned());
+        let mut server = ProcessClock::new("server".to_owned());
+
+        let request = prepare_header_value(&mut client);
+        observe_header_value(&mut server, &request).unwrap();
+
+        let mut seen = VectorClock::new();
+        merge_header_value(&mut seen, &prepare_header_value(&mut server)).unwrap();
+
+        assert_eq!(TemporalRelation::Caused, client.clock().temporal_relation(&seen));
+        assert_eq!(seen, *server.clock());
+    }
+}
@@ -0,0 +1,52 @@
+use std::fmt::Debug;
+use std::hash::Hash;
+
+/// An unsigned integer type usable as the counters of a `VectorClock`.
+///
+/// Clocks default to `u64` counters. Narrower types save space in memory
+/// for clocks that are known to see few events per host, at the cost of
+/// overflowing sooner.
+pub trait Counter: Copy + Ord + Hash + Debug {
+    const ZERO: Self;
+    const ONE: Self;
+
+    fn checked_add(self, other: Self) -> Option<Self>;
+
+    fn saturating_add(self, other: Self) -> Self;
+
+    /// The counter widened to `u64`, e.g. for `VectorClock::distance`.
+    fn to_u64(self) -> u64;
+
+    /// The counter narrowed from `u64`, or `None` if `n` doesn't fit, e.g.
+    /// when decoding a clock written with wider counters.
+    fn from_u64(n: u64) -> Option<Self>;
+}
+
+macro_rules! impl_counter {
+    ($($t:ty),*) => {
+        $(
+            impl Counter for $t {
+                const ZERO: $t = 0;
+                const ONE: $t = 1;
+
+                fn checked_add(self, other: $t) -> Option<$t> {
+                    <$t>::checked_add(self, other)
+                }
+
+                fn saturating_add(self, other: $t) -> $t {
+                    <$t>::saturating_add(self, other)
+                }
+
+                fn to_u64(self) -> u64 {
+                    u64::from(self)
+                }
+
+                fn from_u64(n: u64) -> Option<$t> {
+                    if n <= <$t>::MAX as u64 { Some(n as $t) } else { None }
+                }
+            }
+        )*
+    };
+}
+
+impl_counter!(u8, u16, u32, u64);
@@ -0,0 +1,269 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use clock::{VectorClock, TemporalRelation};
+
+/// The longest causal chain in a `CausalDag`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct CriticalPath {
+    /// The events on the path, earliest first.
+    pub nodes: Vec<usize>,
+    /// The weight of each event on the path.
+    pub weights: Vec<f64>,
+    pub total: f64,
+}
+
+impl CriticalPath {
+    /// The share of the path's weight contributed by each host, largest
+    /// first. `hosts[i]` is the host that performed event `i`.
+    pub fn by_host<HostType: Clone + Hash + Eq>(&self, hosts: &[HostType]) -> Vec<(HostType, f64)> {
+        let mut totals: HashMap<HostType, f64> = HashMap::new();
+
+        for (&node, &w) in self.nodes.iter().zip(self.weights.iter()) {
+            *totals.entry(hosts[node].clone()).or_insert(0.0) += w;
+        }
+
+        let mut totals: Vec<_> = totals.into_iter().collect();
+        totals.sort_by(|a, b| b.1.total_cmp(&a.1));
+        totals
+    }
+}
+
+/// The transitively reduced happens-before graph of a set of events.
+///
+/// Nodes are the indices of the events' clocks in the input. There is an
+/// edge from `a` to `b` if `a` happened before `b` and no other event
+/// happened in between, i.e. the Hasse diagram of the causal order.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CausalDag {
+    successors: Vec<Vec<usize>>,
+    predecessors: Vec<Vec<usize>>,
+    order: Vec<usize>,
+}
+
+impl CausalDag {
+    pub fn build<'a, HostType, I>(clocks: I) -> CausalDag
+        where HostType: Clone + Hash + Eq + 'a, I: IntoIterator<Item=&'a VectorClock<HostType>>
+    {
+        let clocks: Vec<_> = clocks.into_iter().collect();
+        let n = clocks.len();
+
+        // before[j][i] is true if event i happened before event j.
+        let mut before = vec![vec![false; n]; n];
+
+        for i in 0..n {
+            for j in (i + 1)..n {
+                match clocks[i].temporal_relation(clocks[j]) {
+                    TemporalRelation::Caused => before[j][i] = true,
+                    TemporalRelation::EffectOf => before[i][j] = true,
+                    _ => {},
+                }
+            }
+        }
+
+        // Every event has strictly fewer ancestors than its descendants, so
+        // this is a topological order.
+        let mut order: Vec<usize> = (0..n).collect();
+        order.sort_by_key(|&j| before[j].iter().filter(|&&b| b).count());
+
+        let mut position = vec![0; n];
+        for (p, &j) in order.iter().enumerate() {
+            position[j] = p;
+        }
+
+        let mut successors = vec![Vec::new(); n];
+        let mut predecessors = vec![Vec::new(); n];
+
+        for j in 0..n {
+            let mut candidates: Vec<usize> = (0..n).filter(|&i| before[j][i]).collect();
+            candidates.sort_by_key(|&i| ::std::cmp::Reverse(position[i]));
+
+            // Visit ancestors latest first: an ancestor is a direct
+            // predecessor unless it is already an ancestor of one.
+            let mut covered = vec![false; n];
+
+            for i in candidates {
+                if covered[i] {
+                    continue;
+                }
+
+                predecessors[j].push(i);
+                successors[i].push(j);
+
+                for k in 0..n {
+                    covered[k] = covered[k] || before[i][k];
+                }
+            }
+        }
+
+        for list in successors.iter_mut().chain(predecessors.iter_mut()) {
+            list.sort();
+        }
+
+        CausalDag {
+            successors,
+            predecessors,
+            order,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.successors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.successors.is_empty()
+    }
+
+    /// The events that directly follow `node`.
+    pub fn successors(&self, node: usize) -> &[usize] {
+        &self.successors[node]
+    }
+
+    /// The events that `node` directly follows.
+    pub fn predecessors(&self, node: usize) -> &[usize] {
+        &self.predecessors[node]
+    }
+
+    pub fn edges(&self) -> Vec<(usize, usize)> {
+        self.successors.iter().enumerate()
+            .flat_map(|(from, tos)| tos.iter().map(move |&to| (from, to)))
+            .collect()
+    }
+
+    /// Events with no causal predecessors.
+    pub fn roots(&self) -> Vec<usize> {
+        (0..self.len()).filter(|&i| self.predecessors[i].is_empty()).collect()
+    }
+
+    /// Events with no causal successors.
+    pub fn leaves(&self) -> Vec<usize> {
+        (0..self.len()).filter(|&i| self.successors[i].is_empty()).collect()
+    }
+
+    /// All events, ordered so that every event comes after its causes.
+    pub fn topological_order(&self) -> &[usize] {
+        &self.order
+    }
+
+    /// The causal chain with the most events.
+    pub fn critical_path(&self) -> CriticalPath {
+        self.weighted_critical_path(&vec![1.0; self.len()])
+    }
+
+    /// The causal chain with the largest total weight, where `weights[i]`
+    /// is e.g. the duration of event `i`.
+    pub fn weighted_critical_path(&self, weights: &[f64]) -> CriticalPath {
+        let n = self.len();
+        let mut best = vec![0.0; n];
+        let mut via: Vec<Option<usize>> = vec![None; n];
+
+        for &j in &self.order {
+            let mut from = None;
+
+            for &i in &self.predecessors[j] {
+                if from.is_none_or(|f: usize| best[i] > best[f]) {
+                    from = Some(i);
+                }
+            }
+
+            best[j] = weights[j] + from.map_or(0.0, |f| best[f]);
+            via[j] = from;
+        }
+
+        let mut end = None;
+        for j in 0..n {
+            if end.is_none_or(|e: usize| best[j] > best[e]) {
+                end = Some(j);
+            }
+        }
+
+        let mut nodes = Vec::new();
+        let mut cursor = end;
+
+        while let Some(j) = cursor {
+            nodes.push(j);
+            cursor = via[j];
+        }
+
+        nodes.reverse();
+
+        CriticalPath {
+            weights: nodes.iter().map(|&j| weights[j]).collect(),
+            total: end.map_or(0.0, |e| best[e]),
+            nodes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::CausalDag;
+    use clock::VectorClock;
+
+    #[test]
+    fn test_transitive_edges_are_reduced() {
+        let a = VectorClock::new().incremented("A");
+        let b = a.incremented("A");
+        let c = b.incremented("A");
+
+        let dag = CausalDag::build(vec![&c, &a, &b]);
+
+        assert_eq!(vec![(1, 2), (2, 0)], dag.edges());
+        assert_eq!(&[1, 2, 0], dag.topological_order());
+    }
+
+    #[test]
+    fn test_diamond() {
+        let root = VectorClock::new().incremented("A");
+        let left = root.incremented("B");
+        let right = root.incremented("C");
+        let join = left.merge_with(&right).incremented("A");
+
+        let dag = CausalDag::build(vec![&root, &left, &right, &join]);
+
+        assert_eq!(vec![(0, 1), (0, 2), (1, 3), (2, 3)], dag.edges());
+        assert_eq!(vec![0], dag.roots());
+        assert_eq!(vec![3], dag.leaves());
+        assert_eq!(&[1, 2], dag.predecessors(3));
+    }
+
+    #[test]
+    fn test_critical_path() {
+        let root = VectorClock::new().incremented("A");
+        let left = root.incremented("B");
+        let left2 = left.incremented("B");
+        let right = root.incremented("C");
+        let join = left2.merge_with(&right).incremented("A");
+
+        let dag = CausalDag::build(vec![&root, &left, &left2, &right, &join]);
+        let path = dag.critical_path();
+
+        assert_eq!(vec![0, 1, 2, 4], path.nodes);
+        assert_eq!(4.0, path.total);
+
+        let weighted = dag.weighted_critical_path(&[1.0, 1.0, 1.0, 5.0, 2.0]);
+
+        assert_eq!(vec![0, 3, 4], weighted.nodes);
+        assert_eq!(8.0, weighted.total);
+        assert_eq!(vec![("C", 5.0), ("A", 3.0)], weighted.by_host(&["A", "B", "B", "C", "A"]));
+    }
+
+    #[test]
+    fn test_empty_critical_path() {
+        let dag = CausalDag::build(Vec::<&VectorClock<&str>>::new());
+
+        assert!(dag.critical_path().nodes.is_empty());
+    }
+
+    #[test]
+    fn test_concurrent_events_are_unconnected() {
+        let a = VectorClock::new().incremented("A");
+        let b = VectorClock::new().incremented("B");
+
+        let dag = CausalDag::build(vec![&a, &b]);
+
+        assert!(dag.edges().is_empty());
+        assert_eq!(vec![0, 1], dag.roots());
+    }
+}
@@ -0,0 +1,121 @@
+use std::prelude::v1::*;
+use std::hash::Hash;
+
+use clock::VectorClock;
+
+/// Holds back broadcast messages until everything they causally depend on
+/// has been delivered, as in the Birman-Schiper-Stephenson protocol.
+///
+/// Each message is tagged with its sender and the sender's clock after
+/// ticking its own entry for the send. A message is ready once it is the
+/// sender's next message and every other entry of its clock has already
+/// been delivered.
+#[derive(Debug, Clone)]
+pub struct CausalBuffer<HostType: Hash + Eq, M> {
+    delivered: VectorClock<HostType>,
+    pending: Vec<(HostType, VectorClock<HostType>, M)>,
+}
+
+impl<HostType: Clone + Hash + Eq, M> CausalBuffer<HostType, M> {
+    pub fn new() -> CausalBuffer<HostType, M> {
+        CausalBuffer::starting_at(VectorClock::new())
+    }
+
+    /// A buffer that treats everything in `delivered` as delivered already,
+    /// e.g. after restoring local state.
+    pub fn starting_at(delivered: VectorClock<HostType>) -> CausalBuffer<HostType, M> {
+        CausalBuffer {
+            delivered,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Buffer a message `sender` sent at `clock`. Messages that were
+    /// delivered already are dropped, and `false` is returned.
+    pub fn receive(&mut self, sender: HostType, clock: VectorClock<HostType>, message: M) -> bool {
+        if clock.get(&sender) <= self.delivered.get(&sender) {
+            return false;
+        }
+
+        self.pending.push((sender, clock, message));
+        true
+    }
+
+    /// Remove and return every message that is ready, in an order
+    /// consistent with causality.
+    pub fn deliver_ready(&mut self) -> Vec<M> {
+        let mut ready = Vec::new();
+
+        while let Some(i) = self.pending.iter().position(|(sender, clock, _)| self.is_ready(sender, clock)) {
+            let (sender, clock, message) = self.pending.remove(i);
+            self.delivered.set(sender.clone(), clock.get(&sender));
+            ready.push(message);
+
+            let delivered = &self.delivered;
+            self.pending.retain(|(sender, clock, _)| clock.get(sender) > delivered.get(sender));
+        }
+
+        ready
+    }
+
+    /// The clock of everything delivered so far.
+    pub fn delivered(&self) -> &VectorClock<HostType> {
+        &self.delivered
+    }
+
+    /// The number of messages still waiting for their dependencies.
+    pub fn pending_len(&self) -> usize {
+        self.pending.len()
+    }
+
+    fn is_ready(&self, sender: &HostType, clock: &VectorClock<HostType>) -> bool {
+        clock.get(sender) == self.delivered.get(sender) + 1
+            && clock.iter().all(|(host, &n)| host == sender || n <= self.delivered.get(host))
+    }
+}
+
+impl<HostType: Clone + Hash + Eq, M> Default for CausalBuffer<HostType, M> {
+    fn default() -> CausalBuffer<HostType, M> {
+        CausalBuffer::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::CausalBuffer;
+    use clock::VectorClock;
+
+    #[test]
+    fn test_deliver_in_causal_order() {
+        let a1 = VectorClock::new().incremented("A");
+        let a2 = a1.incremented("A");
+        let b1 = a2.incremented("B");
+
+        let mut buffer = CausalBuffer::new();
+
+        assert!(buffer.receive("B", b1.clone(), "b1"));
+        assert!(buffer.receive("A", a2.clone(), "a2"));
+        assert!(buffer.deliver_ready().is_empty());
+        assert_eq!(2, buffer.pending_len());
+
+        assert!(buffer.receive("A", a1.clone(), "a1"));
+        assert_eq!(vec!["a1", "a2", "b1"], buffer.deliver_ready());
+        assert_eq!(&b1, buffer.delivered());
+
+        assert!(!buffer.receive("A", a2, "a2 again"));
+        assert_eq!(0, buffer.pending_len());
+    }
+
+    #[test]
+    fn test_concurrent_messages_are_ready_together() {
+        let mut buffer = CausalBuffer::starting_at(VectorClock::new().incremented("A"));
+
+        buffer.receive("B", VectorClock::new().incremented("B"), 1);
+        buffer.receive("C", VectorClock::new().incremented("A").incremented("C"), 2);
+
+        let mut ready = buffer.deliver_ready();
+        ready.sort();
+
+        assert_eq!(vec![1, 2], ready);
+    }
+}
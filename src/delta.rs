@@ -0,0 +1,102 @@
+use std::prelude::v1::*;
+use std::fmt::Display;
+use std::hash::Hash;
+use std::str::FromStr;
+
+use clock::VectorClock;
+use counter::Counter;
+use error::Result;
+
+/// The entries of a clock that are ahead of some remote clock, produced by
+/// `VectorClock::diff`.
+///
+/// Counters are kept as absolute values rather than increments, so applying
+/// a delta twice, or to a clock that has moved on, is harmless.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct VectorClockDelta<HostType: Hash + Eq, CounterType = u64> {
+    entries: VectorClock<HostType, CounterType>,
+}
+
+impl<HostType: Clone + Hash + Eq, CounterType: Counter> VectorClockDelta<HostType, CounterType> {
+    pub fn entries(&self) -> &VectorClock<HostType, CounterType> {
+        &self.entries
+    }
+
+    pub fn into_clock(self) -> VectorClock<HostType, CounterType> {
+        self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl<HostType: Clone + Hash + Eq + Display, CounterType: Counter> VectorClockDelta<HostType, CounterType> {
+    /// Encode the delta in the packed format of `VectorClock::to_packed`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.entries.to_packed()
+    }
+}
+
+impl<HostType: Clone + Hash + Eq + FromStr, CounterType: Counter> VectorClockDelta<HostType, CounterType> {
+    pub fn from_bytes(bytes: &[u8]) -> Result<VectorClockDelta<HostType, CounterType>> {
+        VectorClock::from_packed(bytes).map(|entries| VectorClockDelta { entries })
+    }
+}
+
+impl<HostType: Clone + Hash + Eq, CounterType: Counter> VectorClock<HostType, CounterType> {
+    /// The entries where this clock is ahead of `remote`. Applying the
+    /// result to `remote`, or to any clock that covers it, brings it up to
+    /// date with this clock.
+    pub fn diff(&self, remote: &Self) -> VectorClockDelta<HostType, CounterType> {
+        VectorClockDelta {
+            entries: self.iter()
+                .filter(|&(host, &n)| n > remote.get(host))
+                .map(|(host, &n)| (host.clone(), n))
+                .collect(),
+        }
+    }
+
+    pub fn apply_delta(&mut self, delta: &VectorClockDelta<HostType, CounterType>) {
+        self.merge(&delta.entries);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::prelude::v1::*;
+    use super::VectorClockDelta;
+    use clock::VectorClock;
+
+    #[test]
+    fn test_diff_and_apply() {
+        let base = (0..100).map(|i| (i.to_string(), 5)).collect::<VectorClock<_>>();
+        let local = base.incremented("7".to_owned()).incremented("200".to_owned());
+        let mut remote = base.incremented("8".to_owned());
+
+        let delta = local.diff(&remote);
+
+        assert_eq!(2, delta.len());
+        assert!(delta.to_bytes().len() < local.to_packed().len() / 10);
+
+        remote.apply_delta(&VectorClockDelta::from_bytes(&delta.to_bytes()).unwrap());
+        assert_eq!(remote, local.merge_with(&base.incremented("8".to_owned())));
+
+        assert!(local.diff(&remote).is_empty());
+    }
+
+    #[test]
+    fn test_narrow_counters() {
+        let local = vec![("a".to_owned(), 3u32), ("b".to_owned(), 1)].into_iter().collect::<VectorClock<_, u32>>();
+        let mut remote = vec![("a".to_owned(), 3u32)].into_iter().collect::<VectorClock<_, u32>>();
+
+        let delta = VectorClockDelta::<String, u32>::from_bytes(&local.diff(&remote).to_bytes()).unwrap();
+        remote.apply_delta(&delta);
+
+        assert_eq!(local, remote);
+    }
+}
@@ -0,0 +1,68 @@
+use std::prelude::v1::*;
+use std::hash::Hash;
+
+use clock::VectorClock;
+
+/// A single event: the `counter`-th event of `host`.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
+pub struct Dot<HostType> {
+    pub host: HostType,
+    pub counter: u64,
+}
+
+impl<HostType> Dot<HostType> {
+    pub fn new(host: HostType, counter: u64) -> Dot<HostType> {
+        Dot {
+            host,
+            counter,
+        }
+    }
+}
+
+impl<HostType: Clone + Hash + Eq> VectorClock<HostType> {
+    /// Whether the event `dot` is part of this clock's causal history.
+    pub fn contains_dot(&self, dot: &Dot<HostType>) -> bool {
+        dot.counter <= self.get(&dot.host)
+    }
+
+    /// Whether every dot in `dots` is part of this clock's causal history,
+    /// e.g. to check that a CRDT delta has already been applied.
+    pub fn covers_dots<'a, I>(&self, dots: I) -> bool
+        where I: IntoIterator<Item=&'a Dot<HostType>>, HostType: 'a
+    {
+        dots.into_iter().all(|dot| self.contains_dot(dot))
+    }
+
+    /// The dots in `dots` that are not yet part of this clock's history.
+    pub fn missing_dots<'a, I>(&self, dots: I) -> Vec<&'a Dot<HostType>>
+        where I: IntoIterator<Item=&'a Dot<HostType>>, HostType: 'a
+    {
+        dots.into_iter().filter(|dot| !self.contains_dot(dot)).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Dot;
+    use clock::VectorClock;
+
+    #[test]
+    fn test_contains_dot() {
+        let c = VectorClock::new().incremented("A").incremented("A");
+
+        assert!(c.contains_dot(&Dot::new("A", 1)));
+        assert!(c.contains_dot(&Dot::new("A", 2)));
+        assert!(!c.contains_dot(&Dot::new("A", 3)));
+        assert!(!c.contains_dot(&Dot::new("B", 1)));
+    }
+
+    #[test]
+    fn test_covers_dots() {
+        let c = VectorClock::new().incremented("A").incremented("B");
+        let dots = vec![Dot::new("A", 1), Dot::new("B", 1), Dot::new("B", 2)];
+
+        assert!(c.covers_dots(&dots[..2]));
+        assert!(!c.covers_dots(&dots));
+        assert_eq!(vec![&Dot::new("B", 2)], c.missing_dots(&dots));
+    }
+}
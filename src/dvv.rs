@@ -0,0 +1,148 @@
+use std::prelude::v1::*;
+use std::hash::Hash;
+
+use clock::VectorClock;
+use dot::Dot;
+
+/// A dotted version vector: the dot of the write that created a version,
+/// and the causal context the writer had read.
+///
+/// Unlike a plain clock, the new dot is kept apart from the context, so a
+/// server can tell a sibling the client never saw from one it replaced,
+/// even when the server assigns every dot itself.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct DottedVersionVector<HostType: Hash + Eq> {
+    dot: Dot<HostType>,
+    context: VectorClock<HostType>,
+}
+
+impl<HostType: Clone + Hash + Eq> DottedVersionVector<HostType> {
+    pub fn new(dot: Dot<HostType>, context: VectorClock<HostType>) -> DottedVersionVector<HostType> {
+        DottedVersionVector {
+            dot,
+            context,
+        }
+    }
+
+    /// A new version written by `host` with client context `context`.
+    ///
+    /// The dot counter follows every dot of `host` in `siblings`, the
+    /// versions currently stored, so that dots stay unique even if the
+    /// client's context is stale.
+    pub fn event<'a, I>(context: &VectorClock<HostType>, siblings: I, host: HostType) -> DottedVersionVector<HostType>
+        where I: IntoIterator<Item=&'a DottedVersionVector<HostType>>, HostType: 'a
+    {
+        let latest = siblings.into_iter()
+            .map(|s| s.to_clock().get(&host))
+            .fold(context.get(&host), ::std::cmp::max);
+
+        DottedVersionVector::new(Dot::new(host, latest + 1), context.clone())
+    }
+
+    pub fn dot(&self) -> &Dot<HostType> {
+        &self.dot
+    }
+
+    pub fn context(&self) -> &VectorClock<HostType> {
+        &self.context
+    }
+
+    /// The full causal history of the version: its context plus its dot.
+    pub fn to_clock(&self) -> VectorClock<HostType> {
+        let mut clock = self.context.clone();
+
+        if clock.get(&self.dot.host) < self.dot.counter {
+            clock.set(self.dot.host.clone(), self.dot.counter);
+        }
+
+        clock
+    }
+
+    /// Whether a causal context, e.g. one a client read, already includes
+    /// this version, so that a write with that context replaces it.
+    pub fn covered_by(&self, context: &VectorClock<HostType>) -> bool {
+        context.contains_dot(&self.dot)
+    }
+
+    /// Whether `self` is a strictly newer version than `other`, i.e. the
+    /// writer of `self` had seen `other`.
+    pub fn dominates(&self, other: &DottedVersionVector<HostType>) -> bool {
+        self.dot != other.dot && other.covered_by(&self.context)
+    }
+
+    /// Combine two sets of sibling versions, e.g. a local and a remote replica
+    /// of one key, keeping only versions that no version in either set
+    /// dominates. A version present on both sides is kept once.
+    pub fn sync<T>(a: Vec<(DottedVersionVector<HostType>, T)>, b: Vec<(DottedVersionVector<HostType>, T)>)
+        -> Vec<(DottedVersionVector<HostType>, T)>
+    {
+        let mut result: Vec<(DottedVersionVector<HostType>, T)> = Vec::with_capacity(a.len() + b.len());
+
+        for (version, value) in a.into_iter().chain(b) {
+            if result.iter().any(|(kept, _)| kept.dot == version.dot || kept.dominates(&version)) {
+                continue;
+            }
+
+            result.retain(|(kept, _)| !version.dominates(kept));
+            result.push((version, value));
+        }
+
+        result
+    }
+
+    /// The causal context covering every version in `siblings`, to hand to a
+    /// client that read them all.
+    pub fn join<'a, I>(siblings: I) -> VectorClock<HostType>
+        where I: IntoIterator<Item=&'a DottedVersionVector<HostType>>, HostType: 'a
+    {
+        let mut context = VectorClock::new();
+
+        for version in siblings {
+            context.merge(&version.to_clock());
+        }
+
+        context
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::DottedVersionVector;
+    use clock::VectorClock;
+    use dot::Dot;
+
+    #[test]
+    fn test_event_and_join() {
+        let empty = VectorClock::new();
+
+        let a = DottedVersionVector::event(&empty, &[], "S");
+        let b = DottedVersionVector::event(&empty, Some(&a), "S");
+
+        assert_eq!(&Dot::new("S", 1), a.dot());
+        assert_eq!(&Dot::new("S", 2), b.dot());
+
+        // Both writes had an empty context, so neither replaces the other,
+        // even though a plain clock would make the second look newer.
+        assert!(!a.dominates(&b) && !b.dominates(&a));
+
+        let context = DottedVersionVector::join(&[a.clone(), b.clone()]);
+        let c = DottedVersionVector::event(&context, &[a.clone(), b.clone()], "S");
+
+        assert_eq!(&Dot::new("S", 3), c.dot());
+        assert!(a.covered_by(c.context()) && b.covered_by(c.context()));
+        assert!(c.dominates(&a) && c.dominates(&b));
+    }
+
+    #[test]
+    fn test_sync() {
+        let empty = VectorClock::new();
+        let a = DottedVersionVector::event(&empty, &[], "S");
+        let b = DottedVersionVector::event(&empty, Some(&a), "S");
+        let c = DottedVersionVector::event(&a.to_clock(), &[a.clone(), b.clone()], "T");
+
+        let local = vec![(a.clone(), "a"), (b.clone(), "b")];
+        let remote = vec![(b.clone(), "b"), (c.clone(), "c")];
+
+        assert_eq!(vec![(b, "b"), (c, "c")], DottedVersionVector::sync(local, remote));
+    }
+}
@@ -0,0 +1,156 @@
+use std::any::{Any, TypeId};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+use clock::VectorClock;
+
+/// The operations a host type needs to be usable behind a `DynHost`.
+///
+/// Implemented for every `'static` type that can be used as a regular
+/// `VectorClock` host.
+pub trait ErasedHost: Any + fmt::Debug + Send + Sync {
+    fn eq_erased(&self, other: &dyn ErasedHost) -> bool;
+    fn hash_erased(&self, state: &mut dyn Hasher);
+    fn clone_erased(&self) -> Box<dyn ErasedHost>;
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<T> ErasedHost for T where T: Any + fmt::Debug + Clone + Hash + Eq + Send + Sync {
+    fn eq_erased(&self, other: &dyn ErasedHost) -> bool {
+        other.as_any().downcast_ref::<T>() == Some(self)
+    }
+
+    fn hash_erased(&self, mut state: &mut dyn Hasher) {
+        TypeId::of::<T>().hash(&mut state);
+        self.hash(&mut state);
+    }
+
+    fn clone_erased(&self) -> Box<dyn ErasedHost> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// A host of any type. Hosts of different types never compare equal.
+pub struct DynHost(Box<dyn ErasedHost>);
+
+impl DynHost {
+    /// Wrap `host`. A `DynHost` is returned as is rather than wrapped
+    /// again, so that it keeps comparing and hashing like the host inside.
+    pub fn new<HostType: ErasedHost>(host: HostType) -> DynHost {
+        match (&host as &dyn Any).downcast_ref::<DynHost>() {
+            Some(inner) => inner.clone(),
+            None => DynHost(Box::new(host)),
+        }
+    }
+
+    pub fn downcast_ref<HostType: Any>(&self) -> Option<&HostType> {
+        self.0.as_any().downcast_ref()
+    }
+
+    pub fn is<HostType: Any>(&self) -> bool {
+        self.0.as_any().is::<HostType>()
+    }
+}
+
+impl PartialEq for DynHost {
+    fn eq(&self, other: &DynHost) -> bool {
+        self.0.eq_erased(&*other.0)
+    }
+}
+
+impl Eq for DynHost {}
+
+impl Hash for DynHost {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash_erased(state)
+    }
+}
+
+impl Clone for DynHost {
+    fn clone(&self) -> DynHost {
+        DynHost(self.0.clone_erased())
+    }
+}
+
+impl fmt::Debug for DynHost {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// A clock whose hosts may be of different types.
+pub type DynVectorClock = VectorClock<DynHost>;
+
+impl<HostType: ErasedHost + Clone + Hash + Eq> VectorClock<HostType> {
+    pub fn to_dyn(&self) -> DynVectorClock {
+        self.iter().map(|(host, &n)| (DynHost::new(host.clone()), n)).collect()
+    }
+}
+
+impl VectorClock<DynHost> {
+    /// Convert back to a typed clock, if every host is a `HostType`.
+    pub fn to_typed<HostType: ErasedHost + Clone + Hash + Eq>(&self) -> Option<VectorClock<HostType>> {
+        self.iter()
+            .map(|(host, &n)| host.downcast_ref::<HostType>().map(|h| (h.clone(), n)))
+            .collect()
+    }
+
+    /// The entries whose hosts are a `HostType`, ignoring all others.
+    pub fn typed_part<HostType: ErasedHost + Clone + Hash + Eq>(&self) -> VectorClock<HostType> {
+        self.iter()
+            .filter_map(|(host, &n)| host.downcast_ref::<HostType>().map(|h| (h.clone(), n)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::DynHost;
+    use clock::{VectorClock, TemporalRelation};
+
+    #[test]
+    fn test_mixed_host_types() {
+        let a = VectorClock::new().incremented("node-a".to_owned()).to_dyn();
+        let b = VectorClock::new().incremented(7u32).to_dyn();
+
+        let m = a.merge_with(&b).incremented(DynHost::new(7u32));
+
+        assert!(a.temporal_relation(&m) == TemporalRelation::Caused);
+        assert_eq!(vec![(7u32, 2)], m.typed_part::<u32>().into_iter().collect::<Vec<_>>());
+        assert!(m.to_typed::<u32>().is_none());
+    }
+
+    #[test]
+    fn test_different_types_are_distinct_hosts() {
+        assert!(DynHost::new(1u32) != DynHost::new(1u64));
+        assert!(DynHost::new(1u32) == DynHost::new(1u32));
+
+        let c = VectorClock::new().incremented(DynHost::new(1u32)).incremented(DynHost::new(1u64));
+
+        assert_eq!(2, c.len());
+    }
+
+    #[test]
+    fn test_no_nesting() {
+        let nested = DynHost::new(DynHost::new(1u32));
+
+        assert!(nested == DynHost::new(1u32));
+        assert!(nested.is::<u32>());
+        assert_eq!(1, VectorClock::new().incremented(nested).incremented(DynHost::new(1u32)).len());
+
+        let c = VectorClock::new().incremented(1u32).to_dyn();
+
+        assert_eq!(c, c.to_dyn());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let c = VectorClock::new().incremented("a").incremented("b");
+
+        assert_eq!(Some(c.clone()), c.to_dyn().to_typed::<&'static str>());
+    }
+}
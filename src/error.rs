@@ -0,0 +1,51 @@
+use std::prelude::v1::*;
+use std::error;
+use std::fmt;
+use std::result;
+
+/// The error type shared by the crate's fallible operations.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Error {
+    /// Encoded data was truncated or malformed.
+    Decode(String),
+    /// A counter could not be advanced without overflowing.
+    Overflow,
+    /// A bounded container was asked to hold more than `capacity` items.
+    CapacityExceeded { capacity: usize },
+    /// An entry was rejected, e.g. because of an unknown or duplicated host.
+    InvalidEntry(String),
+    /// A checksum, digest or signature did not match the data.
+    VerificationFailed,
+    /// A remote timestamp was `drift` milliseconds ahead of local time,
+    /// more than the allowed `max`.
+    ClockDrift { drift: u64, max: u64 },
+}
+
+pub type Result<T> = result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Decode(ref msg) => write!(f, "decode error: {}", msg),
+            Error::Overflow => write!(f, "counter overflow"),
+            Error::CapacityExceeded { capacity } => write!(f, "capacity of {} exceeded", capacity),
+            Error::InvalidEntry(ref msg) => write!(f, "invalid entry: {}", msg),
+            Error::VerificationFailed => write!(f, "verification failed"),
+            Error::ClockDrift { drift, max } => write!(f, "clock drift of {}ms exceeds {}ms", drift, max),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+#[cfg(test)]
+mod test {
+    use std::prelude::v1::*;
+    use super::Error;
+
+    #[test]
+    fn test_display() {
+        assert_eq!("decode error: truncated", Error::Decode("truncated".to_owned()).to_string());
+        assert_eq!("capacity of 8 exceeded", Error::CapacityExceeded { capacity: 8 }.to_string());
+    }
+}
@@ -0,0 +1,177 @@
+use std::fmt::{Display, Write};
+use std::hash::Hash;
+use std::str::FromStr;
+
+use clock::{VectorClock, TemporalRelation};
+use error::{Error, Result};
+use text::{escape_host, unescape_host};
+
+/// The ETag for a resource at `clock`, as a quoted string.
+///
+/// Entries are sorted by host and zero counters left out, so equal clocks
+/// always produce the same ETag. Inside the quotes, entries are written as
+/// `host:counter` separated by commas, with bytes outside printable ASCII
+/// and `"`, `%`, `,` and `:` percent-escaped.
+pub fn to_etag<HostType>(clock: &VectorClock<HostType>) -> String
+    where HostType: Clone + Hash + Eq + Display
+{
+    let mut entries: Vec<(String, u64)> = clock.iter()
+        .filter(|&(_, &n)| n > 0)
+        .map(|(host, &n)| (host.to_string(), n))
+        .collect();
+    entries.sort();
+
+    let mut out = String::from("\"");
+
+    for (i, (host, n)) in entries.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+
+        escape_host(host, b"\"", &mut out);
+        let _ = write!(out, ":{}", n);
+    }
+
+    out.push('"');
+    out
+}
+
+/// Parse a single ETag produced by `to_etag`. Weak ETags are accepted.
+pub fn parse_etag<HostType>(etag: &str) -> Result<VectorClock<HostType>>
+    where HostType: Clone + Hash + Eq + FromStr
+{
+    let malformed = || Error::Decode(format!("malformed ETag {:?}", etag));
+
+    let tag = etag.trim();
+    let tag = tag.strip_prefix("W/").unwrap_or(tag);
+    let tag = tag.strip_prefix('"').and_then(|t| t.strip_suffix('"')).ok_or_else(malformed)?;
+
+    let mut entries = Vec::new();
+
+    for entry in tag.split(',').filter(|e| !e.is_empty()) {
+        let sep = entry.rfind(':').ok_or_else(malformed)?;
+        let n = entry[sep + 1..].parse().map_err(|_| malformed())?;
+        let host = unescape_host(&entry[..sep]).ok_or_else(malformed)?
+            .parse().map_err(|_| malformed())?;
+
+        entries.push((host, n));
+    }
+
+    Ok(entries.into_iter().collect())
+}
+
+/// The parsed value of an `If-Match` or `If-None-Match` header.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Precondition<HostType: Hash + Eq> {
+    /// `*`, matching any current version.
+    Any,
+    Clocks(Vec<VectorClock<HostType>>),
+}
+
+impl<HostType: Clone + Hash + Eq + FromStr> Precondition<HostType> {
+    /// Parse a comma-separated list of ETags, or `*`.
+    pub fn parse(header: &str) -> Result<Precondition<HostType>> {
+        if header.trim() == "*" {
+            return Ok(Precondition::Any);
+        }
+
+        // Commas separate list items only outside quotes.
+        let mut clocks = Vec::new();
+        let mut start = 0;
+        let mut quoted = false;
+
+        for (i, c) in header.char_indices() {
+            match c {
+                '"' => quoted = !quoted,
+                ',' if !quoted => {
+                    clocks.push(parse_etag(&header[start..i])?);
+                    start = i + 1;
+                },
+                _ => {},
+            }
+        }
+
+        clocks.push(parse_etag(&header[start..])?);
+
+        Ok(Precondition::Clocks(clocks))
+    }
+}
+
+/// Whether an `If-Match` precondition holds for a resource currently at
+/// `stored`, or `None` if the resource doesn't exist.
+///
+/// A provided clock matches if it has seen the stored version, i.e. the
+/// stored clock is equal to or happened before it, so a client may update
+/// a resource whose version it has causally observed even if its clock has
+/// since moved on.
+pub fn precondition_holds<HostType>(stored: Option<&VectorClock<HostType>>, provided: &Precondition<HostType>) -> bool
+    where HostType: Clone + Hash + Eq
+{
+    let stored = match stored {
+        Some(stored) => stored,
+        None => return false,
+    };
+
+    match *provided {
+        Precondition::Any => true,
+        Precondition::Clocks(ref clocks) => clocks.iter().any(|c| {
+            matches!(stored.temporal_relation(c), TemporalRelation::Equal | TemporalRelation::Caused)
+        }),
+    }
+}
+
+/// Whether an `If-None-Match` precondition holds, i.e. none of the
+/// provided clocks has seen the stored version.
+pub fn none_match_holds<HostType>(stored: Option<&VectorClock<HostType>>, provided: &Precondition<HostType>) -> bool
+    where HostType: Clone + Hash + Eq
+{
+    stored.is_none() || !precondition_holds(stored, provided)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{to_etag, parse_etag, Precondition, precondition_holds, none_match_holds};
+    use clock::VectorClock;
+
+    fn clock(entries: &[(&str, u64)]) -> VectorClock<String> {
+        entries.iter().map(|&(h, n)| (h.to_owned(), n)).collect::<VectorClock<_>>()
+    }
+
+    #[test]
+    fn test_etag_round_trip() {
+        let c = clock(&[("b", 2), ("a \"x\",é", 1), ("z", 0)]);
+        let etag = to_etag(&c);
+
+        assert_eq!("\"a%20%22x%22%2C%C3%A9:1,b:2\"", etag);
+        assert_eq!(clock(&[("b", 2), ("a \"x\",é", 1)]), parse_etag(&etag).unwrap());
+        assert_eq!(clock(&[("b", 2)]), parse_etag::<String>("W/\"b:2\"").unwrap());
+        assert!(parse_etag::<String>("b:2").is_err());
+        assert!(parse_etag::<String>("\"b%+1:2\"").is_err());
+    }
+
+    #[test]
+    fn test_parse_precondition() {
+        assert_eq!(Precondition::Any, Precondition::<String>::parse(" * ").unwrap());
+        assert_eq!(Precondition::Clocks(vec![clock(&[("a", 1), ("b", 2)]), clock(&[("c", 3)])]),
+                   Precondition::parse("\"a:1,b:2\", W/\"c:3\"").unwrap());
+    }
+
+    #[test]
+    fn test_causal_preconditions() {
+        let stored = clock(&[("a", 2)]);
+
+        let seen = Precondition::Clocks(vec![clock(&[("a", 2), ("b", 1)])]);
+        let stale = Precondition::Clocks(vec![clock(&[("a", 1)])]);
+        let concurrent = Precondition::Clocks(vec![clock(&[("a", 1), ("b", 1)])]);
+
+        assert!(precondition_holds(Some(&stored), &seen));
+        assert!(!precondition_holds(Some(&stored), &stale));
+        assert!(!precondition_holds(Some(&stored), &concurrent));
+        assert!(precondition_holds(Some(&stored), &Precondition::Any));
+        assert!(!precondition_holds(None, &Precondition::<String>::Any));
+
+        assert!(none_match_holds(None, &Precondition::<String>::Any));
+        assert!(!none_match_holds(Some(&stored), &Precondition::Any));
+        assert!(none_match_holds(Some(&stored), &stale));
+    }
+}
@@ -0,0 +1,173 @@
+//! Structured `tracing` events for the points where causal information is
+//! lost or conflicts are found. Everything here compiles to nothing unless
+//! the `tracing` feature is enabled.
+
+use std::hash::{Hash, Hasher};
+
+use clock::EntryHasher;
+
+#[cfg(feature = "tracing")]
+const TARGET: &str = "vectorclock";
+
+/// Whether events are being collected, so callers can skip computing their
+/// fields otherwise.
+#[inline]
+pub(crate) fn enabled() -> bool {
+    #[cfg(feature = "tracing")]
+    {
+        ::tracing::enabled!(target: TARGET, ::tracing::Level::DEBUG)
+    }
+    #[cfg(not(feature = "tracing"))]
+    {
+        false
+    }
+}
+
+/// Identifies a host in events, since host types need not implement
+/// `Debug`: the FNV-1a hash of the host, stable across runs and processes.
+pub(crate) fn host_id<HostType: Hash + ?Sized>(host: &HostType) -> u64 {
+    let mut hasher = EntryHasher::new();
+    host.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Two concurrent clocks were merged. Each side lists the hosts it was
+/// ahead on, as `(host_id, local counter, remote counter)`.
+#[inline]
+pub(crate) fn concurrent_merge(local_ahead: &[(u64, u64, u64)], remote_ahead: &[(u64, u64, u64)]) {
+    #[cfg(feature = "tracing")]
+    ::tracing::debug!(target: TARGET, ?local_ahead, ?remote_ahead, "merged concurrent clocks");
+    #[cfg(not(feature = "tracing"))]
+    let _ = (local_ahead, remote_ahead);
+}
+
+/// Entries were removed from a clock, losing their causal information.
+/// `removed` lists them as `(host_id, counter)`.
+#[inline]
+pub(crate) fn pruned(removed: &[(u64, u64)]) {
+    #[cfg(feature = "tracing")]
+    ::tracing::debug!(target: TARGET, ?removed, "pruned clock entries");
+    #[cfg(not(feature = "tracing"))]
+    let _ = removed;
+}
+
+/// `saturating_increment` left the entry of a host at its maximum
+/// `counter` instead of advancing it.
+#[inline]
+pub(crate) fn saturated(host: u64, counter: u64) {
+    #[cfg(feature = "tracing")]
+    ::tracing::warn!(target: TARGET, host, counter, "clock entry saturated");
+    #[cfg(not(feature = "tracing"))]
+    let _ = (host, counter);
+}
+
+/// A `BoundedVectorClock` reached its size limit and evicted the entry of
+/// a host, with the counter it had.
+#[cfg(feature = "std")]
+#[inline]
+pub(crate) fn evicted(host: u64, counter: u64) {
+    #[cfg(feature = "tracing")]
+    ::tracing::debug!(target: TARGET, host, counter, "evicted clock entry");
+    #[cfg(not(feature = "tracing"))]
+    let _ = (host, counter);
+}
+
+// Capturing events needs a scoped subscriber, which tracing only has with
+// std.
+#[cfg(all(test, feature = "tracing", feature = "std"))]
+mod test {
+    use std::fmt::Debug;
+    use std::sync::{Arc, Mutex};
+
+    use tracing::{Event, Metadata, Subscriber};
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+
+    use clock::VectorClock;
+    use super::host_id;
+
+    // Records each event's fields, except the message, as `name=value`.
+    struct RecordingSubscriber(Arc<Mutex<Vec<String>>>);
+
+    struct Fields(Vec<String>);
+
+    impl Visit for Fields {
+        fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+            if field.name() != "message" {
+                self.0.push(format!("{}={:?}", field.name(), value));
+            }
+        }
+    }
+
+    impl Subscriber for RecordingSubscriber {
+        fn enabled(&self, _: &Metadata) -> bool { true }
+        fn new_span(&self, _: &Attributes) -> Id { Id::from_u64(1) }
+        fn record(&self, _: &Id, _: &Record) {}
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+        fn enter(&self, _: &Id) {}
+        fn exit(&self, _: &Id) {}
+
+        fn event(&self, event: &Event) {
+            let mut fields = Fields(Vec::new());
+            event.record(&mut fields);
+            self.0.lock().unwrap().push(fields.0.join(" "));
+        }
+    }
+
+    fn recorded<F: FnOnce()>(f: F) -> Vec<String> {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        ::tracing::subscriber::with_default(RecordingSubscriber(events.clone()), f);
+
+        let events = events.lock().unwrap();
+        events.clone()
+    }
+
+    #[test]
+    fn test_concurrent_merge_event() {
+        let a = VectorClock::new().incremented("A");
+        let b = VectorClock::new().incremented("B").incremented("B");
+
+        assert!(recorded(|| { a.merge_with(&a.incremented("A")); }).is_empty());
+
+        assert_eq!(vec![format!("local_ahead=[({}, 1, 0)] remote_ahead=[({}, 0, 2)]", host_id("A"), host_id("B"))],
+                   recorded(|| { a.merge_with(&b); }));
+    }
+
+    #[test]
+    fn test_pruned_event() {
+        let mut c = VectorClock::new().incremented("A").incremented("B").incremented("B");
+
+        let events = recorded(|| {
+            c.remove_host(&"C");
+            c.retain(|&host, _| host != "B");
+        });
+
+        assert_eq!(vec![format!("removed=[({}, 2)]", host_id("B"))], events);
+    }
+
+    #[test]
+    fn test_evicted_event() {
+        use bounded::{BoundedVectorClock, EvictionStrategy};
+
+        let mut c = BoundedVectorClock::new(1, EvictionStrategy::SmallestCounter);
+        c.increment("A");
+        c.increment("A");
+
+        let events = recorded(|| c.increment("B"));
+
+        assert!(events.contains(&format!("host={} counter=1", host_id("B"))), "{:?}", events);
+    }
+
+    #[test]
+    fn test_saturated_event() {
+        let mut c = VectorClock::<&str, u8>::default();
+        c.set("A", 254);
+
+        let events = recorded(|| {
+            c.saturating_increment("A");
+            c.saturating_increment("A");
+        });
+
+        assert_eq!(vec![format!("host={} counter=255", host_id("A"))], events);
+    }
+}
@@ -0,0 +1,252 @@
+use std::future::{self, Future};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use axum::extract::{FromRequestParts, Request, State};
+use axum::http::{HeaderValue, StatusCode};
+use axum::http::request::Parts;
+use axum::middleware::{FromFnLayer, Next, from_fn_with_state};
+use axum::response::{IntoResponseParts, Response, ResponseParts};
+
+use clock::VectorClock;
+use context::{to_header_value, from_header_value};
+use header::CLOCK_HEADER;
+use process::ProcessClock;
+use scope::ClockScope;
+
+/// Extracts the clock sent with a request, or an empty clock if the request
+/// has none. A malformed header is rejected with `400 Bad Request`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct RequestClock(pub VectorClock<String>);
+
+impl RequestClock {
+    /// The clock to send back after `host` handled the request: the
+    /// request's clock merged with `local` and ticked for `host`.
+    pub fn respond(&self, host: &str, local: &VectorClock<String>) -> ResponseClock {
+        ResponseClock(self.0.merge_with(local).incremented(host.to_owned()))
+    }
+}
+
+impl<State: Send + Sync> FromRequestParts<State> for RequestClock {
+    type Rejection = (StatusCode, String);
+
+    fn from_request_parts(parts: &mut Parts, _: &State) -> impl Future<Output=Result<RequestClock, Self::Rejection>> + Send {
+        let bad_request = |e: String| (StatusCode::BAD_REQUEST, e);
+
+        future::ready(match parts.headers.get(CLOCK_HEADER) {
+            None => Ok(RequestClock(VectorClock::new())),
+            Some(value) => value.to_str()
+                .map_err(|e| bad_request(e.to_string()))
+                .and_then(|v| from_header_value(v).map_err(|e| bad_request(e.to_string())))
+                .map(RequestClock),
+        })
+    }
+}
+
+/// Stamps a response with a clock in the `CLOCK_HEADER` header.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ResponseClock(pub VectorClock<String>);
+
+impl IntoResponseParts for ResponseClock {
+    type Error = (StatusCode, String);
+
+    fn into_response_parts(self, mut res: ResponseParts) -> Result<ResponseParts, Self::Error> {
+        let value = HeaderValue::from_str(&to_header_value(&self.0))
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+        res.headers_mut().insert(CLOCK_HEADER, value);
+        Ok(res)
+    }
+}
+
+/// The clock of a server, shared by the requests it handles.
+pub type ServerClock = Arc<Mutex<ProcessClock<String>>>;
+
+type ClockMiddleware = fn(State<ServerClock>, RequestClock, Request, Next) -> StampResponse;
+
+/// A layer that runs each request's handler with the request's clock as the
+/// task-local context (see `with_clock`), and stamps the response with the
+/// server's clock for `host` after it received that context.
+///
+/// Handlers and the `ClockSession`s they call through then share the
+/// request's clock without passing it around. Responses that already carry
+/// a clock, e.g. from a `ResponseClock`, are left as they are.
+pub fn clock_layer(host: &str) -> FromFnLayer<ClockMiddleware, ServerClock, (State<ServerClock>, RequestClock, Request)> {
+    clock_layer_with(Arc::new(Mutex::new(ProcessClock::new(host.to_owned()))))
+}
+
+/// `clock_layer` advancing the given server clock, e.g. one that other
+/// parts of the server also stamp their events with.
+pub fn clock_layer_with(clock: ServerClock)
+    -> FromFnLayer<ClockMiddleware, ServerClock, (State<ServerClock>, RequestClock, Request)>
+{
+    from_fn_with_state(clock, propagate as ClockMiddleware)
+}
+
+fn propagate(State(server): State<ServerClock>, RequestClock(clock): RequestClock, req: Request, next: Next)
+    -> StampResponse
+{
+    StampResponse {
+        server,
+        scope: ClockScope::new(clock, Box::pin(next.run(req))),
+    }
+}
+
+#[doc(hidden)]
+pub struct StampResponse {
+    server: ServerClock,
+    scope: ClockScope<dyn Future<Output=Response> + Send>,
+}
+
+impl Future for StampResponse {
+    type Output = Response;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Response> {
+        let mut res = match Pin::new(&mut self.scope).poll(cx) {
+            Poll::Ready(res) => res,
+            Poll::Pending => return Poll::Pending,
+        };
+
+        if !res.headers().contains_key(CLOCK_HEADER) {
+            let clock = {
+                let mut server = self.server.lock().unwrap_or_else(|e| e.into_inner());
+                server.observe_receive(&self.scope.clock());
+                server.clock().clone()
+            };
+
+            if let Ok(value) = HeaderValue::from_str(&to_header_value(&clock)) {
+                res.headers_mut().insert(CLOCK_HEADER, value);
+            }
+        }
+
+        Poll::Ready(res)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::future::Future;
+    use std::pin::pin;
+    use std::task::{Context, Poll, Waker};
+
+    use axum::extract::FromRequestParts;
+    use axum::http::{Request, StatusCode};
+    use axum::response::IntoResponse;
+
+    use super::{RequestClock, ResponseClock};
+    use clock::{VectorClock, TemporalRelation};
+    use context::{to_header_value, from_header_value};
+    use header::CLOCK_HEADER;
+
+    fn extract(request: Request<()>) -> Result<RequestClock, (StatusCode, String)> {
+        let (mut parts, _) = request.into_parts();
+        let future = pin!(RequestClock::from_request_parts(&mut parts, &()));
+
+        match future.poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(result) => result,
+            Poll::Pending => panic!("extractor should not block"),
+        }
+    }
+
+    #[test]
+    fn test_extract() {
+        let request = Request::builder().header(CLOCK_HEADER, "a:2,b%3Ac:1").body(()).unwrap();
+        let expected = vec![("a".to_owned(), 2), ("b:c".to_owned(), 1)].into_iter().collect::<VectorClock<_>>();
+
+        assert_eq!(RequestClock(expected), extract(request).unwrap());
+    }
+
+    #[test]
+    fn test_extract_missing_and_malformed() {
+        assert_eq!(RequestClock(VectorClock::new()), extract(Request::new(())).unwrap());
+
+        let request = Request::builder().header(CLOCK_HEADER, "a:x").body(()).unwrap();
+
+        assert_eq!(StatusCode::BAD_REQUEST, extract(request).unwrap_err().0);
+    }
+
+    #[test]
+    fn test_respond() {
+        let request = RequestClock(VectorClock::new().incremented("a".to_owned()));
+        let local = VectorClock::new().incremented("s".to_owned());
+
+        let response = (request.respond("s", &local), "ok").into_response();
+
+        let value = response.headers()[CLOCK_HEADER].to_str().unwrap();
+        let expected = vec![("a".to_owned(), 1), ("s".to_owned(), 2)].into_iter().collect::<VectorClock<_>>();
+
+        assert!(value.starts_with("v1."));
+        assert_eq!(expected, from_header_value(value).unwrap());
+    }
+
+    #[test]
+    fn test_response_clock_round_trips() {
+        let clock = VectorClock::new().incremented("x,y".to_owned());
+        let response = (ResponseClock(clock.clone()), ()).into_response();
+
+        let request = Request::builder().header(CLOCK_HEADER, response.headers()[CLOCK_HEADER].clone()).body(()).unwrap();
+
+        assert_eq!(RequestClock(clock), extract(request).unwrap());
+    }
+
+    #[test]
+    fn test_clock_layer() {
+        use std::future;
+
+        use axum::Router;
+        use axum::body::Body;
+        use axum::routing::get;
+        use tower_service::Service;
+
+        use super::clock_layer;
+        use scope::merge_current;
+
+        fn handler() -> impl Future<Output=&'static str> {
+            future::poll_fn(|_| {
+                assert!(merge_current(&VectorClock::new().incremented("db".to_owned())));
+                Poll::Ready("ok")
+            })
+        }
+
+        fn call(app: &mut Router, request: Request<Body>) -> axum::response::Response {
+            let future = pin!(app.call(request));
+
+            match future.poll(&mut Context::from_waker(Waker::noop())) {
+                Poll::Ready(result) => result.unwrap(),
+                Poll::Pending => panic!("service should not block"),
+            }
+        }
+
+        let mut app = Router::new()
+            .route("/", get(handler))
+            .route("/explicit", get(|| future::ready((ResponseClock(VectorClock::new()), "ok"))))
+            .layer(clock_layer("s"));
+
+        let clock = vec![("a".to_owned(), 2)].into_iter().collect::<VectorClock<_>>();
+        let request = Request::builder().uri("/").header(CLOCK_HEADER, to_header_value(&clock)).body(Body::empty()).unwrap();
+        let response = call(&mut app, request);
+
+        let expected = vec![("a".to_owned(), 2), ("db".to_owned(), 1), ("s".to_owned(), 1)].into_iter().collect::<VectorClock<_>>();
+        assert_eq!(expected, from_header_value(response.headers()[CLOCK_HEADER].to_str().unwrap()).unwrap());
+
+        let request = Request::builder().uri("/explicit").body(Body::empty()).unwrap();
+        let response = call(&mut app, request);
+        assert_eq!(to_header_value(&VectorClock::<String>::new()), response.headers()[CLOCK_HEADER]);
+
+        let request = Request::builder().uri("/").header(CLOCK_HEADER, "a:x").body(Body::empty()).unwrap();
+        assert_eq!(StatusCode::BAD_REQUEST, call(&mut app, request).status());
+
+        let mut app = Router::new().route("/", get(|| future::ready("ok"))).layer(clock_layer("s"));
+        let mut stamp = || {
+            let response = call(&mut app, Request::builder().uri("/").body(Body::empty()).unwrap());
+            from_header_value::<String>(response.headers()[CLOCK_HEADER].to_str().unwrap()).unwrap()
+        };
+
+        let first = stamp();
+        let second = stamp();
+
+        assert_eq!((1, 2), (first.get(&"s".to_owned()), second.get(&"s".to_owned())));
+        assert_eq!(TemporalRelation::Caused, first.temporal_relation(&second));
+    }
+}
@@ -0,0 +1,147 @@
+use std::fmt::{self, Display};
+use std::hash::Hash;
+
+use clock::VectorClock;
+use counter::Counter;
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum CounterBase {
+    Decimal,
+    Hex,
+}
+
+/// Display a clock with a configurable layout, e.g. for logging.
+///
+/// Entries are written as `host:counter` separated by commas. Created by
+/// `VectorClock::format`.
+#[derive(Debug, Clone)]
+pub struct ClockFormat<'a, HostType: Hash + Eq + 'a, CounterType: 'a = u64> {
+    clock: &'a VectorClock<HostType, CounterType>,
+    sorted: bool,
+    max_entries: Option<usize>,
+    host_width: Option<usize>,
+    base: CounterBase,
+}
+
+impl<HostType: Clone + Hash + Eq, CounterType: Counter> VectorClock<HostType, CounterType> {
+    pub fn format(&self) -> ClockFormat<'_, HostType, CounterType> {
+        ClockFormat {
+            clock: self,
+            sorted: true,
+            max_entries: None,
+            host_width: None,
+            base: CounterBase::Decimal,
+        }
+    }
+}
+
+impl<'a, HostType: Clone + Hash + Eq, CounterType: Counter> ClockFormat<'a, HostType, CounterType> {
+    /// Sort entries by host (the default), or write them in the clock's
+    /// internal order, which is cheaper for large clocks.
+    pub fn sorted(mut self, sorted: bool) -> Self {
+        self.sorted = sorted;
+        self
+    }
+
+    /// Write at most `n` entries, followed by a count of the omitted ones.
+    pub fn max_entries(mut self, n: usize) -> Self {
+        self.max_entries = Some(n);
+        self
+    }
+
+    /// Shorten host names to at most `width` characters.
+    pub fn truncate_hosts(mut self, width: usize) -> Self {
+        self.host_width = Some(width);
+        self
+    }
+
+    pub fn base(mut self, base: CounterBase) -> Self {
+        self.base = base;
+        self
+    }
+}
+
+impl<'a, HostType, CounterType> Display for ClockFormat<'a, HostType, CounterType>
+    where HostType: Clone + Hash + Eq + Ord + Display, CounterType: Counter
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut entries: Vec<_> = self.clock.iter().collect();
+
+        if self.sorted {
+            entries.sort();
+        }
+
+        let shown = self.max_entries.unwrap_or(entries.len());
+
+        for (i, (host, n)) in entries.iter().take(shown).enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+
+            match self.host_width {
+                Some(width) => {
+                    let host = host.to_string();
+
+                    if host.chars().count() > width {
+                        let short: String = host.chars().take(width.saturating_sub(1)).collect();
+                        write!(f, "{}…", short)?;
+                    }
+                    else {
+                        f.write_str(&host)?;
+                    }
+                },
+                None => write!(f, "{}", host)?,
+            }
+
+            match self.base {
+                CounterBase::Decimal => write!(f, ":{}", n.to_u64())?,
+                CounterBase::Hex => write!(f, ":{:#x}", n.to_u64())?,
+            }
+        }
+
+        if entries.len() > shown {
+            if shown > 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "…(+{} more)", entries.len() - shown)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::CounterBase;
+    use clock::VectorClock;
+
+    fn clock() -> VectorClock<&'static str> {
+        vec![("gamma", 3), ("alpha", 26), ("beta", 1)].into_iter().collect::<VectorClock<_>>()
+    }
+
+    #[test]
+    fn test_sorted() {
+        assert_eq!("alpha:26,beta:1,gamma:3", clock().format().to_string());
+    }
+
+    #[test]
+    fn test_max_entries() {
+        assert_eq!("alpha:26,…(+2 more)", clock().format().max_entries(1).to_string());
+        assert_eq!("…(+3 more)", clock().format().max_entries(0).to_string());
+        assert_eq!("alpha:26,beta:1,gamma:3", clock().format().max_entries(3).to_string());
+    }
+
+    #[test]
+    fn test_truncate_and_base() {
+        assert_eq!("alp…:0x1a,beta:0x1,gam…:0x3",
+                   clock().format().truncate_hosts(4).base(CounterBase::Hex).to_string());
+    }
+
+    #[test]
+    fn test_unsorted_contains_all() {
+        let s = clock().format().sorted(false).to_string();
+
+        assert_eq!(3, s.split(',').count());
+        assert!(s.contains("beta:1"));
+    }
+}
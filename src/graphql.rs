@@ -0,0 +1,82 @@
+use async_graphql::{InputValueError, InputValueResult, Name, Number, Scalar, ScalarType, Value};
+use async_graphql::indexmap::IndexMap;
+
+use clock::VectorClock;
+
+/// Exposes `VectorClock<String>` as the `VectorClock` GraphQL scalar.
+///
+/// Clocks are represented as an object mapping each host to its counter,
+/// with hosts in sorted order, e.g. `{"A": 3, "B": 1}`.
+#[Scalar(name = "VectorClock")]
+impl ScalarType for VectorClock<String> {
+    fn parse(value: Value) -> InputValueResult<Self> {
+        let object = match value {
+            Value::Object(object) => object,
+            other => return Err(InputValueError::expected_type(other)),
+        };
+
+        let mut entries = Vec::with_capacity(object.len());
+
+        for (host, n) in object {
+            match n {
+                Value::Number(ref number) if number.is_u64() => {
+                    entries.push((host.to_string(), number.as_u64().unwrap_or(0)));
+                },
+                _ => return Err(InputValueError::custom(
+                    format!("counter for host \"{}\" is not a non-negative integer", host))),
+            }
+        }
+
+        Ok(entries.into_iter().collect())
+    }
+
+    fn is_valid(value: &Value) -> bool {
+        match *value {
+            Value::Object(ref object) => object.values().all(|n| match *n {
+                Value::Number(ref number) => number.is_u64(),
+                _ => false,
+            }),
+            _ => false,
+        }
+    }
+
+    fn to_value(&self) -> Value {
+        let mut entries: Vec<_> = self.iter().collect();
+        entries.sort();
+
+        Value::Object(entries.into_iter()
+            .map(|(host, &n)| (Name::new(host), Value::Number(Number::from(n))))
+            .collect::<IndexMap<_, _>>())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use async_graphql::{Name, Number, ScalarType, Value};
+    use async_graphql::indexmap::IndexMap;
+
+    use clock::VectorClock;
+
+    #[test]
+    fn test_round_trip() {
+        let c = VectorClock::new()
+            .incremented("B".to_owned())
+            .incremented("A".to_owned())
+            .incremented("A".to_owned());
+
+        let value = c.to_value();
+
+        assert_eq!("{A: 2, B: 1}", value.to_string());
+        assert_eq!(c, <VectorClock<String> as ScalarType>::parse(value).unwrap());
+    }
+
+    #[test]
+    fn test_rejects_negative_counters() {
+        let mut object = IndexMap::new();
+        object.insert(Name::new("A"), Value::Number(Number::from(-1)));
+        let value = Value::Object(object);
+
+        assert!(!<VectorClock<String> as ScalarType>::is_valid(&value));
+        assert!(<VectorClock<String> as ScalarType>::parse(value).is_err());
+    }
+}
@@ -0,0 +1,69 @@
+use std::fmt::Display;
+use std::hash::Hash;
+use std::str::FromStr;
+
+use clock::VectorClock;
+use context::{to_header_value, from_header_value};
+use error::Result;
+
+/// The header carrying a clock between HTTP clients and servers.
+pub const CLOCK_HEADER: &str = "x-vector-clock";
+
+/// Format a clock for `CLOCK_HEADER`, as a versioned causal context (see
+/// the `context` module).
+#[deprecated(note = "use `context::to_header_value` instead")]
+pub fn encode_header<HostType>(clock: &VectorClock<HostType>) -> String
+    where HostType: Clone + Hash + Eq + Display
+{
+    to_header_value(clock)
+}
+
+/// Parse a `CLOCK_HEADER` value, either a causal context or a clock in the
+/// older `host:counter` text form.
+#[deprecated(note = "use `context::from_header_value` instead")]
+pub fn decode_header<HostType>(value: &str) -> Result<VectorClock<HostType>>
+    where HostType: Clone + Hash + Eq + FromStr
+{
+    from_header_value(value)
+}
+
+#[cfg(test)]
+mod test {
+    use clock::VectorClock;
+    use context::{to_header_value, from_header_value};
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_round_trip() {
+        use super::{encode_header, decode_header};
+
+        let clock = VectorClock::new().incremented("a,b".to_owned()).incremented("c".to_owned());
+
+        assert_eq!(clock, decode_header(&encode_header(&clock)).unwrap());
+        assert!(decode_header::<String>("a:x").is_err());
+    }
+
+    #[test]
+    fn test_encoding_is_printable_ascii() {
+        for host in &["zürich-1", "db 1", "tab\there", "bell\u{7}", "%,:"] {
+            let clock = VectorClock::new().incremented(host.to_string());
+            let value = to_header_value(&clock);
+
+            assert!(value.bytes().all(|b| (0x21..=0x7E).contains(&b)), "{:?} for {:?}", value, host);
+            assert_eq!(clock, from_header_value(&value).unwrap());
+        }
+    }
+
+    #[cfg(feature = "reqwest")]
+    #[test]
+    fn test_round_trip_through_header_value() {
+        use http::HeaderValue;
+
+        for host in &["zürich-1", "db 1", "ctl\u{1}\r\n"] {
+            let clock = VectorClock::new().incremented(host.to_string()).incremented("b".to_owned());
+            let value = HeaderValue::from_str(&to_header_value(&clock)).unwrap();
+
+            assert_eq!(clock, from_header_value(value.to_str().unwrap()).unwrap());
+        }
+    }
+}
@@ -0,0 +1,235 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+
+use clock::VectorClock;
+use error::Result;
+use matrix::MatrixClock;
+use membership::{MembershipChange, MembershipView};
+use propagate::ClockTransport;
+
+#[derive(Debug)]
+struct PeerBeat {
+    last_heard: Option<Instant>,
+    next_beat: Instant,
+}
+
+/// Exchanges clocks with peers periodically, so that stability and
+/// divergence keep advancing while application traffic is idle.
+///
+/// `beat` sends the local clock through a `ClockTransport` to the peers
+/// that are due a heartbeat, and clocks received from peers are reported
+/// with `received`. Both are recorded in a `MatrixClock`, which decides
+/// what is stable. Application messages that carry the clock can be
+/// reported with `sent`, which pushes the peer's next heartbeat back.
+#[derive(Debug)]
+pub struct Heartbeat<HostType: Hash + Eq> {
+    interval: Duration,
+    local: HostType,
+    matrix: MatrixClock<HostType>,
+    peers: HashMap<HostType, PeerBeat>,
+}
+
+impl<HostType: Clone + Hash + Eq> Heartbeat<HostType> {
+    /// A heartbeat for the node `local`, with no peers yet.
+    pub fn new(local: HostType, interval: Duration) -> Heartbeat<HostType> {
+        Heartbeat {
+            interval,
+            matrix: MatrixClock::with_peers(vec![local.clone()]),
+            local,
+            peers: HashMap::new(),
+        }
+    }
+
+    /// Start tracking `peer`. Its first heartbeat is due immediately.
+    pub fn add_peer(&mut self, peer: HostType, now: Instant) {
+        if self.peers.contains_key(&peer) {
+            return;
+        }
+
+        self.matrix.update(peer.clone(), &VectorClock::new());
+        self.peers.insert(peer, PeerBeat {
+            last_heard: None,
+            next_beat: now,
+        });
+    }
+
+    pub fn remove_peer(&mut self, peer: &HostType) {
+        if self.peers.remove(peer).is_some() {
+            self.matrix.remove_peer(peer);
+        }
+    }
+
+    /// Add and remove peers according to the changes reported by `view`.
+    pub fn follow<View>(&mut self, view: &mut View, now: Instant)
+        where View: MembershipView<Peer=HostType>
+    {
+        for change in view.changes() {
+            match change {
+                MembershipChange::Joined(peer) => self.add_peer(peer, now),
+                MembershipChange::Left(peer) => self.remove_peer(&peer),
+            }
+        }
+    }
+
+    /// Send `local`, the local clock, to the peers that are due a
+    /// heartbeat, and schedule their next one.
+    ///
+    /// Stops at the first send that fails, leaving that peer and any not
+    /// reached yet due.
+    pub fn beat<Transport>(&mut self, local: &VectorClock<HostType>, transport: &mut Transport, now: Instant)
+        -> Result<()>
+        where Transport: ClockTransport<HostType, HostType>
+    {
+        let interval = self.interval;
+
+        self.observe_local(local);
+
+        for (peer, state) in self.peers.iter_mut().filter(|(_, state)| now >= state.next_beat) {
+            transport.send_clock(peer, local)?;
+            state.next_beat = now + interval;
+        }
+
+        Ok(())
+    }
+
+    /// Report that the local clock reached `peer` some other way.
+    pub fn sent(&mut self, peer: &HostType, now: Instant) {
+        if let Some(state) = self.peers.get_mut(peer) {
+            state.next_beat = now + self.interval;
+        }
+    }
+
+    /// Report a clock received from `peer`, by heartbeat or otherwise.
+    pub fn received(&mut self, peer: &HostType, clock: &VectorClock<HostType>, now: Instant) {
+        if let Some(state) = self.peers.get_mut(peer) {
+            self.matrix.update(peer.clone(), clock);
+            state.last_heard = Some(now);
+        }
+    }
+
+    /// Record the local clock without sending it, e.g. after local events.
+    /// `beat` does this too.
+    pub fn observe_local(&mut self, local: &VectorClock<HostType>) {
+        self.matrix.update(self.local.clone(), local);
+    }
+
+    /// The entries every peer and the local node are known to have seen.
+    /// Events covered by it are stable and can be garbage collected.
+    pub fn stable(&self) -> VectorClock<HostType> {
+        self.matrix.min_clock()
+    }
+
+    /// What each peer and the local node are known to have seen, e.g. for
+    /// `MatrixClock::collect_stable`.
+    pub fn matrix(&self) -> &MatrixClock<HostType> {
+        &self.matrix
+    }
+
+    /// How far `peer`'s last known clock is from the local one, as measured
+    /// by `VectorClock::distance`.
+    pub fn divergence(&self, peer: &HostType, local: &VectorClock<HostType>) -> Option<u64> {
+        self.peers.get(peer).and_then(|_| self.matrix.peer_clock(peer)).map(|known| local.distance(known))
+    }
+
+    /// Peers not heard from since `cutoff`, including those never heard
+    /// from at all.
+    pub fn silent_since(&self, cutoff: Instant) -> Vec<HostType> {
+        self.peers.iter()
+            .filter(|&(_, s)| s.last_heard.is_none_or(|t| t < cutoff))
+            .map(|(peer, _)| peer.clone())
+            .collect()
+    }
+
+    /// The next time any peer is due a heartbeat.
+    pub fn next_due(&self) -> Option<Instant> {
+        self.peers.values().map(|s| s.next_beat).min()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use super::Heartbeat;
+    use clock::VectorClock;
+    use error::{Error, Result};
+    use propagate::ClockTransport;
+
+    #[derive(Default)]
+    struct Outbox {
+        sent: Vec<(&'static str, VectorClock<&'static str>)>,
+        fail: bool,
+    }
+
+    impl ClockTransport<&'static str, &'static str> for Outbox {
+        fn send_clock(&mut self, peer: &&'static str, clock: &VectorClock<&'static str>) -> Result<()> {
+            if self.fail {
+                return Err(Error::InvalidEntry("unreachable".to_owned()));
+            }
+
+            self.sent.push((*peer, clock.clone()));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_beat_schedule() {
+        let now = Instant::now();
+        let second = Duration::from_secs(1);
+        let local = VectorClock::new().incremented("me");
+        let mut h = Heartbeat::new("me", second);
+        let mut outbox = Outbox::default();
+
+        h.add_peer("p1", now);
+        h.add_peer("p2", now);
+
+        h.beat(&local, &mut outbox, now).unwrap();
+        outbox.sent.sort_by_key(|&(peer, _)| peer);
+        assert_eq!(vec![("p1", local.clone()), ("p2", local.clone())], outbox.sent);
+
+        outbox.sent.clear();
+        h.beat(&local, &mut outbox, now).unwrap();
+        assert!(outbox.sent.is_empty());
+
+        h.sent(&"p1", now + second / 2);
+
+        h.beat(&local, &mut outbox, now + second).unwrap();
+        assert_eq!(vec![("p2", local.clone())], outbox.sent);
+        assert_eq!(Some(now + second * 3 / 2), h.next_due());
+    }
+
+    #[test]
+    fn test_failed_send_stays_due() {
+        let now = Instant::now();
+        let mut h = Heartbeat::new("me", Duration::from_secs(1));
+        h.add_peer("p1", now);
+
+        assert!(h.beat(&VectorClock::new(), &mut Outbox { fail: true, ..Outbox::default() }, now).is_err());
+        assert_eq!(Some(now), h.next_due());
+    }
+    #[test]
+    fn test_stable_and_divergence() {
+        let now = Instant::now();
+        let mut h = Heartbeat::new("me", Duration::from_secs(1));
+        h.add_peer("p1", now);
+        h.add_peer("p2", now);
+
+        let local = vec![("A", 3), ("B", 2)].into_iter().collect::<VectorClock<_>>();
+        h.observe_local(&local);
+
+        h.received(&"p1", &vec![("A", 2), ("B", 2)].into_iter().collect::<VectorClock<_>>(), now);
+        assert_eq!(VectorClock::new(), h.stable());
+        assert_eq!(vec!["p2"], h.silent_since(now));
+
+        h.received(&"p2", &vec![("A", 3), ("B", 1)].into_iter().collect::<VectorClock<_>>(), now);
+
+        assert_eq!(vec![("A", 2), ("B", 1)].into_iter().collect::<VectorClock<_>>(), h.stable());
+        assert!(h.matrix().is_stable(&VectorClock::new().incremented("A")));
+        assert_eq!(Some(1), h.divergence(&"p1", &local));
+        assert!(h.silent_since(now).is_empty());
+
+        h.remove_peer(&"p2");
+        assert_eq!(vec![("A", 2), ("B", 2)].into_iter().collect::<VectorClock<_>>(), h.stable());
+    }
+}
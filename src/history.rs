@@ -0,0 +1,226 @@
+use std::fmt::{self, Display, Write};
+use std::hash::Hash;
+
+use clock::VectorClock;
+use error::{Error, Result};
+
+/// The kind of a history entry, as understood by Jepsen-style checkers.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum OpType {
+    Invoke,
+    Ok,
+    Fail,
+    Info,
+}
+
+impl OpType {
+    fn name(&self) -> &'static str {
+        match *self {
+            OpType::Invoke => "invoke",
+            OpType::Ok => "ok",
+            OpType::Fail => "fail",
+            OpType::Info => "info",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Operation<HostType: Hash + Eq> {
+    pub process: u64,
+    pub op_type: OpType,
+    pub f: String,
+    pub value: Option<String>,
+    pub clock: VectorClock<HostType>,
+}
+
+/// A log of client operations stamped with the clock they were made with.
+///
+/// Histories can be exported as EDN, for Jepsen and elle, or as JSON lines
+/// for other tooling. Both formats write one operation per line with an
+/// `index`, and include the clock as a map from host to counter.
+#[derive(Debug, Clone, Default)]
+pub struct History<HostType: Hash + Eq> {
+    ops: Vec<Operation<HostType>>,
+}
+
+impl<HostType: Clone + Hash + Eq + Ord + Display> History<HostType> {
+    pub fn new() -> History<HostType> {
+        History {
+            ops: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, op: Operation<HostType>) {
+        self.ops.push(op);
+    }
+
+    pub fn invoke(&mut self, process: u64, f: &str, value: Option<String>, clock: VectorClock<HostType>) {
+        self.record(process, OpType::Invoke, f, value, clock);
+    }
+
+    pub fn complete(&mut self, process: u64, op_type: OpType, f: &str, value: Option<String>,
+                    clock: VectorClock<HostType>)
+    {
+        self.record(process, op_type, f, value, clock);
+    }
+
+    pub fn ops(&self) -> &[Operation<HostType>] {
+        &self.ops
+    }
+
+    /// The history as EDN, failing if an operation's `f` can't be written
+    /// as a keyword.
+    pub fn to_edn(&self) -> Result<String> {
+        let mut out = String::new();
+
+        for (index, op) in self.ops.iter().enumerate() {
+            if !is_keyword_name(&op.f) {
+                return Err(Error::InvalidEntry(format!("{:?} is not a valid EDN keyword", op.f)));
+            }
+
+            let _ = write!(out, "{{:index {}, :process {}, :type :{}, :f :{}, :value {}, :clock {{",
+                           index, op.process, op.op_type.name(), op.f,
+                           op.value.as_ref().map_or("nil".to_owned(), quoted));
+            write_clock(&mut out, &op.clock, " ", ", ");
+            out.push_str("}}\n");
+        }
+
+        Ok(out)
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut out = String::new();
+
+        for (index, op) in self.ops.iter().enumerate() {
+            let _ = write!(out, "{{\"index\":{},\"process\":{},\"type\":\"{}\",\"f\":{},\"value\":{},\"clock\":{{",
+                           index, op.process, op.op_type.name(), quoted(&op.f),
+                           op.value.as_ref().map_or("null".to_owned(), quoted));
+            write_clock(&mut out, &op.clock, ":", ",");
+            out.push_str("}}\n");
+        }
+
+        out
+    }
+
+    fn record(&mut self, process: u64, op_type: OpType, f: &str, value: Option<String>,
+              clock: VectorClock<HostType>)
+    {
+        self.ops.push(Operation {
+            process,
+            op_type,
+            f: f.to_owned(),
+            value,
+            clock,
+        });
+    }
+}
+
+// Whether `name` can follow the `:` of an EDN keyword.
+fn is_keyword_name(name: &str) -> bool {
+    let symbol_char = |c: char| c.is_ascii_alphanumeric() || "*+!-_?<>=.".contains(c);
+    let mut chars = name.chars();
+
+    match chars.next() {
+        Some(c) if symbol_char(c) && !c.is_ascii_digit() && c != '.' => {},
+        _ => return false,
+    }
+
+    chars.all(|c| symbol_char(c) || c == ':' || c == '#' || c == '/')
+}
+
+pub(crate) fn write_clock<HostType>(out: &mut String, clock: &VectorClock<HostType>, kv_sep: &str, sep: &str)
+    where HostType: Clone + Hash + Eq + Ord + Display
+{
+    let mut entries: Vec<_> = clock.iter().collect();
+    entries.sort();
+
+    for (i, (host, n)) in entries.into_iter().enumerate() {
+        if i > 0 {
+            out.push_str(sep);
+        }
+        let _ = write!(out, "{}{}{}", quoted(host), kv_sep, n);
+    }
+}
+
+/// Quote a string using the escapes EDN and JSON have in common, with
+/// other control characters written as `\u00XX`.
+pub(crate) fn quoted<T: Display + ?Sized>(value: &T) -> String {
+    struct Escaped<'a>(&'a str);
+
+    impl<'a> Display for Escaped<'a> {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            for c in self.0.chars() {
+                match c {
+                    '"' => f.write_str("\\\"")?,
+                    '\\' => f.write_str("\\\\")?,
+                    '\n' => f.write_str("\\n")?,
+                    '\r' => f.write_str("\\r")?,
+                    '\t' => f.write_str("\\t")?,
+                    c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+                    c => f.write_char(c)?,
+                }
+            }
+            Ok(())
+        }
+    }
+
+    format!("\"{}\"", Escaped(&value.to_string()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{History, OpType};
+    use clock::VectorClock;
+    use error::Error;
+
+    fn history() -> History<&'static str> {
+        let c1 = VectorClock::new().incremented("n1");
+        let c2 = c1.incremented("n2");
+
+        let mut h = History::new();
+        h.invoke(0, "write", Some("x".to_owned()), c1);
+        h.complete(0, OpType::Ok, "write", Some("x\"y".to_owned()), c2);
+        h
+    }
+
+    #[test]
+    fn test_edn() {
+        assert_eq!("\
+{:index 0, :process 0, :type :invoke, :f :write, :value \"x\", :clock {\"n1\" 1}}
+{:index 1, :process 0, :type :ok, :f :write, :value \"x\\\"y\", :clock {\"n1\" 1, \"n2\" 1}}
+", history().to_edn().unwrap());
+    }
+
+    #[test]
+    fn test_edn_rejects_invalid_f() {
+        for f in &["", "1st", "read x", "read}", "a\"b"] {
+            let mut h = History::new();
+            h.invoke(0, f, None, VectorClock::new().incremented("n1"));
+
+            assert!(matches!(h.to_edn(), Err(Error::InvalidEntry(_))), "{:?}", f);
+        }
+
+        let mut h = History::new();
+        h.invoke(0, "txn/read-all?", None, VectorClock::new().incremented("n1"));
+
+        assert!(h.to_edn().is_ok());
+    }
+
+    #[test]
+    fn test_json() {
+        let json = history().to_json();
+        let lines: Vec<_> = json.lines().collect();
+
+        assert_eq!(r#"{"index":0,"process":0,"type":"invoke","f":"write","value":"x","clock":{"n1":1}}"#, lines[0]);
+        assert_eq!(r#"{"index":1,"process":0,"type":"ok","f":"write","value":"x\"y","clock":{"n1":1,"n2":1}}"#, lines[1]);
+    }
+
+    #[test]
+    fn test_control_characters() {
+        let mut h = History::new();
+        h.invoke(0, "write", Some("a\u{1}b\u{7f}".to_owned()), VectorClock::new().incremented("n\u{1f}"));
+
+        assert_eq!(r#"{"index":0,"process":0,"type":"invoke","f":"write","value":"a\u0001b\u007f","clock":{"n\u001f":1}}"#,
+                   h.to_json().trim_end());
+    }
+}
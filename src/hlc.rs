@@ -0,0 +1,217 @@
+use std::cmp;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use error::{Error, Result};
+
+/// A source of physical time for a `HybridLogicalClock`, in milliseconds.
+pub trait PhysicalClock {
+    fn now_millis(&self) -> u64;
+}
+
+/// The system wall clock, in milliseconds since the Unix epoch.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct SystemClock;
+
+impl PhysicalClock for SystemClock {
+    fn now_millis(&self) -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0)
+    }
+}
+
+/// A hybrid logical clock timestamp: the largest physical time seen, and
+/// a counter ordering events within the same millisecond.
+///
+/// Timestamps are totally ordered, first by `wall` and then by `logical`,
+/// and that order is consistent with causality.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone, Default)]
+pub struct HlcTimestamp {
+    pub wall: u64,
+    pub logical: u64,
+}
+
+impl HlcTimestamp {
+    pub fn new(wall: u64, logical: u64) -> HlcTimestamp {
+        HlcTimestamp {
+            wall,
+            logical,
+        }
+    }
+}
+
+/// A hybrid logical clock, following Kulkarni et al.
+///
+/// Unlike a `VectorClock`, it cannot detect concurrency, but its timestamps
+/// stay within a bounded distance of physical time and take constant space.
+#[derive(Debug, Clone)]
+pub struct HybridLogicalClock<Source: PhysicalClock = SystemClock> {
+    source: Source,
+    last: HlcTimestamp,
+    max_drift: Option<u64>,
+}
+
+impl HybridLogicalClock<SystemClock> {
+    pub fn new() -> HybridLogicalClock<SystemClock> {
+        HybridLogicalClock::with_source(SystemClock)
+    }
+}
+
+impl<Source: PhysicalClock> HybridLogicalClock<Source> {
+    pub fn with_source(source: Source) -> HybridLogicalClock<Source> {
+        HybridLogicalClock {
+            source,
+            last: HlcTimestamp::default(),
+            max_drift: None,
+        }
+    }
+
+    /// Reject remote timestamps more than `millis` ahead of local physical
+    /// time, so that a single peer with a bad clock cannot drag every other
+    /// clock forward.
+    pub fn with_max_drift(mut self, millis: u64) -> HybridLogicalClock<Source> {
+        self.max_drift = Some(millis);
+        self
+    }
+
+    /// The latest timestamp issued or observed.
+    pub fn last(&self) -> HlcTimestamp {
+        self.last
+    }
+
+    /// A timestamp for a local or send event.
+    ///
+    /// Panics if the logical counter would overflow, which can only happen
+    /// after observing a remote timestamp with a logical counter close to
+    /// its maximum; see `checked_now`.
+    pub fn now(&mut self) -> HlcTimestamp {
+        self.checked_now().expect("hybrid logical clock counter overflow")
+    }
+
+    /// A timestamp for a local or send event, or `Error::Overflow` with the
+    /// clock unchanged if the logical counter is already at its maximum.
+    pub fn checked_now(&mut self) -> Result<HlcTimestamp> {
+        let physical = self.source.now_millis();
+
+        if physical > self.last.wall {
+            self.last = HlcTimestamp::new(physical, 0);
+        }
+        else {
+            self.last.logical = self.last.logical.checked_add(1).ok_or(Error::Overflow)?;
+        }
+
+        Ok(self.last)
+    }
+
+    /// Observe the timestamp of a received message, returning the
+    /// timestamp of the receive event.
+    pub fn update(&mut self, remote: HlcTimestamp) -> Result<HlcTimestamp> {
+        let physical = self.source.now_millis();
+
+        if let Some(max) = self.max_drift {
+            if remote.wall > physical.saturating_add(max) {
+                return Err(Error::ClockDrift { drift: remote.wall - physical, max });
+            }
+        }
+
+        let wall = cmp::max(physical, cmp::max(self.last.wall, remote.wall));
+
+        let logical = if wall == self.last.wall && wall == remote.wall {
+            cmp::max(self.last.logical, remote.logical).checked_add(1)
+        }
+        else if wall == self.last.wall {
+            self.last.logical.checked_add(1)
+        }
+        else if wall == remote.wall {
+            remote.logical.checked_add(1)
+        }
+        else {
+            Some(0)
+        };
+
+        let logical = logical.ok_or(Error::Overflow)?;
+
+        self.last = HlcTimestamp::new(wall, logical);
+        Ok(self.last)
+    }
+}
+
+impl Default for HybridLogicalClock<SystemClock> {
+    fn default() -> HybridLogicalClock<SystemClock> {
+        HybridLogicalClock::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use super::{PhysicalClock, HybridLogicalClock, HlcTimestamp};
+    use error::Error;
+
+    #[derive(Clone)]
+    struct ManualClock(Rc<Cell<u64>>);
+
+    impl PhysicalClock for ManualClock {
+        fn now_millis(&self) -> u64 {
+            self.0.get()
+        }
+    }
+
+    #[test]
+    fn test_now_and_update() {
+        let time = ManualClock(Rc::new(Cell::new(100)));
+        let mut hlc = HybridLogicalClock::with_source(time.clone());
+
+        assert_eq!(HlcTimestamp::new(100, 0), hlc.now());
+        assert_eq!(HlcTimestamp::new(100, 1), hlc.now());
+
+        assert_eq!(HlcTimestamp::new(150, 4), hlc.update(HlcTimestamp::new(150, 3)).unwrap());
+        assert_eq!(HlcTimestamp::new(150, 5), hlc.now());
+
+        time.0.set(200);
+        assert_eq!(HlcTimestamp::new(200, 0), hlc.update(HlcTimestamp::new(120, 9)).unwrap());
+        assert!(hlc.now() > HlcTimestamp::new(200, 0));
+    }
+
+    #[test]
+    fn test_max_drift() {
+        let time = ManualClock(Rc::new(Cell::new(1000)));
+        let mut hlc = HybridLogicalClock::with_source(time).with_max_drift(50);
+
+        assert!(hlc.update(HlcTimestamp::new(1050, 0)).is_ok());
+        assert_eq!(Err(Error::ClockDrift { drift: 51, max: 50 }), hlc.update(HlcTimestamp::new(1051, 0)));
+        assert_eq!(HlcTimestamp::new(1050, 1), hlc.last());
+    }
+
+    #[test]
+    fn test_overflow() {
+        let time = ManualClock(Rc::new(Cell::new(u64::MAX - 10)));
+        let mut hlc = HybridLogicalClock::with_source(time).with_max_drift(50);
+
+        assert!(hlc.update(HlcTimestamp::new(u64::MAX, 0)).is_ok());
+        assert_eq!(Err(Error::Overflow), hlc.update(HlcTimestamp::new(u64::MAX, u64::MAX)));
+        assert_eq!(HlcTimestamp::new(u64::MAX, 1), hlc.last());
+    }
+
+    #[test]
+    fn test_checked_now_overflow() {
+        let time = ManualClock(Rc::new(Cell::new(1000)));
+        let mut hlc = HybridLogicalClock::with_source(time);
+
+        assert!(hlc.update(HlcTimestamp::new(1000, u64::MAX - 1)).is_ok());
+        assert_eq!(Err(Error::Overflow), hlc.checked_now());
+        assert_eq!(HlcTimestamp::new(1000, u64::MAX), hlc.last());
+    }
+
+    #[test]
+    #[should_panic(expected = "hybrid logical clock counter overflow")]
+    fn test_now_overflow_panics() {
+        let time = ManualClock(Rc::new(Cell::new(1000)));
+        let mut hlc = HybridLogicalClock::with_source(time);
+
+        hlc.update(HlcTimestamp::new(1000, u64::MAX - 1)).unwrap();
+        hlc.now();
+    }
+}
@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+use std::collections::hash_map::{DefaultHasher, Entry, RandomState};
+use std::env;
+use std::fmt;
+use std::fs;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use clock::VectorClock;
+
+static GENERATED: AtomicUsize = AtomicUsize::new(0);
+
+/// A randomly generated node identity, suitable as a `VectorClock` host.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone)]
+pub struct HostId(u128);
+
+impl HostId {
+    pub fn from_u128(id: u128) -> HostId {
+        HostId(id)
+    }
+
+    pub fn as_u128(&self) -> u128 {
+        self.0
+    }
+
+    /// A fully random 128-bit identity.
+    pub fn random() -> HostId {
+        HostId(((entropy(0) as u128) << 64) | entropy(1) as u128)
+    }
+
+    /// A random identity that only uses the low 64 bits.
+    pub fn random_64() -> HostId {
+        HostId(entropy(0) as u128)
+    }
+
+    /// An identity whose high 64 bits are derived from the hostname and
+    /// process id, and whose low 64 bits are random.
+    ///
+    /// IDs generated by the same process share a recognizable prefix, which
+    /// makes them easier to attribute when reading logs.
+    pub fn with_process_seed() -> HostId {
+        let mut hasher = DefaultHasher::new();
+        hostname().hash(&mut hasher);
+        process::id().hash(&mut hasher);
+
+        HostId(((hasher.finish() as u128) << 64) | entropy(0) as u128)
+    }
+}
+
+impl fmt::Display for HostId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:032x}", self.0)
+    }
+}
+
+fn entropy(salt: u64) -> u64 {
+    let mut hasher = RandomState::new().build_hasher();
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or(0);
+
+    hasher.write_u64(salt);
+    hasher.write_u128(now);
+    hasher.write_usize(GENERATED.fetch_add(1, Ordering::Relaxed));
+    hasher.write_u32(process::id());
+    hasher.finish()
+}
+
+fn hostname() -> String {
+    env::var("HOSTNAME").ok()
+        .or_else(|| fs::read_to_string("/etc/hostname").ok())
+        .map(|h| h.trim().to_owned())
+        .unwrap_or_default()
+}
+
+/// Two different sources claimed the same host identity.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Collision<HostType, Source> {
+    pub host: HostType,
+    pub first_source: Source,
+    pub first_counter: u64,
+    pub second_source: Source,
+    pub second_counter: u64,
+}
+
+/// Flags host identities that appear to be shared by more than one node.
+///
+/// A correctly behaving node ticks its own entry before every send, so each
+/// clock it sends carries a strictly larger counter for its host. When a
+/// clock said to come from `host` arrives through a different source than
+/// the previous one and its counter for `host` did not advance, two nodes
+/// are most likely using the same identity.
+#[derive(Debug)]
+pub struct CollisionDetector<HostType: Hash + Eq, Source> {
+    last_seen: HashMap<HostType, (Source, u64)>,
+}
+
+impl<HostType: Clone + Hash + Eq, Source: Clone + Eq> CollisionDetector<HostType, Source> {
+    pub fn new() -> CollisionDetector<HostType, Source> {
+        CollisionDetector {
+            last_seen: HashMap::new(),
+        }
+    }
+
+    /// Record that `source` delivered `clock` on behalf of `host`.
+    pub fn observe(&mut self, source: Source, host: &HostType, clock: &VectorClock<HostType>)
+        -> Option<Collision<HostType, Source>>
+    {
+        let n = clock.get(host);
+
+        match self.last_seen.entry(host.clone()) {
+            Entry::Vacant(e) => { e.insert((source, n)); },
+            Entry::Occupied(mut e) => {
+                let (ref last_source, last_n) = *e.get();
+
+                if n > last_n {
+                    e.insert((source, n));
+                }
+                else if *last_source != source {
+                    return Some(Collision {
+                        host: host.clone(),
+                        first_source: last_source.clone(),
+                        first_counter: last_n,
+                        second_source: source,
+                        second_counter: n,
+                    });
+                }
+            },
+        };
+
+        None
+    }
+
+    /// Like `observe`, but reports collisions to `hook` instead of
+    /// returning them.
+    pub fn observe_with<F>(&mut self, source: Source, host: &HostType,
+                           clock: &VectorClock<HostType>, hook: F)
+        where F: FnOnce(&Collision<HostType, Source>)
+    {
+        if let Some(collision) = self.observe(source, host, clock) {
+            hook(&collision);
+        }
+    }
+
+    pub fn forget(&mut self, host: &HostType) {
+        self.last_seen.remove(host);
+    }
+}
+
+impl<HostType: Clone + Hash + Eq, Source: Clone + Eq> Default for CollisionDetector<HostType, Source> {
+    fn default() -> CollisionDetector<HostType, Source> {
+        CollisionDetector::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{HostId, CollisionDetector};
+    use clock::VectorClock;
+
+    #[test]
+    fn test_random_ids_differ() {
+        assert!(HostId::random() != HostId::random());
+        assert!(HostId::random_64().as_u128() >> 64 == 0);
+    }
+
+    #[test]
+    fn test_process_seed_prefix() {
+        let a = HostId::with_process_seed();
+        let b = HostId::with_process_seed();
+
+        assert_eq!(a.as_u128() >> 64, b.as_u128() >> 64);
+        assert!(a != b);
+        assert_eq!(32, a.to_string().len());
+    }
+
+    #[test]
+    fn test_collision_detected() {
+        let mut detector = CollisionDetector::new();
+
+        let c1 = VectorClock::new().incremented("A");
+        let c2 = VectorClock::new().incremented("A");
+
+        assert!(detector.observe("10.0.0.1", &"A", &c1).is_none());
+
+        let collision = detector.observe("10.0.0.2", &"A", &c2).unwrap();
+
+        assert_eq!("A", collision.host);
+        assert_eq!("10.0.0.1", collision.first_source);
+        assert_eq!("10.0.0.2", collision.second_source);
+    }
+
+    #[test]
+    fn test_no_collision_when_advancing() {
+        let mut detector = CollisionDetector::new();
+
+        let c1 = VectorClock::new().incremented("A");
+        let c2 = c1.incremented("A");
+
+        assert!(detector.observe("10.0.0.1", &"A", &c1).is_none());
+        assert!(detector.observe("10.0.0.2", &"A", &c2).is_none());
+        assert!(detector.observe("10.0.0.2", &"A", &c2).is_none());
+    }
+}
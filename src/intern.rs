@@ -0,0 +1,288 @@
+use std::collections::HashSet;
+use std::hash::Hash;
+use std::sync::Arc;
+
+use clock::VectorClock;
+use counter::Counter;
+use error::Result;
+use wire::decode_with;
+
+/// Turns host names read by a deserializer into host values, so that
+/// repeated names can share a single allocation.
+///
+/// Accepted by `VectorClock::decode_interned` for the wire format, and by
+/// `InternedMap` and `InternedPairs` for serde's map and `pairs` formats.
+pub trait Interner {
+    type Host;
+
+    fn intern(&mut self, host: &str) -> Self::Host;
+}
+
+impl<F, HostType> Interner for F where F: FnMut(&str) -> HostType {
+    type Host = HostType;
+
+    fn intern(&mut self, host: &str) -> HostType {
+        self(host)
+    }
+}
+
+/// An interner that hands out shared `Arc<str>` hosts.
+#[derive(Debug, Default, Clone)]
+pub struct ArcStrInterner {
+    cache: HashSet<Arc<str>>,
+}
+
+impl ArcStrInterner {
+    pub fn new() -> ArcStrInterner {
+        ArcStrInterner {
+            cache: HashSet::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+}
+
+impl Interner for ArcStrInterner {
+    type Host = Arc<str>;
+
+    fn intern(&mut self, host: &str) -> Arc<str> {
+        if let Some(existing) = self.cache.get(host) {
+            return existing.clone();
+        }
+
+        let host: Arc<str> = Arc::from(host);
+        self.cache.insert(host.clone());
+        host
+    }
+}
+
+impl<HostType: Clone + Hash + Eq, CounterType: Counter> VectorClock<HostType, CounterType> {
+    /// `decode`, passing every host name through `interner`.
+    pub fn decode_interned<I>(bytes: &[u8], interner: &mut I) -> Result<VectorClock<HostType, CounterType>>
+        where I: Interner<Host = HostType>
+    {
+        decode_with(bytes, |host| Some(interner.intern(host)))
+    }
+}
+
+#[cfg(feature = "serde")]
+pub use self::seeds::{InternedMap, InternedPairs};
+
+#[cfg(feature = "serde")]
+mod seeds {
+    use std::fmt;
+    use std::hash::Hash;
+
+    use serde::de::{DeserializeSeed, Deserializer, Error, MapAccess, SeqAccess, Visitor};
+
+    use clock::VectorClock;
+    use super::Interner;
+
+    /// Deserializes a clock in the default map format, passing every host
+    /// name through an `Interner`.
+    pub struct InternedMap<'a, I: 'a> {
+        interner: &'a mut I,
+    }
+
+    impl<'a, I: Interner> InternedMap<'a, I> {
+        pub fn new(interner: &'a mut I) -> InternedMap<'a, I> {
+            InternedMap {
+                interner,
+            }
+        }
+    }
+
+    impl<'de, 'a, I> DeserializeSeed<'de> for InternedMap<'a, I>
+        where I: Interner, I::Host: Clone + Hash + Eq
+    {
+        type Value = VectorClock<I::Host>;
+
+        fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+            deserializer.deserialize_map(self)
+        }
+    }
+
+    impl<'de, 'a, I> Visitor<'de> for InternedMap<'a, I>
+        where I: Interner, I::Host: Clone + Hash + Eq
+    {
+        type Value = VectorClock<I::Host>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a map from host to counter")
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+            let mut entries = Vec::with_capacity(map.size_hint().unwrap_or(0));
+
+            while let Some(host) = map.next_key_seed(Host(&mut *self.interner))? {
+                entries.push((host, map.next_value()?));
+            }
+
+            Ok(entries.into_iter().collect())
+        }
+    }
+
+    /// Deserializes a clock in the `pairs` format, passing every host name
+    /// through an `Interner`.
+    pub struct InternedPairs<'a, I: 'a> {
+        interner: &'a mut I,
+    }
+
+    impl<'a, I: Interner> InternedPairs<'a, I> {
+        pub fn new(interner: &'a mut I) -> InternedPairs<'a, I> {
+            InternedPairs {
+                interner,
+            }
+        }
+    }
+
+    impl<'de, 'a, I> DeserializeSeed<'de> for InternedPairs<'a, I>
+        where I: Interner, I::Host: Clone + Hash + Eq
+    {
+        type Value = VectorClock<I::Host>;
+
+        fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+            deserializer.deserialize_seq(self)
+        }
+    }
+
+    impl<'de, 'a, I> Visitor<'de> for InternedPairs<'a, I>
+        where I: Interner, I::Host: Clone + Hash + Eq
+    {
+        type Value = VectorClock<I::Host>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a list of [host, counter] pairs")
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut entries = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+
+            while let Some(entry) = seq.next_element_seed(Pair(&mut *self.interner))? {
+                entries.push(entry);
+            }
+
+            Ok(entries.into_iter().collect())
+        }
+    }
+
+    struct Pair<'a, I: 'a>(&'a mut I);
+
+    impl<'de, 'a, I: Interner> DeserializeSeed<'de> for Pair<'a, I> {
+        type Value = (I::Host, u64);
+
+        fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+            deserializer.deserialize_tuple(2, self)
+        }
+    }
+
+    impl<'de, 'a, I: Interner> Visitor<'de> for Pair<'a, I> {
+        type Value = (I::Host, u64);
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a [host, counter] pair")
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let host = seq.next_element_seed(Host(self.0))?
+                .ok_or_else(|| A::Error::invalid_length(0, &"a [host, counter] pair"))?;
+            let n = seq.next_element()?
+                .ok_or_else(|| A::Error::invalid_length(1, &"a [host, counter] pair"))?;
+
+            Ok((host, n))
+        }
+    }
+
+    struct Host<'a, I: 'a>(&'a mut I);
+
+    impl<'de, 'a, I: Interner> DeserializeSeed<'de> for Host<'a, I> {
+        type Value = I::Host;
+
+        fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+            deserializer.deserialize_str(self)
+        }
+    }
+
+    impl<'de, 'a, I: Interner> Visitor<'de> for Host<'a, I> {
+        type Value = I::Host;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a host name")
+        }
+
+        fn visit_str<E: Error>(self, host: &str) -> Result<Self::Value, E> {
+            Ok(self.0.intern(host))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use super::{ArcStrInterner, Interner};
+    use clock::VectorClock;
+
+    #[test]
+    fn test_arc_str_interner_shares() {
+        let mut interner = ArcStrInterner::new();
+
+        let a = interner.intern("host-a");
+        let b = interner.intern("host-a");
+
+        assert!(Arc::ptr_eq(&a, &b));
+        assert_eq!(1, interner.len());
+    }
+
+    #[test]
+    fn test_decode_interned() {
+        let bytes = vec![("a", 1), ("b", 2)].into_iter().collect::<VectorClock<_>>().encode();
+        let mut interner = ArcStrInterner::new();
+
+        let first: VectorClock<_> = VectorClock::decode_interned(&bytes, &mut interner).unwrap();
+        let second = VectorClock::decode_interned(&bytes, &mut interner).unwrap();
+
+        assert_eq!(2, interner.len());
+        assert_eq!(first, second);
+        assert!(first.iter().all(|(h, _)| second.iter().any(|(o, _)| Arc::ptr_eq(h, o))));
+        assert!(VectorClock::<_>::decode_interned(&bytes[..3], &mut interner).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_interned_pairs() {
+        use serde::de::DeserializeSeed;
+        use serde_json;
+
+        use super::{InternedMap, InternedPairs};
+
+        let mut interner = ArcStrInterner::new();
+        let mut clocks = Vec::new();
+
+        for json in &[r#"[["a", 1], ["b", 2]]"#, r#"[["a", 3]]"#] {
+            let mut de = serde_json::Deserializer::from_str(json);
+            clocks.push(InternedPairs::new(&mut interner).deserialize(&mut de).unwrap());
+        }
+
+        assert_eq!(2, interner.len());
+        assert_eq!(vec![(Arc::from("a"), 3)].into_iter().collect::<VectorClock<_>>(), clocks[1]);
+
+        let mut upper = |h: &str| h.to_uppercase();
+        let mut de = serde_json::Deserializer::from_str(r#"[["a", 1]]"#);
+        let c = InternedPairs::new(&mut upper).deserialize(&mut de).unwrap();
+
+        assert_eq!(vec![("A".to_owned(), 1)], c.clone().into_iter().collect::<Vec<_>>());
+
+        let mut de = serde_json::Deserializer::from_str(r#"{"a": 4, "c": 1}"#);
+        let c = InternedMap::new(&mut interner).deserialize(&mut de).unwrap();
+
+        assert_eq!(3, interner.len());
+        assert_eq!(vec![(Arc::from("a"), 4), (Arc::from("c"), 1)].into_iter().collect::<VectorClock<_>>(), c);
+    }
+}
@@ -0,0 +1,472 @@
+use std::prelude::v1::*;
+use std::cmp;
+
+use clock::TemporalRelation;
+use error::{Error, Result};
+use wire::{write_varint, read_varint, take};
+
+/// Encoded trees nested deeper than this are rejected when decoding.
+const MAX_DEPTH: usize = 256;
+
+// Every counter in a stamp, summed along its path, fits in a u64: decoding
+// rejects trees that don't, and `checked_event` never creates one. Joins
+// and comparisons stay below the larger of their inputs' maximums.
+const IN_RANGE: &str = "interval tree clock counters stay within their maximum";
+
+/// The share of the identity space owned by a stamp.
+#[derive(Debug, PartialEq, Eq, Clone)]
+enum Id {
+    Leaf(bool),
+    Node(Box<Id>, Box<Id>),
+}
+
+/// The causal history of a stamp: a tree of counters over the identity
+/// space, where each node's counter is added to everything below it.
+#[derive(Debug, PartialEq, Eq, Clone)]
+enum Event {
+    Leaf(u64),
+    Node(u64, Box<Event>, Box<Event>),
+}
+
+/// An interval tree clock stamp, from "Interval Tree Clocks: A Logical
+/// Clock for Dynamic Systems" by Almeida, Baquero and Fonte.
+///
+/// Instead of a counter per host, each stamp owns part of an identity
+/// space that is split on `fork` and reunited on `join`, so participants
+/// can come and go without any globally known host IDs.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct IntervalTreeClock {
+    id: Id,
+    event: Event,
+}
+
+impl IntervalTreeClock {
+    /// The initial stamp, owning the whole identity space.
+    pub fn seed() -> IntervalTreeClock {
+        IntervalTreeClock {
+            id: Id::Leaf(true),
+            event: Event::Leaf(0),
+        }
+    }
+
+    /// Split this stamp's identity between two new stamps with the same
+    /// history, e.g. when spawning an actor.
+    pub fn fork(&self) -> (IntervalTreeClock, IntervalTreeClock) {
+        let (left, right) = self.id.split();
+
+        (
+            IntervalTreeClock { id: left, event: self.event.clone() },
+            IntervalTreeClock { id: right, event: self.event.clone() },
+        )
+    }
+
+    /// A copy of the history without any identity, to attach to messages.
+    /// Anonymous stamps can be compared and joined, but not ticked.
+    pub fn peek(&self) -> IntervalTreeClock {
+        IntervalTreeClock {
+            id: Id::Leaf(false),
+            event: self.event.clone(),
+        }
+    }
+
+    /// Record a local event. Anonymous stamps are left unchanged.
+    ///
+    /// Panics if a counter would overflow; see `checked_event`.
+    pub fn event(&mut self) {
+        self.checked_event().expect("interval tree clock counter overflow");
+    }
+
+    /// Record a local event, or fail with `Error::Overflow` and leave the
+    /// stamp unchanged if a counter is already at its maximum.
+    pub fn checked_event(&mut self) -> Result<()> {
+        if self.id == Id::Leaf(false) {
+            return Ok(());
+        }
+
+        let filled = fill(&self.id, &self.event)?;
+
+        self.event = if filled != self.event { filled } else { grow(&self.id, &self.event)?.0 };
+        Ok(())
+    }
+
+    /// Merge two stamps, combining both identities and histories, e.g.
+    /// when an actor retires into another or receives a message.
+    pub fn join(&self, other: &IntervalTreeClock) -> IntervalTreeClock {
+        IntervalTreeClock {
+            id: sum(&self.id, &other.id),
+            event: join(&self.event, &other.event).expect(IN_RANGE),
+        }
+    }
+
+    /// Whether this stamp's history is included in `other`'s.
+    pub fn leq(&self, other: &IntervalTreeClock) -> bool {
+        leq(&self.event, &other.event).expect(IN_RANGE)
+    }
+
+    pub fn is_anonymous(&self) -> bool {
+        self.id == Id::Leaf(false)
+    }
+
+    pub fn temporal_relation(&self, other: &IntervalTreeClock) -> TemporalRelation {
+        match (self.leq(other), other.leq(self)) {
+            (true, true) => TemporalRelation::Equal,
+            (true, false) => TemporalRelation::Caused,
+            (false, true) => TemporalRelation::EffectOf,
+            (false, false) => TemporalRelation::Concurrent,
+        }
+    }
+
+    /// Encode the stamp as bytes.
+    ///
+    /// Identity trees are written in prefix order with one byte per node:
+    /// 0 or 1 for leaves and 2 for inner nodes. Event trees follow, with a
+    /// tag byte of 0 for leaves and 1 for inner nodes, each followed by the
+    /// node's counter as a varint.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.id.encode(&mut out);
+        self.event.encode(&mut out);
+        out
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<IntervalTreeClock> {
+        let mut input = bytes;
+
+        let id = Id::decode(&mut input, 0)?;
+        let event = Event::decode(&mut input, 0)?;
+
+        if !input.is_empty() {
+            return Err(Error::Decode("trailing bytes after interval tree clock".to_owned()));
+        }
+
+        Ok(IntervalTreeClock { id, event })
+    }
+}
+
+impl Id {
+    fn node(left: Id, right: Id) -> Id {
+        match (left, right) {
+            (Id::Leaf(a), Id::Leaf(b)) if a == b => Id::Leaf(a),
+            (left, right) => Id::Node(Box::new(left), Box::new(right)),
+        }
+    }
+
+    fn split(&self) -> (Id, Id) {
+        match *self {
+            Id::Leaf(false) => (Id::Leaf(false), Id::Leaf(false)),
+            Id::Leaf(true) => (Id::node(Id::Leaf(true), Id::Leaf(false)), Id::node(Id::Leaf(false), Id::Leaf(true))),
+            Id::Node(ref l, ref r) => match (&**l, &**r) {
+                (&Id::Leaf(false), r) => {
+                    let (r1, r2) = r.split();
+                    (Id::node(Id::Leaf(false), r1), Id::node(Id::Leaf(false), r2))
+                },
+                (l, &Id::Leaf(false)) => {
+                    let (l1, l2) = l.split();
+                    (Id::node(l1, Id::Leaf(false)), Id::node(l2, Id::Leaf(false)))
+                },
+                (l, r) => (Id::node(l.clone(), Id::Leaf(false)), Id::node(Id::Leaf(false), r.clone())),
+            },
+        }
+    }
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        match *self {
+            Id::Leaf(owned) => out.push(owned as u8),
+            Id::Node(ref l, ref r) => {
+                out.push(2);
+                l.encode(out);
+                r.encode(out);
+            },
+        }
+    }
+
+    fn decode(input: &mut &[u8], depth: usize) -> Result<Id> {
+        if depth > MAX_DEPTH {
+            return Err(Error::Decode("interval tree clock nested too deeply".to_owned()));
+        }
+
+        match take(input, 1)?[0] {
+            0 => Ok(Id::Leaf(false)),
+            1 => Ok(Id::Leaf(true)),
+            2 => {
+                let l = Id::decode(input, depth + 1)?;
+                let r = Id::decode(input, depth + 1)?;
+                Ok(Id::node(l, r))
+            },
+            tag => Err(Error::Decode(format!("invalid identity tag {}", tag))),
+        }
+    }
+}
+
+fn sum(a: &Id, b: &Id) -> Id {
+    match (a, b) {
+        (&Id::Leaf(false), other) | (other, &Id::Leaf(false)) => other.clone(),
+        (Id::Node(l1, r1), Id::Node(l2, r2)) => Id::node(sum(l1, l2), sum(r1, r2)),
+        // Overlapping identities only arise from joining a stamp with
+        // itself or one of its ancestors; own the union.
+        _ => Id::Leaf(true),
+    }
+}
+
+impl Event {
+    fn node(n: u64, left: Event, right: Event) -> Result<Event> {
+        Ok(match (left, right) {
+            (Event::Leaf(a), Event::Leaf(b)) if a == b => Event::Leaf(add(n, a)?),
+            (left, right) => {
+                let m = cmp::min(left.min(), right.min());
+                Event::Node(add(n, m)?, Box::new(left.sink(m)), Box::new(right.sink(m)))
+            },
+        })
+    }
+
+    fn lift(&self, m: u64) -> Result<Event> {
+        Ok(match *self {
+            Event::Leaf(n) => Event::Leaf(add(n, m)?),
+            Event::Node(n, ref l, ref r) => Event::Node(add(n, m)?, l.clone(), r.clone()),
+        })
+    }
+
+    fn sink(self, m: u64) -> Event {
+        match self {
+            Event::Leaf(n) => Event::Leaf(n - m),
+            Event::Node(n, l, r) => Event::Node(n - m, l, r),
+        }
+    }
+
+    fn base(&self) -> u64 {
+        match *self {
+            Event::Leaf(n) | Event::Node(n, _, _) => n,
+        }
+    }
+
+    /// The smallest counter anywhere in the tree. In a normalized tree one
+    /// child of every node has a zero minimum.
+    fn min(&self) -> u64 {
+        self.base()
+    }
+
+    fn max(&self) -> Result<u64> {
+        match *self {
+            Event::Leaf(n) => Ok(n),
+            Event::Node(n, ref l, ref r) => add(n, cmp::max(l.max()?, r.max()?)),
+        }
+    }
+
+    fn children(&self) -> (Event, Event) {
+        match *self {
+            Event::Leaf(_) => (Event::Leaf(0), Event::Leaf(0)),
+            Event::Node(_, ref l, ref r) => ((**l).clone(), (**r).clone()),
+        }
+    }
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        match *self {
+            Event::Leaf(n) => {
+                out.push(0);
+                write_varint(out, n);
+            },
+            Event::Node(n, ref l, ref r) => {
+                out.push(1);
+                write_varint(out, n);
+                l.encode(out);
+                r.encode(out);
+            },
+        }
+    }
+
+    fn decode(input: &mut &[u8], depth: usize) -> Result<Event> {
+        if depth > MAX_DEPTH {
+            return Err(Error::Decode("interval tree clock nested too deeply".to_owned()));
+        }
+
+        match take(input, 1)?[0] {
+            0 => Ok(Event::Leaf(read_varint(input)?)),
+            1 => {
+                let n = read_varint(input)?;
+                let l = Event::decode(input, depth + 1)?;
+                let r = Event::decode(input, depth + 1)?;
+
+                let node = Event::node(n, l, r)?;
+                node.max()?;
+                Ok(node)
+            },
+            tag => Err(Error::Decode(format!("invalid event tag {}", tag))),
+        }
+    }
+}
+
+fn add(a: u64, b: u64) -> Result<u64> {
+    a.checked_add(b).ok_or(Error::Overflow)
+}
+
+fn leq(a: &Event, b: &Event) -> Result<bool> {
+    match (a, b) {
+        (&Event::Leaf(n1), _) => Ok(n1 <= b.base()),
+        (&Event::Node(n1, ref l1, ref r1), _) => {
+            let n2 = b.base();
+            let (l2, r2) = b.children();
+
+            Ok(n1 <= n2
+                && leq(&l1.lift(n1)?, &l2.lift(n2)?)?
+                && leq(&r1.lift(n1)?, &r2.lift(n2)?)?)
+        },
+    }
+}
+
+fn join(a: &Event, b: &Event) -> Result<Event> {
+    match (a, b) {
+        (&Event::Leaf(n1), &Event::Leaf(n2)) => Ok(Event::Leaf(cmp::max(n1, n2))),
+        _ if a.base() > b.base() => join(b, a),
+        _ => {
+            let (n1, n2) = (a.base(), b.base());
+            let (l1, r1) = a.children();
+            let (l2, r2) = b.children();
+            let d = n2 - n1;
+
+            Event::node(n1, join(&l1, &l2.lift(d)?)?, join(&r1, &r2.lift(d)?)?)
+        },
+    }
+}
+
+/// Raise the parts of `e` owned by `id` as far as possible without adding
+/// new events, simplifying the tree.
+fn fill(id: &Id, e: &Event) -> Result<Event> {
+    match (id, e) {
+        (&Id::Leaf(false), _) => Ok(e.clone()),
+        (&Id::Leaf(true), _) => Ok(Event::Leaf(e.max()?)),
+        (_, &Event::Leaf(_)) => Ok(e.clone()),
+        (Id::Node(il, ir), &Event::Node(n, ref el, ref er)) => match (&**il, &**ir) {
+            (&Id::Leaf(true), ir) => {
+                let er = fill(ir, er)?;
+                let el = Event::Leaf(cmp::max(el.max()?, er.min()));
+                Event::node(n, el, er)
+            },
+            (il, &Id::Leaf(true)) => {
+                let el = fill(il, el)?;
+                let er = Event::Leaf(cmp::max(er.max()?, el.min()));
+                Event::node(n, el, er)
+            },
+            (il, ir) => Event::node(n, fill(il, el)?, fill(ir, er)?),
+        },
+    }
+}
+
+/// Add an event in the part of the tree owned by `id`, returning the new
+/// tree and a cost used to prefer the growth that adds the fewest nodes.
+fn grow(id: &Id, e: &Event) -> Result<(Event, u64)> {
+    match (id, e) {
+        (&Id::Leaf(true), &Event::Leaf(n)) => Ok((Event::Leaf(add(n, 1)?), 0)),
+        (_, &Event::Leaf(n)) => {
+            let (e, cost) = grow(id, &Event::Node(n, Box::new(Event::Leaf(0)), Box::new(Event::Leaf(0))))?;
+            Ok((e, cost + 1000))
+        },
+        (Id::Node(il, ir), &Event::Node(n, ref el, ref er)) => {
+            let left = if **il == Id::Leaf(false) { None } else { Some(grow(il, el)?) };
+            let right = if **ir == Id::Leaf(false) { None } else { Some(grow(ir, er)?) };
+
+            let grown = match (left, right) {
+                (Some((el, cl)), Some((_, cr))) if cl < cr => (Event::node(n, el, (**er).clone())?, cl + 1),
+                (_, Some((er, cr))) => (Event::node(n, (**el).clone(), er)?, cr + 1),
+                (Some((el, cl)), None) => (Event::node(n, el, (**er).clone())?, cl + 1),
+                (None, None) => (e.clone(), 0),
+            };
+
+            // The grown tree's maximum may exceed the old one.
+            grown.0.max()?;
+            Ok(grown)
+        },
+        // Only anonymous identities reach here, and `event` skips those.
+        _ => Ok((e.clone(), 0)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::prelude::v1::*;
+
+    use super::IntervalTreeClock;
+    use clock::TemporalRelation;
+    use error::Error;
+    use wire::write_varint;
+
+    #[test]
+    fn test_fork_event_join() {
+        let (mut a, b) = IntervalTreeClock::seed().fork();
+        let (mut b, mut c) = b.fork();
+
+        a.event();
+        b.event();
+        b.event();
+
+        assert_eq!(TemporalRelation::Concurrent, a.temporal_relation(&b));
+        assert!(c.leq(&a) && c.leq(&b));
+
+        c = c.join(&b.peek());
+        c.event();
+
+        assert_eq!(TemporalRelation::Caused, b.temporal_relation(&c));
+        assert_eq!(TemporalRelation::Concurrent, a.temporal_relation(&c));
+
+        // Retiring `b` and `c` into `a` leaves a stamp that owns the whole
+        // space again and has seen everything.
+        let mut whole = a.join(&b).join(&c);
+        assert!(b.leq(&whole) && c.leq(&whole));
+
+        whole.event();
+        assert_eq!(IntervalTreeClock::seed().id, whole.id);
+        assert_eq!(TemporalRelation::EffectOf, whole.temporal_relation(&c));
+    }
+
+    #[test]
+    fn test_anonymous_stamps() {
+        let mut a = IntervalTreeClock::seed();
+        a.event();
+
+        let mut peeked = a.peek();
+        peeked.event();
+
+        assert!(peeked.is_anonymous());
+        assert_eq!(TemporalRelation::Equal, peeked.temporal_relation(&a));
+    }
+
+    #[test]
+    fn test_encoding() {
+        let (mut a, b) = IntervalTreeClock::seed().fork();
+        let (mut b, _) = b.fork();
+        a.event();
+        b.event();
+        b.event();
+        let c = b.join(&a.peek());
+
+        assert_eq!(c, IntervalTreeClock::from_bytes(&c.to_bytes()).unwrap());
+        assert_eq!(vec![1, 0, 0], IntervalTreeClock::seed().to_bytes());
+
+        let bytes = c.to_bytes();
+        assert!(IntervalTreeClock::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(IntervalTreeClock::from_bytes(&[bytes.clone(), vec![0]].concat()).is_err());
+        assert!(IntervalTreeClock::from_bytes(&[3, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn test_overflow() {
+        // Nodes at the maximum with leaves of 1 below them, which overflow
+        // when normalized into a leaf and when taking the maximum.
+        for leaves in &[[0, 1, 0, 1], [0, 1, 0, 0]] {
+            let mut bytes = vec![1, 1];
+            write_varint(&mut bytes, u64::MAX);
+            bytes.extend(leaves);
+
+            match IntervalTreeClock::from_bytes(&bytes) {
+                Err(Error::Overflow) => {},
+                other => panic!("expected an overflow, got {:?}", other),
+            }
+        }
+
+        let mut bytes = vec![1, 0];
+        write_varint(&mut bytes, u64::MAX);
+
+        let mut max = IntervalTreeClock::from_bytes(&bytes).unwrap();
+        assert_eq!(Err(Error::Overflow), max.checked_event());
+        assert_eq!(IntervalTreeClock::from_bytes(&bytes).unwrap(), max);
+    }
+}
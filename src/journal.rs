@@ -0,0 +1,236 @@
+use std::fmt::{Display, Write};
+use std::hash::Hash;
+use std::str::FromStr;
+
+use clock::VectorClock;
+use error::{Error, Result};
+use text::{escape_host, unescape_host};
+
+/// A clock persisted as a checkpoint followed by a chain of deltas.
+///
+/// Each change to the clock produces a one-line delta holding only the
+/// entries that advanced, which can be appended to a file instead of
+/// rewriting the whole clock. `compact` folds the chain into a new
+/// checkpoint line.
+///
+/// Lines start with `C` for a checkpoint or `D` for a delta, followed by
+/// `host:counter` entries separated by commas, with host names escaped as
+/// by the clock's `Display`. Recovery starts from the last checkpoint in
+/// the input.
+///
+/// A final line without its newline is what's left when the process dies
+/// during an append. `recover` ignores it, and only fails on malformed
+/// complete lines; cut the file back to `complete_len` before appending to
+/// it again.
+#[derive(Debug, Clone)]
+pub struct ClockJournal<HostType: Hash + Eq> {
+    base: VectorClock<HostType>,
+    deltas: Vec<VectorClock<HostType>>,
+    clock: VectorClock<HostType>,
+}
+
+impl<HostType> ClockJournal<HostType>
+    where HostType: Clone + Hash + Eq + Display + FromStr
+{
+    pub fn new(base: VectorClock<HostType>) -> ClockJournal<HostType> {
+        ClockJournal {
+            clock: base.clone(),
+            base,
+            deltas: Vec::new(),
+        }
+    }
+
+    pub fn clock(&self) -> &VectorClock<HostType> {
+        &self.clock
+    }
+
+    pub fn deltas(&self) -> &[VectorClock<HostType>] {
+        &self.deltas
+    }
+
+    /// Record that the clock is now `clock`, returning the delta line to
+    /// append, or `None` if nothing advanced.
+    pub fn record(&mut self, clock: &VectorClock<HostType>) -> Option<String> {
+        let delta = clock.iter()
+            .filter(|&(host, &n)| n > self.clock.get(host))
+            .map(|(host, &n)| (host.clone(), n))
+            .collect::<VectorClock<_>>();
+
+        if delta.is_empty() {
+            return None;
+        }
+
+        let line = encode_line('D', &delta);
+
+        self.clock.merge(&delta);
+        self.deltas.push(delta);
+
+        Some(line)
+    }
+
+    /// Fold the delta chain into the checkpoint, returning the new
+    /// checkpoint line.
+    pub fn compact(&mut self) -> String {
+        self.base = self.clock.clone();
+        self.deltas.clear();
+
+        encode_line('C', &self.base)
+    }
+
+    /// The checkpoint followed by every delta, one per line.
+    pub fn encode(&self) -> String {
+        let mut out = encode_line('C', &self.base);
+
+        for delta in &self.deltas {
+            out.push_str(&encode_line('D', delta));
+        }
+
+        out
+    }
+
+    /// Rebuild a journal by replaying the input from its last checkpoint,
+    /// ignoring an incomplete final line.
+    pub fn recover(input: &str) -> Result<ClockJournal<HostType>> {
+        let lines: Vec<&str> = input[..ClockJournal::<HostType>::complete_len(input)].lines()
+            .filter(|l| !l.is_empty())
+            .collect();
+        let start = lines.iter().rposition(|l| l.starts_with('C'))
+            .ok_or_else(|| Error::Decode("journal has no checkpoint".to_owned()))?;
+
+        let mut journal = ClockJournal::new(decode_entries(&lines[start][1..])?);
+
+        for line in &lines[start + 1..] {
+            if !line.starts_with('D') {
+                return Err(Error::Decode(format!("unexpected journal line {:?}", line)));
+            }
+
+            let delta = decode_entries(&line[1..])?;
+            journal.clock.merge(&delta);
+            journal.deltas.push(delta);
+        }
+
+        Ok(journal)
+    }
+
+    /// The length of `input` up to and including its last newline, i.e.
+    /// without an append that was cut short.
+    pub fn complete_len(input: &str) -> usize {
+        input.rfind('\n').map_or(0, |i| i + 1)
+    }
+}
+
+fn encode_line<HostType>(kind: char, clock: &VectorClock<HostType>) -> String
+    where HostType: Clone + Hash + Eq + Display
+{
+    let mut line = String::new();
+    line.push(kind);
+    line.push_str(&encode_entries(clock));
+    line.push('\n');
+    line
+}
+
+/// Format `clock` as `host:counter` entries separated by commas, with
+/// `%`, `,`, `:` and every byte outside printable ASCII in host names
+/// percent-escaped. The result is printable ASCII without spaces, so it
+/// can be used as a header value or a whitespace-separated log token.
+pub(crate) fn encode_entries<HostType>(clock: &VectorClock<HostType>) -> String
+    where HostType: Clone + Hash + Eq + Display
+{
+    let mut out = String::new();
+
+    for (i, (host, n)) in clock.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+
+        escape_host(&host.to_string(), &[], &mut out);
+        let _ = write!(out, ":{}", n);
+    }
+
+    out
+}
+
+pub(crate) fn decode_entries<HostType>(entries: &str) -> Result<VectorClock<HostType>>
+    where HostType: Clone + Hash + Eq + FromStr
+{
+    let mut clock = Vec::new();
+
+    for entry in entries.split(',').filter(|e| !e.is_empty()) {
+        let malformed = || Error::Decode(format!("malformed journal entry {:?}", entry));
+
+        let sep = entry.rfind(':').ok_or_else(malformed)?;
+        let n = entry[sep + 1..].parse().map_err(|_| malformed())?;
+        let host = unescape_host(&entry[..sep]).ok_or_else(malformed)?
+            .parse().map_err(|_| malformed())?;
+
+        clock.push((host, n));
+    }
+
+    Ok(clock.into_iter().collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::ClockJournal;
+    use clock::VectorClock;
+
+    #[test]
+    fn test_record_and_recover() {
+        let mut journal = ClockJournal::new(VectorClock::new().incremented("a".to_owned()));
+        let mut file = journal.encode();
+
+        let c1 = journal.clock().incremented("b".to_owned());
+        file.push_str(&journal.record(&c1).unwrap());
+
+        let c2 = c1.incremented("a".to_owned());
+        file.push_str(&journal.record(&c2).unwrap());
+
+        assert!(journal.record(&c1).is_none());
+
+        let recovered = ClockJournal::<String>::recover(&file).unwrap();
+
+        assert_eq!(c2, *recovered.clock());
+        assert_eq!(2, recovered.deltas().len());
+    }
+
+    #[test]
+    fn test_compact() {
+        let mut journal = ClockJournal::new(VectorClock::new());
+        let mut file = String::new();
+
+        let c = VectorClock::new().incremented("x:y,z".to_owned());
+        file.push_str(&journal.record(&c).unwrap());
+        file.push_str(&journal.compact());
+
+        assert_eq!("Dx%3Ay%2Cz:1\nCx%3Ay%2Cz:1\n", file);
+
+        let recovered = ClockJournal::<String>::recover(&file).unwrap();
+
+        assert_eq!(c, *recovered.clock());
+        assert!(recovered.deltas().is_empty());
+    }
+
+    #[test]
+    fn test_recover_errors() {
+        assert!(ClockJournal::<String>::recover("Da:1\n").is_err());
+        assert!(ClockJournal::<String>::recover("Ca:1\nXa:2\n").is_err());
+        assert!(ClockJournal::<String>::recover("Ca:one\n").is_err());
+        assert!(ClockJournal::<String>::recover("Ca%+1:1\n").is_err());
+        assert!(ClockJournal::<String>::recover("Ca%-1:1\n").is_err());
+        assert!(ClockJournal::<String>::recover("Ca:1\nDa:x\nDa:3\n").is_err());
+        assert!(ClockJournal::<String>::recover("Ca:1").is_err());
+    }
+
+    #[test]
+    fn test_torn_final_line() {
+        let file = "Ca:1\nDb:1\nDa:1";
+        let recovered = ClockJournal::<String>::recover(file).unwrap();
+
+        assert_eq!(vec![("a".to_owned(), 1), ("b".to_owned(), 1)].into_iter().collect::<VectorClock<_>>(),
+                   *recovered.clock());
+        assert_eq!("Ca:1\nDb:1\n", &file[..ClockJournal::<String>::complete_len(file)]);
+
+        assert!(ClockJournal::<String>::recover("Ca:1\nDb:1\nD%").is_ok());
+        assert_eq!(10, ClockJournal::<String>::complete_len("Ca:1\nDb:1\n"));
+    }
+}
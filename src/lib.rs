@@ -1,3 +1,285 @@
+//! Without the default `std` feature the crate is `no_std` and needs only
+//! `alloc`, with clocks backed by `hashbrown` through the `hashbrown`
+//! feature. Only the core clock types are available in that build; the
+//! networking, storage and analysis modules require `std`.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(not(any(feature = "std", feature = "hashbrown")))]
+compile_error!("the no_std build needs the `hashbrown` feature for its clock storage");
+#[cfg(all(not(feature = "std"), any(feature = "axum", feature = "coap-lite", feature = "proptest",
+                                    feature = "quickcheck", feature = "serde")))]
+compile_error!("the axum, coap-lite, proptest, quickcheck and serde integrations require the `std` feature");
+
+#[cfg(not(feature = "std"))]
+#[macro_use]
+extern crate alloc;
+#[cfg(feature = "arrow")]
+extern crate arrow_array;
+#[cfg(feature = "arrow")]
+extern crate arrow_schema;
+#[cfg(feature = "graphql")]
+extern crate async_graphql;
+#[cfg(feature = "axum")]
+extern crate axum;
+#[cfg(feature = "coap-lite")]
+extern crate coap_lite;
+#[cfg(feature = "riak")]
+extern crate flate2;
+#[cfg(all(feature = "hashbrown", not(feature = "std")))]
+extern crate hashbrown;
+#[cfg(feature = "reqwest")]
+extern crate http;
+#[cfg(feature = "parquet")]
+extern crate parquet;
+#[cfg(feature = "proptest")]
+extern crate proptest;
+#[cfg(feature = "quickcheck")]
+extern crate quickcheck;
+#[cfg(feature = "reqwest")]
+extern crate reqwest_middleware;
+#[cfg(feature = "serde")]
+extern crate serde;
+extern crate smallvec;
+#[cfg(feature = "tracing")]
+extern crate tracing;
+#[cfg(feature = "derive")]
+extern crate vectorclock_derive;
+
+#[cfg(all(test, feature = "serde"))]
+#[macro_use]
+extern crate serde_derive;
+#[cfg(test)]
+extern crate serde_json;
+#[cfg(all(test, feature = "axum"))]
+extern crate tower_service;
+
+/// Stands in for `std` in the `no_std` build, so that modules can keep
+/// importing from `std::` paths.
+#[cfg(not(feature = "std"))]
+mod std {
+    pub use core::{cmp, error, fmt, hash, iter, result, slice, str};
+
+    pub mod prelude {
+        pub mod v1 {
+            pub use core::prelude::v1::*;
+            pub use alloc::borrow::ToOwned;
+            pub use alloc::boxed::Box;
+            pub use alloc::string::{String, ToString};
+            pub use alloc::vec::Vec;
+        }
+    }
+
+    pub mod collections {
+        pub use hashbrown::{HashMap, hash_map};
+        #[cfg(test)]
+        pub use hashbrown::HashSet;
+    }
+}
+
+#[cfg(feature = "std")]
+mod analytics;
+#[cfg(any(feature = "proptest", feature = "quickcheck"))]
+pub mod arbitrary;
+#[cfg(feature = "arrow")]
+mod arrow;
+mod base64;
+#[cfg(feature = "std")]
+mod bounded;
 mod clock;
+#[cfg(feature = "std")]
+mod clock_set;
+#[cfg(feature = "std")]
+mod coap;
+mod compact;
+pub mod context;
+mod counter;
+#[cfg(feature = "std")]
+mod dag;
+mod delivery;
+mod delta;
+mod dot;
+mod dvv;
+#[cfg(feature = "std")]
+mod dynamic;
+mod error;
+#[cfg(feature = "std")]
+mod etag;
+mod events;
+#[cfg(feature = "axum")]
+mod extract;
+#[cfg(feature = "std")]
+mod format;
+#[cfg(feature = "graphql")]
+mod graphql;
+#[cfg(feature = "std")]
+mod header;
+#[cfg(feature = "std")]
+mod heartbeat;
+#[cfg(feature = "std")]
+mod history;
+#[cfg(feature = "std")]
+mod hlc;
+#[cfg(feature = "std")]
+mod host_id;
+#[cfg(feature = "std")]
+mod intern;
+mod itc;
+#[cfg(feature = "std")]
+mod journal;
+#[cfg(feature = "std")]
+mod log_check;
+mod matrix;
+#[cfg(feature = "std")]
+mod membership;
+#[cfg(feature = "std")]
+mod mqtt;
+mod mv_register;
+#[cfg(feature = "std")]
+mod owned;
+mod packed;
+#[cfg(feature = "serde")]
+pub mod pairs;
+mod process;
+#[cfg(feature = "std")]
+mod propagate;
+#[cfg(feature = "std")]
+mod reconcile;
+#[cfg(feature = "std")]
+mod recorder;
+#[cfg(feature = "std")]
+mod registry;
+#[cfg(feature = "std")]
+mod relation_cache;
+#[cfg(feature = "riak")]
+pub mod riak;
+#[cfg(feature = "std")]
+mod scheduler;
+#[cfg(feature = "std")]
+mod scope;
+#[cfg(feature = "serde")]
+mod serialize;
+#[cfg(feature = "std")]
+mod server;
+#[cfg(feature = "std")]
+mod shared;
+#[cfg(feature = "std")]
+mod snapshot;
+#[cfg(feature = "std")]
+mod sort_key;
+#[cfg(feature = "std")]
+mod staleness;
+mod stamped;
+#[cfg(feature = "std")]
+mod stats;
+#[cfg(feature = "std")]
+mod stream;
+#[cfg(feature = "std")]
+mod sync_plan;
+mod text;
+#[cfg(feature = "std")]
+mod trace;
+mod version_set;
+mod versioned;
+mod wire;
 
-pub use clock::VectorClock;
+#[cfg(feature = "std")]
+pub use analytics::{WindowReport, ConflictAnalyzer};
+#[cfg(feature = "arrow")]
+pub use arrow::{entries_batch, clocks_batch};
+#[cfg(feature = "parquet")]
+pub use arrow::write_parquet;
+#[cfg(feature = "std")]
+pub use bounded::{EvictionStrategy, BoundedVectorClock};
+pub use clock::{VectorClock, TemporalRelation, ClockEvent, Iter, IntoIter};
+#[cfg(feature = "std")]
+pub use clock_set::{ClockId, ClockSet};
+#[cfg(feature = "std")]
+pub use coap::{CLOCK_OPTION, encode_option, decode_option};
+pub use compact::CompactVectorClock;
+pub use counter::Counter;
+#[cfg(feature = "coap-lite")]
+pub use coap::{set_clock_option, clock_option};
+#[cfg(feature = "std")]
+pub use dag::{CausalDag, CriticalPath};
+pub use delivery::CausalBuffer;
+pub use delta::VectorClockDelta;
+pub use dot::Dot;
+pub use dvv::DottedVersionVector;
+#[cfg(feature = "std")]
+pub use dynamic::{ErasedHost, DynHost, DynVectorClock};
+pub use error::{Error, Result};
+#[cfg(feature = "std")]
+pub use etag::{to_etag, parse_etag, Precondition, precondition_holds, none_match_holds};
+#[cfg(feature = "axum")]
+pub use extract::{RequestClock, ResponseClock, ServerClock, clock_layer, clock_layer_with};
+#[cfg(feature = "std")]
+pub use format::{CounterBase, ClockFormat};
+#[cfg(feature = "std")]
+pub use header::CLOCK_HEADER;
+#[cfg(feature = "std")]
+#[allow(deprecated)]
+pub use header::{encode_header, decode_header};
+#[cfg(feature = "std")]
+pub use heartbeat::Heartbeat;
+#[cfg(feature = "std")]
+pub use history::{OpType, Operation, History};
+#[cfg(feature = "std")]
+pub use hlc::{PhysicalClock, SystemClock, HlcTimestamp, HybridLogicalClock};
+#[cfg(feature = "std")]
+pub use host_id::{HostId, Collision, CollisionDetector};
+#[cfg(feature = "std")]
+pub use intern::{Interner, ArcStrInterner};
+#[cfg(feature = "serde")]
+pub use intern::{InternedMap, InternedPairs};
+pub use itc::IntervalTreeClock;
+#[cfg(feature = "std")]
+pub use journal::ClockJournal;
+#[cfg(feature = "std")]
+pub use log_check::{Expectation, LoggedOp, MalformedLine, Violation, LogChecker};
+pub use matrix::MatrixClock;
+#[cfg(feature = "std")]
+pub use membership::{Incarnation, MembershipChange, MembershipView, LocalMembership, prune_departed};
+#[cfg(feature = "std")]
+pub use mqtt::{CLOCK_PROPERTY, set_clock_property, clock_property, TopicClocks};
+pub use mv_register::MVRegister;
+#[cfg(feature = "std")]
+pub use owned::{OwnedClock, OwnedHost};
+pub use process::ProcessClock;
+#[cfg(feature = "std")]
+pub use propagate::ClockTransport;
+#[cfg(feature = "reqwest")]
+pub use propagate::ClockSession;
+#[cfg(feature = "std")]
+pub use reconcile::{Reconciled, reconcile};
+#[cfg(feature = "std")]
+pub use recorder::{RecordedOp, Recorder, replay};
+#[cfg(feature = "std")]
+pub use registry::{NodeId, HostRecord, RegistryBackend, MemoryBackend, HostRegistry};
+#[cfg(feature = "std")]
+pub use relation_cache::{DigestedClock, RelationCache};
+#[cfg(feature = "std")]
+pub use scheduler::{SyncTask, SchedulerConfig, AntiEntropyScheduler};
+#[cfg(feature = "std")]
+pub use scope::{ClockScope, with_clock, current_clock, merge_current};
+#[cfg(feature = "std")]
+pub use server::{Assigned, ServerDots};
+#[cfg(feature = "std")]
+pub use shared::SharedVectorClock;
+#[cfg(feature = "std")]
+pub use snapshot::{LocalSnapshot, SnapshotNode, GlobalSnapshot};
+#[cfg(feature = "std")]
+pub use staleness::StalenessBound;
+pub use stamped::Stamped;
+#[cfg(feature = "std")]
+pub use stats::{ClockStats, TrackedClock};
+#[cfg(feature = "std")]
+pub use stream::{StreamStats, StreamAnalyzer};
+#[cfg(feature = "std")]
+pub use sync_plan::{SyncMessage, SyncPlanner, clock_digest};
+#[cfg(feature = "std")]
+pub use trace::{TraceEvent, CausalRecorder};
+pub use version_set::VersionSet;
+pub use versioned::CausallyVersioned;
+#[cfg(feature = "derive")]
+pub use vectorclock_derive::CausallyVersioned;
@@ -0,0 +1,207 @@
+use std::fmt;
+
+use clock::{VectorClock, TemporalRelation};
+use context::from_header_value;
+use error::Error;
+
+/// An ordering expectation between operations, identified by label.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Expectation {
+    /// Every `after` operation must causally follow every `before` one.
+    Follows { before: String, after: String },
+    /// Every `a` operation must be causally ordered, either way, with every
+    /// `b` one, i.e. they must never race.
+    Ordered { a: String, b: String },
+}
+
+impl fmt::Display for Expectation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Expectation::Follows { ref before, ref after } => write!(f, "{} must follow {}", after, before),
+            Expectation::Ordered { ref a, ref b } => write!(f, "{} and {} must be ordered", a, b),
+        }
+    }
+}
+
+/// An operation found in a log.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct LoggedOp {
+    pub process: String,
+    pub label: String,
+    pub clock: VectorClock<String>,
+    /// The line number in the process's log, starting at 1.
+    pub line: usize,
+}
+
+/// A line whose `vclock=` token could not be decoded.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct MalformedLine {
+    pub process: String,
+    /// The line number in the process's log, starting at 1.
+    pub line: usize,
+    pub error: Error,
+}
+
+/// A pair of operations that broke an expectation.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Violation {
+    pub expectation: Expectation,
+    pub first: LoggedOp,
+    pub second: LoggedOp,
+    pub relation: TemporalRelation,
+}
+
+/// Checks ordering expectations against clock-stamped logs from several
+/// processes.
+///
+/// A log line counts as an operation if it contains an `op=<label>` token
+/// and a `vclock=<clock>` token, with the clock encoded as in
+/// `CLOCK_HEADER`. Lines with a `vclock=` token that doesn't decode are
+/// kept aside in `malformed`, other lines are ignored.
+#[derive(Debug, Clone, Default)]
+pub struct LogChecker {
+    ops: Vec<LoggedOp>,
+    malformed: Vec<MalformedLine>,
+    expectations: Vec<Expectation>,
+}
+
+impl LogChecker {
+    pub fn new() -> LogChecker {
+        LogChecker::default()
+    }
+
+    pub fn expect(&mut self, expectation: Expectation) {
+        self.expectations.push(expectation);
+    }
+
+    /// Read the log of `process`, returning the number of operations found.
+    pub fn ingest(&mut self, process: &str, log: &str) -> usize {
+        let before = self.ops.len();
+
+        for (i, line) in log.lines().enumerate() {
+            match parse_line(line) {
+                Ok(Some((label, clock))) => self.ops.push(LoggedOp {
+                    process: process.to_owned(),
+                    label,
+                    clock,
+                    line: i + 1,
+                }),
+                Ok(None) => {},
+                Err(error) => self.malformed.push(MalformedLine {
+                    process: process.to_owned(),
+                    line: i + 1,
+                    error,
+                }),
+            }
+        }
+
+        self.ops.len() - before
+    }
+
+    pub fn ops(&self) -> &[LoggedOp] {
+        &self.ops
+    }
+
+    /// The lines that looked like operations but had an undecodable clock.
+    pub fn malformed(&self) -> &[MalformedLine] {
+        &self.malformed
+    }
+
+    pub fn check(&self) -> Vec<Violation> {
+        let mut violations = Vec::new();
+
+        for expectation in &self.expectations {
+            let (first, second) = match *expectation {
+                Expectation::Follows { ref before, ref after } => (before, after),
+                Expectation::Ordered { ref a, ref b } => (a, b),
+            };
+
+            for x in self.ops.iter().filter(|op| op.label == *first) {
+                for y in self.ops.iter().filter(|op| op.label == *second) {
+                    let relation = x.clock.temporal_relation(&y.clock);
+
+                    let ok = match *expectation {
+                        Expectation::Follows { .. } => relation == TemporalRelation::Caused,
+                        Expectation::Ordered { .. } => relation != TemporalRelation::Concurrent,
+                    };
+
+                    if !ok {
+                        violations.push(Violation {
+                            expectation: expectation.clone(),
+                            first: x.clone(),
+                            second: y.clone(),
+                            relation,
+                        });
+                    }
+                }
+            }
+        }
+
+        violations
+    }
+}
+
+fn parse_line(line: &str) -> Result<Option<(String, VectorClock<String>)>, Error> {
+    let mut label = None;
+    let mut clock = None;
+
+    for token in line.split_whitespace() {
+        if let Some(l) = token.strip_prefix("op=") {
+            label = Some(l.to_owned());
+        }
+        else if let Some(c) = token.strip_prefix("vclock=") {
+            clock = Some(from_header_value(c)?);
+        }
+    }
+
+    Ok(label.and_then(|label| clock.map(|clock| (label, clock))))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{LogChecker, Expectation, MalformedLine};
+    use clock::{VectorClock, TemporalRelation};
+    use context::to_header_value;
+    use error::Error;
+
+    #[test]
+    fn test_check() {
+        let mut checker = LogChecker::new();
+
+        assert_eq!(2, checker.ingest("p1", "starting\nINFO op=write vclock=p1:1\nINFO op=ack vclock=p1:2,p2:1\n"));
+        assert_eq!(1, checker.ingest("p2", "INFO op=read vclock=p2:1\n"));
+
+        checker.expect(Expectation::Follows { before: "write".to_owned(), after: "ack".to_owned() });
+        checker.expect(Expectation::Follows { before: "write".to_owned(), after: "read".to_owned() });
+        checker.expect(Expectation::Ordered { a: "read".to_owned(), b: "ack".to_owned() });
+
+        let violations = checker.check();
+
+        assert_eq!(1, violations.len());
+        assert_eq!("read must follow write", violations[0].expectation.to_string());
+        assert_eq!(("p1", 2), (&violations[0].first.process[..], violations[0].first.line));
+        assert_eq!("p2", violations[0].second.process);
+        assert_eq!(TemporalRelation::Concurrent, violations[0].relation);
+    }
+
+    #[test]
+    fn test_hosts_with_spaces() {
+        let clock = VectorClock::new().incremented("web 1".to_owned());
+        let mut checker = LogChecker::new();
+
+        assert_eq!(1, checker.ingest("p1", &format!("INFO op=write vclock={} done\n", to_header_value(&clock))));
+    }
+
+    #[test]
+    fn test_malformed_clock() {
+        let mut checker = LogChecker::new();
+
+        assert_eq!(1, checker.ingest("p1", "INFO op=write vclock=p1:x\nINFO op=ack vclock=p1:1\n"));
+
+        let malformed: &[MalformedLine] = checker.malformed();
+
+        assert_eq!(1, malformed.len());
+        assert_eq!(("p1", 1), (&malformed[0].process[..], malformed[0].line));
+        assert!(matches!(malformed[0].error, Error::Decode(_)));
+    }
+}
@@ -0,0 +1,133 @@
+use std::prelude::v1::*;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use clock::VectorClock;
+use stamped::Stamped;
+
+/// What each peer is known to have seen, for telling when events have
+/// reached every peer.
+///
+/// Each peer has a row holding the latest clock it reported, e.g. on the
+/// messages it sent. All peers, including the local node, should be
+/// registered so that the frontier doesn't advance past a peer that
+/// hasn't reported yet.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct MatrixClock<HostType: Hash + Eq> {
+    rows: HashMap<HostType, VectorClock<HostType>>,
+}
+
+impl<HostType: Clone + Hash + Eq> MatrixClock<HostType> {
+    pub fn new() -> MatrixClock<HostType> {
+        MatrixClock {
+            rows: HashMap::new(),
+        }
+    }
+
+    /// Start tracking `peers`, with nothing known to be seen by them yet.
+    pub fn with_peers<I: IntoIterator<Item=HostType>>(peers: I) -> MatrixClock<HostType> {
+        MatrixClock {
+            rows: peers.into_iter().map(|peer| (peer, VectorClock::new())).collect(),
+        }
+    }
+
+    /// Record that `peer` has seen everything in `clock`, adding the peer
+    /// if it isn't tracked yet.
+    pub fn update(&mut self, peer: HostType, clock: &VectorClock<HostType>) {
+        self.rows.entry(peer).or_default().merge(clock);
+    }
+
+    pub fn remove_peer(&mut self, peer: &HostType) -> Option<VectorClock<HostType>> {
+        self.rows.remove(peer)
+    }
+
+    pub fn peer_clock(&self, peer: &HostType) -> Option<&VectorClock<HostType>> {
+        self.rows.get(peer)
+    }
+
+    /// The stable frontier: the entries every tracked peer has seen.
+    pub fn min_clock(&self) -> VectorClock<HostType> {
+        let mut rows = self.rows.values();
+
+        let mut frontier = match rows.next() {
+            Some(row) => row.clone(),
+            None => return VectorClock::new(),
+        };
+
+        for row in rows {
+            frontier.intersect(row);
+        }
+
+        frontier
+    }
+
+    /// Whether the event stamped with `clock` has been seen by every peer.
+    pub fn is_stable(&self, clock: &VectorClock<HostType>) -> bool {
+        !self.rows.is_empty() && self.rows.values().all(|row| row.dominates(clock))
+    }
+
+    /// Remove the stable entries from `log` and return them, in log order.
+    /// No peer can still need them, so they're safe to garbage collect.
+    pub fn collect_stable<T>(&self, log: &mut Vec<Stamped<T, HostType>>) -> Vec<Stamped<T, HostType>> {
+        if self.rows.is_empty() {
+            return Vec::new();
+        }
+
+        let frontier = self.min_clock();
+        let (stable, pending) = log.drain(..).partition(|entry| frontier.dominates(&entry.clock));
+
+        *log = pending;
+        stable
+    }
+}
+
+impl<HostType: Clone + Hash + Eq> Default for MatrixClock<HostType> {
+    fn default() -> MatrixClock<HostType> {
+        MatrixClock::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::prelude::v1::*;
+    use super::MatrixClock;
+    use clock::VectorClock;
+    use stamped::Stamped;
+
+    #[test]
+    fn test_min_clock() {
+        let mut m = MatrixClock::with_peers(vec!["A", "B", "C"]);
+        let a2 = VectorClock::new().incremented("A").incremented("A");
+
+        m.update("A", &a2);
+        m.update("B", &a2.incremented("B"));
+        assert_eq!(VectorClock::new(), m.min_clock());
+
+        m.update("C", &VectorClock::new().incremented("A"));
+        assert_eq!(VectorClock::new().incremented("A"), m.min_clock());
+        assert!(m.is_stable(&VectorClock::new().incremented("A")));
+        assert!(!m.is_stable(&a2));
+
+        m.remove_peer(&"C");
+        assert_eq!(a2, m.min_clock());
+        assert!(!MatrixClock::new().is_stable(&VectorClock::<&str>::new()));
+    }
+
+    #[test]
+    fn test_collect_stable() {
+        let e1 = VectorClock::new().incremented("A");
+        let e2 = e1.incremented("A");
+        let e3 = e1.incremented("B");
+
+        let mut log = vec![Stamped::new(1, e1.clone()), Stamped::new(2, e2.clone()), Stamped::new(3, e3.clone())];
+        let mut m = MatrixClock::with_peers(vec!["A", "B"]);
+
+        m.update("A", &e2);
+        m.update("B", &e3);
+
+        let stable: Vec<_> = m.collect_stable(&mut log).into_iter().map(|e| e.value).collect();
+
+        assert_eq!(vec![1], stable);
+        assert_eq!(vec![2, 3], log.iter().map(|e| e.value).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,264 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::hash::Hash;
+use std::str::FromStr;
+
+use clock::VectorClock;
+use error::Error;
+
+/// One incarnation of a peer: the peer and the epoch it joined in.
+///
+/// Using incarnations rather than peers as clock hosts keeps a node that
+/// left and rejoined, e.g. after losing its disk, from being mistaken for
+/// its old self: its new entries start from zero under a new epoch. The
+/// text form is `peer@epoch`.
+#[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Clone)]
+pub struct Incarnation<Peer> {
+    pub peer: Peer,
+    pub epoch: u64,
+}
+
+impl<Peer> Incarnation<Peer> {
+    pub fn new(peer: Peer, epoch: u64) -> Incarnation<Peer> {
+        Incarnation {
+            peer,
+            epoch,
+        }
+    }
+}
+
+impl<Peer: fmt::Display> fmt::Display for Incarnation<Peer> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}@{}", self.peer, self.epoch)
+    }
+}
+
+impl<Peer: FromStr> FromStr for Incarnation<Peer> {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Incarnation<Peer>, Error> {
+        let malformed = || Error::Decode(format!("malformed incarnation {:?}", s));
+        let sep = s.rfind('@').ok_or_else(malformed)?;
+
+        Ok(Incarnation {
+            peer: s[..sep].parse().map_err(|_| malformed())?,
+            epoch: s[sep + 1..].parse().map_err(|_| malformed())?,
+        })
+    }
+}
+
+/// A change in cluster membership.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum MembershipChange<Peer> {
+    Joined(Peer),
+    Left(Peer),
+}
+
+/// A source of cluster membership, such as a gossip-based membership
+/// library.
+///
+/// Components that keep per-peer state poll `changes` to learn about peers
+/// joining and leaving. Adapters for membership libraries typically forward
+/// the library's join and leave callbacks into a `LocalMembership` and
+/// expose that; see `examples/membership_adapter.rs`.
+pub trait MembershipView {
+    type Peer;
+
+    /// The peers currently believed to be members.
+    fn peers(&self) -> Vec<Self::Peer>;
+
+    /// The epoch of the current incarnation of `peer`, or `None` if it is
+    /// not a member.
+    fn epoch(&self, peer: &Self::Peer) -> Option<u64>;
+
+    /// Changes since the last call, oldest first.
+    fn changes(&mut self) -> Vec<MembershipChange<Self::Peer>>;
+}
+
+/// A membership view maintained by explicit `join` and `leave` calls.
+///
+/// A peer that rejoins after leaving is given the next epoch.
+#[derive(Debug, Clone, Default)]
+pub struct LocalMembership<Peer> {
+    peers: Vec<Peer>,
+    epochs: Vec<(Peer, u64)>,
+    pending: VecDeque<MembershipChange<Peer>>,
+}
+
+impl<Peer: Clone + PartialEq> LocalMembership<Peer> {
+    pub fn new() -> LocalMembership<Peer> {
+        LocalMembership {
+            peers: Vec::new(),
+            epochs: Vec::new(),
+            pending: VecDeque::new(),
+        }
+    }
+
+    pub fn join(&mut self, peer: Peer) {
+        if self.peers.contains(&peer) {
+            return;
+        }
+
+        let epoch = match self.epochs.iter().find(|(p, _)| *p == peer) {
+            Some(&(_, epoch)) => epoch + 1,
+            None => 0,
+        };
+
+        self.join_at(peer, epoch);
+    }
+
+    /// Record that `peer` is a member in `epoch`, for membership services
+    /// that number incarnations themselves, like memberlist's incarnation
+    /// or chitchat's generation. A newer epoch of a current member is
+    /// reported as the old incarnation leaving and the new one joining;
+    /// older epochs are ignored.
+    pub fn join_at(&mut self, peer: Peer, epoch: u64) {
+        match self.epochs.iter().position(|(p, _)| *p == peer) {
+            Some(i) if self.epochs[i].1 > epoch || (self.epochs[i].1 == epoch && self.peers.contains(&peer)) => return,
+            Some(i) => self.epochs[i].1 = epoch,
+            None => self.epochs.push((peer.clone(), epoch)),
+        }
+
+        self.leave(&peer);
+        self.peers.push(peer.clone());
+        self.pending.push_back(MembershipChange::Joined(peer));
+    }
+
+    pub fn leave(&mut self, peer: &Peer) {
+        if let Some(i) = self.peers.iter().position(|p| p == peer) {
+            let peer = self.peers.remove(i);
+            self.pending.push_back(MembershipChange::Left(peer));
+        }
+    }
+}
+
+impl<Peer: Clone + PartialEq> MembershipView for LocalMembership<Peer> {
+    type Peer = Peer;
+
+    fn peers(&self) -> Vec<Peer> {
+        self.peers.clone()
+    }
+
+    fn epoch(&self, peer: &Peer) -> Option<u64> {
+        if !self.peers.contains(peer) {
+            return None;
+        }
+
+        self.epochs.iter().find(|(p, _)| p == peer).map(|&(_, epoch)| epoch)
+    }
+
+    fn changes(&mut self) -> Vec<MembershipChange<Peer>> {
+        self.pending.drain(..).collect()
+    }
+}
+
+/// Remove the entries of incarnations that are no longer members of `view`
+/// from `clock`: peers that left, and earlier epochs of peers that
+/// rejoined. Returns the removed incarnations.
+///
+/// Like any pruning this loses causal information, so it should only run
+/// once every replica has seen the departed incarnations' last events,
+/// e.g. when they are stable by a `MatrixClock`.
+pub fn prune_departed<Peer, View>(clock: &mut VectorClock<Incarnation<Peer>>, view: &View) -> Vec<Incarnation<Peer>>
+    where Peer: Clone + Hash + Eq, View: MembershipView<Peer=Peer>
+{
+    let mut removed = Vec::new();
+
+    clock.retain(|host, _| {
+        let live = view.epoch(&host.peer) == Some(host.epoch);
+
+        if !live {
+            removed.push(host.clone());
+        }
+
+        live
+    });
+
+    removed
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Incarnation, LocalMembership, MembershipView, MembershipChange, prune_departed};
+    use clock::VectorClock;
+    use error::Error;
+
+    #[test]
+    fn test_changes_are_drained() {
+        let mut m = LocalMembership::new();
+
+        m.join("a");
+        m.join("b");
+        m.join("a");
+        m.leave(&"a");
+
+        assert_eq!(vec!["b"], m.peers());
+        assert_eq!(vec![
+            MembershipChange::Joined("a"),
+            MembershipChange::Joined("b"),
+            MembershipChange::Left("a"),
+        ], m.changes());
+        assert!(m.changes().is_empty());
+    }
+
+    #[test]
+    fn test_rejoin_gets_new_epoch() {
+        let mut m = LocalMembership::new();
+
+        m.join("a");
+        assert_eq!(Some(0), m.epoch(&"a"));
+
+        m.leave(&"a");
+        assert_eq!(None, m.epoch(&"a"));
+
+        m.join("a");
+        assert_eq!(Some(1), m.epoch(&"a"));
+    }
+
+    #[test]
+    fn test_join_at() {
+        let mut m = LocalMembership::new();
+
+        m.join_at("a", 5);
+        m.join_at("a", 5);
+        m.join_at("a", 3);
+        m.changes();
+
+        m.join_at("a", 7);
+
+        assert_eq!(Some(7), m.epoch(&"a"));
+        assert_eq!(vec![MembershipChange::Left("a"), MembershipChange::Joined("a")], m.changes());
+    }
+
+    #[test]
+    fn test_prune_departed() {
+        let mut m = LocalMembership::new();
+        m.join("a");
+        m.join("b");
+
+        let mut clock = VectorClock::new()
+            .incremented(Incarnation::new("a", 0))
+            .incremented(Incarnation::new("b", 0));
+
+        m.leave(&"a");
+        m.leave(&"b");
+        m.join("b");
+        clock.increment(Incarnation::new("b", 1));
+
+        let mut removed = prune_departed(&mut clock, &m);
+        removed.sort();
+
+        assert_eq!(vec![Incarnation::new("a", 0), Incarnation::new("b", 0)], removed);
+        assert_eq!(vec![(Incarnation::new("b", 1), 1)], clock.into_iter().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_incarnation_text() {
+        let i = Incarnation::new("node@dc1".to_owned(), 3);
+
+        assert_eq!("node@dc1@3", i.to_string());
+        assert_eq!(Ok(i), "node@dc1@3".parse());
+        assert_eq!(Err(Error::Decode("malformed incarnation \"node\"".to_owned())), "node".parse::<Incarnation<String>>());
+        assert!("node@x".parse::<Incarnation<String>>().is_err());
+    }
+}
@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+
+use clock::{VectorClock, TemporalRelation};
+use error::Result;
+use context::{to_header_value, from_header_value};
+
+/// The MQTT v5 user property carrying a message's clock as a causal
+/// context, as in `CLOCK_HEADER`.
+pub const CLOCK_PROPERTY: &str = "vclock";
+
+/// Set the clock property in a list of user properties, replacing any
+/// earlier one. This is the `user_properties` field of rumqttc's v5
+/// publish properties; for paho, push the pair into its `Properties`.
+pub fn set_clock_property(properties: &mut Vec<(String, String)>, clock: &VectorClock<String>) {
+    properties.retain(|(k, _)| k != CLOCK_PROPERTY);
+    properties.push((CLOCK_PROPERTY.to_owned(), to_header_value(clock)));
+}
+
+/// The clock in a list of user properties, if there is one.
+pub fn clock_property(properties: &[(String, String)]) -> Option<Result<VectorClock<String>>> {
+    properties.iter()
+        .find(|(k, _)| k == CLOCK_PROPERTY)
+        .map(|(_, v)| from_header_value(v))
+}
+
+/// Tracks the latest clock seen on each topic on the subscriber side.
+#[derive(Debug, Clone, Default)]
+pub struct TopicClocks {
+    topics: HashMap<String, VectorClock<String>>,
+}
+
+impl TopicClocks {
+    pub fn new() -> TopicClocks {
+        TopicClocks::default()
+    }
+
+    /// Merge the clock carried by a message on `topic` into the topic's
+    /// clock, returning how the message relates to what was seen before.
+    ///
+    /// `EffectOf` means the message is new, while `Caused` or `Equal` mean
+    /// it was already covered, e.g. a redelivery. Messages without a clock
+    /// property return `None`.
+    pub fn observe(&mut self, topic: &str, properties: &[(String, String)]) -> Option<Result<TemporalRelation>> {
+        let clock = match clock_property(properties)? {
+            Ok(clock) => clock,
+            Err(e) => return Some(Err(e)),
+        };
+
+        let known = self.topics.entry(topic.to_owned()).or_default();
+        let relation = clock.temporal_relation(known);
+        known.merge(&clock);
+
+        Some(Ok(relation))
+    }
+
+    pub fn clock(&self, topic: &str) -> Option<&VectorClock<String>> {
+        self.topics.get(topic)
+    }
+
+    pub fn forget(&mut self, topic: &str) -> Option<VectorClock<String>> {
+        self.topics.remove(topic)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{set_clock_property, clock_property, TopicClocks};
+    use clock::{VectorClock, TemporalRelation};
+
+    #[test]
+    fn test_clock_property() {
+        let clock = VectorClock::new().incremented("sensor-1".to_owned());
+        let mut props = vec![("unit".to_owned(), "C".to_owned())];
+
+        set_clock_property(&mut props, &VectorClock::new());
+        set_clock_property(&mut props, &clock);
+
+        assert_eq!(2, props.len());
+        assert_eq!(clock, clock_property(&props).unwrap().unwrap());
+        assert!(clock_property(&props[..1]).is_none());
+    }
+
+    #[test]
+    fn test_topic_clocks() {
+        let first = VectorClock::new().incremented("s".to_owned());
+        let second = first.incremented("s".to_owned());
+        let mut props = Vec::new();
+        let mut topics = TopicClocks::new();
+
+        set_clock_property(&mut props, &first);
+        assert_eq!(TemporalRelation::EffectOf, topics.observe("t", &props).unwrap().unwrap());
+
+        set_clock_property(&mut props, &second);
+        assert_eq!(TemporalRelation::EffectOf, topics.observe("t", &props).unwrap().unwrap());
+
+        set_clock_property(&mut props, &first);
+        assert_eq!(TemporalRelation::Caused, topics.observe("t", &props).unwrap().unwrap());
+
+        assert_eq!(Some(&second), topics.clock("t"));
+        assert!(topics.observe("t", &[]).is_none());
+    }
+}
@@ -0,0 +1,95 @@
+use std::prelude::v1::*;
+use std::hash::Hash;
+
+use clock::VectorClock;
+use stamped::Stamped;
+use version_set::VersionSet;
+
+/// A multi-value register: a CRDT holding the latest value written, or
+/// every concurrently written value when writes conflict.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct MVRegister<HostType: Hash + Eq, T> {
+    versions: VersionSet<HostType, T>,
+}
+
+impl<HostType: Clone + Hash + Eq, T: Clone> MVRegister<HostType, T> {
+    pub fn new() -> MVRegister<HostType, T> {
+        MVRegister {
+            versions: VersionSet::new(),
+        }
+    }
+
+    /// Replace every current value with `value`, written by `host`.
+    pub fn write(&mut self, host: HostType, value: T) {
+        let mut clock = self.clock();
+        clock.increment(host);
+
+        self.versions.insert(clock, value);
+    }
+
+    /// Merge another replica of the register, keeping the values that no
+    /// value on either side supersedes.
+    pub fn merge(&mut self, other: &MVRegister<HostType, T>) {
+        self.versions.merge(&other.versions);
+    }
+
+    /// The current values: one, or several after concurrent writes.
+    pub fn values(&self) -> Vec<&T> {
+        self.versions.values()
+    }
+
+    /// The current values with their clocks.
+    pub fn entries(&self) -> &[Stamped<T, HostType>] {
+        self.versions.concurrent_versions()
+    }
+
+    /// The clock covering every current value, which the next write
+    /// supersedes.
+    pub fn clock(&self) -> VectorClock<HostType> {
+        self.versions.clock()
+    }
+}
+
+impl<HostType: Clone + Hash + Eq, T: Clone> Default for MVRegister<HostType, T> {
+    fn default() -> MVRegister<HostType, T> {
+        MVRegister::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::MVRegister;
+
+    #[test]
+    fn test_concurrent_writes_are_kept() {
+        let mut a = MVRegister::new();
+        a.write("A", 1);
+
+        let mut b = a.clone();
+        a.write("A", 2);
+        b.write("B", 3);
+
+        let mut merged = a.clone();
+        merged.merge(&b);
+        merged.merge(&a);
+
+        assert_eq!(vec![&2, &3], merged.values());
+
+        merged.write("B", 4);
+        a.merge(&merged);
+
+        assert_eq!(vec![&4], a.values());
+    }
+
+    #[test]
+    fn test_merge_older_replica() {
+        let mut a = MVRegister::new();
+        a.write("A", "x");
+
+        let old = a.clone();
+        a.write("A", "y");
+        a.merge(&old);
+
+        assert_eq!(vec![&"y"], a.values());
+    }
+}
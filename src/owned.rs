@@ -0,0 +1,138 @@
+use std::hash::Hash;
+
+use clock::VectorClock;
+
+/// Proof that the holder is the node identified by `host`.
+///
+/// The token is deliberately neither `Clone` nor `Copy`: a node creates one
+/// for its own identity and only that token can tick the node's entry.
+/// Entries belonging to other hosts can only grow through `observe`, which
+/// merges in a clock received from elsewhere.
+///
+/// On its own the token is advisory, since `VectorClock::increment` can
+/// still tick any host. Keep the clock in an `OwnedClock` to make ticking a
+/// foreign entry a compile error.
+#[derive(Debug, PartialEq, Eq)]
+pub struct OwnedHost<HostType> {
+    host: HostType,
+}
+
+impl<HostType: Clone + Hash + Eq> OwnedHost<HostType> {
+    pub fn new(host: HostType) -> OwnedHost<HostType> {
+        OwnedHost {
+            host,
+        }
+    }
+
+    pub fn host(&self) -> &HostType {
+        &self.host
+    }
+
+    pub fn into_host(self) -> HostType {
+        self.host
+    }
+
+    pub fn tick(&self, clock: &VectorClock<HostType>) -> VectorClock<HostType> {
+        clock.incremented(self.host.clone())
+    }
+
+    pub fn observe(&self, clock: &VectorClock<HostType>, remote: &VectorClock<HostType>)
+        -> VectorClock<HostType>
+    {
+        clock.merge_with(remote)
+    }
+}
+
+/// A clock that can only be ticked for the host of its `OwnedHost`.
+///
+/// The clock is only handed out by shared reference, so the sole way to
+/// advance it is `tick`, for the owner, and `observe`, for everyone else.
+#[derive(Debug, PartialEq, Eq)]
+pub struct OwnedClock<HostType: Hash + Eq> {
+    owner: OwnedHost<HostType>,
+    clock: VectorClock<HostType>,
+}
+
+impl<HostType: Clone + Hash + Eq> OwnedClock<HostType> {
+    pub fn new(owner: OwnedHost<HostType>) -> OwnedClock<HostType> {
+        OwnedClock::resume(owner, VectorClock::new())
+    }
+
+    /// Continue from a previously persisted clock.
+    pub fn resume(owner: OwnedHost<HostType>, clock: VectorClock<HostType>) -> OwnedClock<HostType> {
+        OwnedClock {
+            owner,
+            clock,
+        }
+    }
+
+    pub fn owner(&self) -> &OwnedHost<HostType> {
+        &self.owner
+    }
+
+    pub fn clock(&self) -> &VectorClock<HostType> {
+        &self.clock
+    }
+
+    pub fn into_parts(self) -> (OwnedHost<HostType>, VectorClock<HostType>) {
+        (self.owner, self.clock)
+    }
+
+    /// Tick the owner's entry.
+    pub fn tick(&mut self) -> &VectorClock<HostType> {
+        self.clock.increment(self.owner.host.clone());
+        &self.clock
+    }
+
+    /// Merge in a clock received from elsewhere.
+    pub fn observe(&mut self, remote: &VectorClock<HostType>) -> &VectorClock<HostType> {
+        self.clock.merge(remote);
+        &self.clock
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{OwnedHost, OwnedClock};
+    use clock::{VectorClock, TemporalRelation};
+
+    type StrVectorClock = VectorClock<&'static str>;
+
+    #[test]
+    fn test_tick_only_own_entry() {
+        let a = OwnedHost::new("A");
+        let c = a.tick(&StrVectorClock::new());
+
+        assert_eq!(vec![("A", 1)], c.clone().into_iter().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_observe_raises_foreign_entries() {
+        let a = OwnedHost::new("A");
+        let b = OwnedHost::new("B");
+
+        let ca = a.tick(&StrVectorClock::new());
+        let cb = b.tick(&StrVectorClock::new());
+
+        let m = a.observe(&ca, &cb);
+        let mut v = m.clone().into_iter().collect::<Vec<_>>();
+        v.sort();
+
+        assert_eq!(vec![("A", 1), ("B", 1)], v);
+        assert!(cb.temporal_relation(&m) == TemporalRelation::Caused);
+    }
+
+    #[test]
+    fn test_owned_clock() {
+        let mut a = OwnedClock::new(OwnedHost::new("A"));
+        let b = OwnedHost::new("B").tick(&StrVectorClock::new());
+
+        a.tick();
+        a.observe(&b);
+        a.tick();
+
+        assert_eq!(2, a.clock().get(&"A"));
+        assert_eq!(1, a.clock().get(&"B"));
+        assert_eq!("A", *a.owner().host());
+    }
+}
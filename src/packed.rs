@@ -0,0 +1,133 @@
+use std::prelude::v1::*;
+use std::fmt::Display;
+use std::hash::Hash;
+use std::str::FromStr;
+
+use clock::VectorClock;
+use counter::Counter;
+use error::{Error, Result};
+use wire::{write_varint, read_varint, take};
+
+impl<HostType: Clone + Hash + Eq + Display, CounterType: Counter> VectorClock<HostType, CounterType> {
+    /// Encode the clock compactly, packing counters by size.
+    ///
+    /// The encoding is a varint entry count, then each host as a varint
+    /// length and its `Display` bytes, then the counters in groups of four.
+    /// Each group starts with a tag byte holding two bits per counter, from
+    /// the low bits up, selecting a width of 1, 2, 4 or 8 little-endian
+    /// bytes. Clocks with counters below 2^16 take two bytes or less per
+    /// counter instead of eight.
+    pub fn to_packed(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        write_varint(&mut out, self.len() as u64);
+
+        let mut counters = Vec::with_capacity(self.len());
+
+        for (host, &n) in self.iter() {
+            let host = host.to_string();
+            write_varint(&mut out, host.len() as u64);
+            out.extend_from_slice(host.as_bytes());
+            counters.push(n.to_u64());
+        }
+
+        for group in counters.chunks(4) {
+            let tag_pos = out.len();
+            out.push(0);
+
+            for (i, &n) in group.iter().enumerate() {
+                let tag = match n {
+                    0..=0xFF => 0,
+                    0x100..=0xFFFF => 1,
+                    0x1_0000..=0xFFFF_FFFF => 2,
+                    _ => 3,
+                };
+
+                out[tag_pos] |= tag << (2 * i);
+                out.extend_from_slice(&n.to_le_bytes()[..1 << tag]);
+            }
+        }
+
+        out
+    }
+}
+
+impl<HostType: Clone + Hash + Eq + FromStr, CounterType: Counter> VectorClock<HostType, CounterType> {
+    /// Decode a clock written by `to_packed`. A counter too large for
+    /// `CounterType` fails with `Error::Overflow`.
+    pub fn from_packed(bytes: &[u8]) -> Result<VectorClock<HostType, CounterType>> {
+        let mut input = bytes;
+
+        let count = read_varint(&mut input)? as usize;
+        let mut hosts = Vec::with_capacity(count.min(input.len()));
+
+        for _ in 0..count {
+            let len = read_varint(&mut input)? as usize;
+            let host = take(&mut input, len)?;
+
+            let host = ::std::str::from_utf8(host).ok()
+                .and_then(|h| h.parse().ok())
+                .ok_or_else(|| Error::Decode("invalid packed host".to_owned()))?;
+
+            hosts.push(host);
+        }
+
+        let mut entries = Vec::with_capacity(hosts.len());
+        let mut hosts = hosts.into_iter();
+
+        while entries.len() < count {
+            let tag = take(&mut input, 1)?[0];
+
+            for (i, host) in hosts.by_ref().take(4).enumerate() {
+                let width = 1 << ((tag >> (2 * i)) & 3);
+                let mut le = [0; 8];
+                le[..width].copy_from_slice(take(&mut input, width)?);
+
+                entries.push((host, CounterType::from_u64(u64::from_le_bytes(le)).ok_or(Error::Overflow)?));
+            }
+        }
+
+        if !input.is_empty() {
+            return Err(Error::Decode("trailing bytes after packed clock".to_owned()));
+        }
+
+        Ok(entries.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::prelude::v1::*;
+    use clock::VectorClock;
+
+    #[test]
+    fn test_round_trip() {
+        let c = vec![
+            ("a".to_owned(), 1),
+            ("b".to_owned(), 300),
+            ("c".to_owned(), 70_000),
+            ("d".to_owned(), 1 << 40),
+            ("e".to_owned(), 0),
+        ].into_iter().collect::<VectorClock<_>>();
+
+        let packed = c.to_packed();
+
+        assert_eq!(c, VectorClock::<String>::from_packed(&packed).unwrap());
+    }
+
+    #[test]
+    fn test_small_counters_are_compact() {
+        let c = vec![("a".to_owned(), 5), ("b".to_owned(), 1000)].into_iter().collect::<VectorClock<_>>();
+
+        // Count, two one-byte hosts with lengths, one tag, 1 + 2 counter bytes.
+        assert_eq!(1 + 4 + 1 + 3, c.to_packed().len());
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        let packed = VectorClock::new().incremented("a".to_owned()).to_packed();
+
+        assert!(VectorClock::<String>::from_packed(&packed[..packed.len() - 1]).is_err());
+        assert!(VectorClock::<String>::from_packed(&[packed.clone(), vec![0]].concat()).is_err());
+        assert!(VectorClock::<String>::from_packed(&[0xFF; 11]).is_err());
+    }
+}
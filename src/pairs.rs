@@ -0,0 +1,51 @@
+//! Serialize a `VectorClock` as a sequence of `[host, counter]` pairs.
+//!
+//! Formats such as JSON only allow strings as map keys, which rules out
+//! clocks keyed by integers, tuples or structs. Use this module with
+//! `#[serde(with = "vectorclock::pairs")]` on a clock field to store it as
+//! `[[host, counter], ...]` instead.
+
+use std::hash::Hash;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use clock::VectorClock;
+use counter::Counter;
+
+pub fn serialize<HostType, CounterType, S>(clock: &VectorClock<HostType, CounterType>, serializer: S)
+    -> Result<S::Ok, S::Error>
+    where HostType: Serialize + Clone + Hash + Eq, CounterType: Counter + Serialize, S: Serializer
+{
+    serializer.collect_seq(clock.iter().map(|(host, n)| (host, *n)))
+}
+
+pub fn deserialize<'de, HostType, CounterType, D>(deserializer: D) -> Result<VectorClock<HostType, CounterType>, D::Error>
+    where HostType: Deserialize<'de> + Clone + Hash + Eq, CounterType: Counter + Deserialize<'de>, D: Deserializer<'de>
+{
+    Vec::<(HostType, CounterType)>::deserialize(deserializer).map(|entries| entries.into_iter().collect())
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json;
+
+    use clock::VectorClock;
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Message {
+        #[serde(with = "::pairs")]
+        clock: VectorClock<(u32, u32)>,
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let m = Message {
+            clock: VectorClock::new().incremented((1, 2)).incremented((1, 2)),
+        };
+
+        let json = serde_json::to_string(&m).unwrap();
+
+        assert_eq!(r#"{"clock":[[[1,2],2]]}"#, json);
+        assert_eq!(m, serde_json::from_str(&json).unwrap());
+    }
+}
@@ -0,0 +1,95 @@
+use std::hash::Hash;
+
+use clock::VectorClock;
+
+/// The clock of a single process, advanced according to the usual rules:
+/// every local event and every send ticks the process' own entry, and a
+/// receive merges in the sender's clock before ticking.
+///
+/// The clock is only reachable through these operations, so the ordering
+/// of merging and incrementing can't be gotten wrong.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ProcessClock<HostType: Hash + Eq> {
+    host: HostType,
+    clock: VectorClock<HostType>,
+}
+
+impl<HostType: Clone + Hash + Eq> ProcessClock<HostType> {
+    pub fn new(host: HostType) -> ProcessClock<HostType> {
+        ProcessClock::resume(host, VectorClock::new())
+    }
+
+    /// Continue from a previously persisted clock, e.g. after a restart.
+    pub fn resume(host: HostType, clock: VectorClock<HostType>) -> ProcessClock<HostType> {
+        ProcessClock {
+            host,
+            clock,
+        }
+    }
+
+    pub fn host(&self) -> &HostType {
+        &self.host
+    }
+
+    pub fn clock(&self) -> &VectorClock<HostType> {
+        &self.clock
+    }
+
+    pub fn into_clock(self) -> VectorClock<HostType> {
+        self.clock
+    }
+
+    /// Record an event that isn't visible to other processes.
+    pub fn local_event(&mut self) {
+        self.clock.increment(self.host.clone());
+    }
+
+    /// Record sending a message, returning the clock to attach to it.
+    pub fn prepare_send(&mut self) -> VectorClock<HostType> {
+        self.local_event();
+        self.clock.clone()
+    }
+
+    /// Record receiving a message that carried `remote`.
+    pub fn observe_receive(&mut self, remote: &VectorClock<HostType>) {
+        self.clock.merge(remote);
+        self.local_event();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ProcessClock;
+    use clock::{VectorClock, TemporalRelation};
+
+    #[test]
+    fn test_send_receive() {
+        let mut a = ProcessClock::new("A");
+        let mut b = ProcessClock::new("B");
+
+        a.local_event();
+        let message = a.prepare_send();
+        assert_eq!(2, message.get(&"A"));
+
+        b.local_event();
+        b.observe_receive(&message);
+
+        assert_eq!(2, b.clock().get(&"A"));
+        assert_eq!(2, b.clock().get(&"B"));
+        assert_eq!(TemporalRelation::Caused, message.temporal_relation(b.clock()));
+    }
+
+    #[test]
+    fn test_receive_is_after_sender() {
+        let mut a = ProcessClock::new("A");
+        let mut b = ProcessClock::resume("B", VectorClock::new().incremented("A"));
+
+        // B has already seen everything in the message, but receiving it
+        // is still a new event.
+        let before = b.clock().clone();
+        b.observe_receive(&a.prepare_send());
+
+        assert_eq!(TemporalRelation::Caused, before.temporal_relation(b.clock()));
+        assert_eq!(TemporalRelation::Caused, a.clock().temporal_relation(b.clock()));
+    }
+}
@@ -0,0 +1,214 @@
+#[cfg(feature = "reqwest")]
+use std::future::Future;
+use std::hash::Hash;
+#[cfg(feature = "reqwest")]
+use std::pin::Pin;
+#[cfg(feature = "reqwest")]
+use std::sync::{Arc, Mutex};
+#[cfg(feature = "reqwest")]
+use std::task::{Context, Poll};
+
+#[cfg(feature = "reqwest")]
+use http::Extensions;
+#[cfg(feature = "reqwest")]
+use reqwest_middleware::{Middleware, Next};
+#[cfg(feature = "reqwest")]
+use reqwest_middleware::reqwest::{Request, Response};
+#[cfg(feature = "reqwest")]
+use reqwest_middleware::reqwest::header::HeaderValue;
+
+use clock::VectorClock;
+#[cfg(feature = "reqwest")]
+use context::{to_header_value, from_header_value};
+use error::Result;
+#[cfg(feature = "reqwest")]
+use header::CLOCK_HEADER;
+#[cfg(feature = "reqwest")]
+use scope::{current_clock, merge_current};
+
+/// Sends the local clock to a peer over the application's transport, e.g.
+/// for `Heartbeat::beat`.
+///
+/// Closures taking the peer and the clock implement it.
+pub trait ClockTransport<Peer, HostType: Hash + Eq> {
+    fn send_clock(&mut self, peer: &Peer, clock: &VectorClock<HostType>) -> Result<()>;
+}
+
+impl<Peer, HostType, F> ClockTransport<Peer, HostType> for F
+    where HostType: Hash + Eq, F: FnMut(&Peer, &VectorClock<HostType>) -> Result<()>
+{
+    fn send_clock(&mut self, peer: &Peer, clock: &VectorClock<HostType>) -> Result<()> {
+        self(peer, clock)
+    }
+}
+
+/// The causal context of an HTTP client session.
+///
+/// Added to a `reqwest_middleware` client, it sends the session clock in
+/// the `CLOCK_HEADER` header of every request and merges the clock found on
+/// each response back into the session. Clones share the same clock.
+///
+/// Requests made within a task-local context, e.g. from a handler behind
+/// the axum `clock_layer`, also carry that context, and responses are
+/// merged back into it.
+#[cfg(feature = "reqwest")]
+#[derive(Debug, Clone, Default)]
+pub struct ClockSession {
+    clock: Arc<Mutex<VectorClock<String>>>,
+}
+
+#[cfg(feature = "reqwest")]
+impl ClockSession {
+    pub fn new(clock: VectorClock<String>) -> ClockSession {
+        ClockSession {
+            clock: Arc::new(Mutex::new(clock)),
+        }
+    }
+
+    pub fn clock(&self) -> VectorClock<String> {
+        self.lock().clone()
+    }
+
+    pub fn merge(&self, other: &VectorClock<String>) {
+        self.lock().merge(other);
+    }
+
+    fn lock(&self) -> ::std::sync::MutexGuard<'_, VectorClock<String>> {
+        self.clock.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn stamp(&self, req: &mut Request) -> Result<()> {
+        let mut clock = self.clock();
+
+        if let Some(current) = current_clock() {
+            clock.merge(&current);
+        }
+
+        let value = HeaderValue::from_str(&to_header_value(&clock))
+            .map_err(|e| ::error::Error::InvalidEntry(e.to_string()))?;
+
+        req.headers_mut().insert(CLOCK_HEADER, value);
+        Ok(())
+    }
+
+    fn absorb(&self, res: &Response) -> Result<()> {
+        if let Some(value) = res.headers().get(CLOCK_HEADER) {
+            let value = value.to_str().map_err(|e| ::error::Error::Decode(e.to_string()))?;
+            let clock = from_header_value(value)?;
+
+            self.merge(&clock);
+            merge_current(&clock);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "reqwest")]
+type BoxFuture<'a> = Pin<Box<dyn Future<Output=reqwest_middleware::Result<Response>> + Send + 'a>>;
+
+#[cfg(feature = "reqwest")]
+impl Middleware for ClockSession {
+    // The trait's `async fn` as expanded by async_trait, since async syntax
+    // is not available in this crate's edition.
+    fn handle<'life0, 'life1, 'life2, 'async_trait>(&'life0 self, mut req: Request, extensions: &'life1 mut Extensions, next: Next<'life2>)
+        -> BoxFuture<'async_trait>
+        where 'life0: 'async_trait, 'life1: 'async_trait, 'life2: 'async_trait, Self: 'async_trait
+    {
+        if let Err(e) = self.stamp(&mut req) {
+            return Box::pin(::std::future::ready(Err(reqwest_middleware::Error::middleware(e))));
+        }
+
+        Box::pin(Absorb {
+            session: self,
+            response: next.run(req, extensions),
+        })
+    }
+}
+
+// Merges the response's clock into the session once the request finishes.
+#[cfg(feature = "reqwest")]
+struct Absorb<'a> {
+    session: &'a ClockSession,
+    response: BoxFuture<'a>,
+}
+
+#[cfg(feature = "reqwest")]
+impl<'a> Future for Absorb<'a> {
+    type Output = reqwest_middleware::Result<Response>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let result = match self.response.as_mut().poll(cx) {
+            Poll::Ready(result) => result,
+            Poll::Pending => return Poll::Pending,
+        };
+
+        Poll::Ready(result.and_then(|res| {
+            self.session.absorb(&res).map_err(reqwest_middleware::Error::middleware)?;
+            Ok(res)
+        }))
+    }
+}
+
+#[cfg(all(test, feature = "reqwest"))]
+mod test {
+    use http;
+    use reqwest_middleware::reqwest::{Method, Request, Response};
+
+    use std::future::{self, Future};
+    use std::pin::pin;
+    use std::task::{Context, Poll, Waker};
+
+    use super::ClockSession;
+    use clock::VectorClock;
+    use context::{to_header_value, from_header_value};
+    use header::CLOCK_HEADER;
+    use scope::with_clock;
+
+    #[test]
+    fn test_stamp_request() {
+        let session = ClockSession::new(VectorClock::new().incremented("client".to_owned()));
+        let mut req = Request::new(Method::GET, "http://localhost/".parse().unwrap());
+
+        session.stamp(&mut req).unwrap();
+
+        assert_eq!(to_header_value(&session.clock()), req.headers()[CLOCK_HEADER]);
+    }
+
+    #[test]
+    fn test_absorb_response() {
+        let session = ClockSession::new(VectorClock::new().incremented("client".to_owned()));
+        let res = Response::from(http::Response::builder().header(CLOCK_HEADER, "server:3").body("").unwrap());
+
+        session.absorb(&res).unwrap();
+
+        let expected = vec![("client".to_owned(), 1), ("server".to_owned(), 3)].into_iter().collect::<VectorClock<_>>();
+        assert_eq!(expected, session.clock());
+
+        let res = Response::from(http::Response::builder().header(CLOCK_HEADER, "server").body("").unwrap());
+        assert!(session.absorb(&res).is_err());
+    }
+
+    #[test]
+    fn test_task_local_context() {
+        let session = ClockSession::new(VectorClock::new().incremented("client".to_owned()));
+        let incoming = VectorClock::new().incremented("caller".to_owned());
+
+        let mut scope = pin!(with_clock(incoming.clone(), future::poll_fn(|_| {
+            let mut req = Request::new(Method::GET, "http://localhost/".parse().unwrap());
+            session.stamp(&mut req).unwrap();
+
+            let sent = from_header_value(req.headers()[CLOCK_HEADER].to_str().unwrap()).unwrap();
+            assert_eq!(session.clock().merge_with(&incoming), sent);
+
+            let res = Response::from(http::Response::builder().header(CLOCK_HEADER, "server:3").body("").unwrap());
+            session.absorb(&res).unwrap();
+
+            Poll::Ready(())
+        })));
+
+        assert!(scope.as_mut().poll(&mut Context::from_waker(Waker::noop())).is_ready());
+        assert_eq!(3, scope.clock().get(&"server".to_owned()));
+        assert_eq!(1, scope.clock().get(&"caller".to_owned()));
+    }
+}
@@ -0,0 +1,129 @@
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+use clock::{VectorClock, TemporalRelation};
+
+/// The outcome of reconciling the responses of a quorum read.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Reconciled<Value, HostType: Hash + Eq, Replica> {
+    /// The join of every response clock, to be used as the context for the
+    /// next write.
+    pub context: VectorClock<HostType>,
+    /// The versions not dominated by any other response, each listed once.
+    pub siblings: Vec<(VectorClock<HostType>, Value)>,
+    /// Replicas that did not return the full set of siblings and should be
+    /// sent a read repair, each listed once in the order of their first
+    /// response.
+    pub stale: Vec<Replica>,
+}
+
+/// Reconcile the `(replica, clock, value)` responses of a quorum read.
+///
+/// A replica may respond several times, once per sibling it holds. It is
+/// stale if the join of its clocks is behind the joined context.
+pub fn reconcile<Replica, HostType, Value>(responses: Vec<(Replica, VectorClock<HostType>, Value)>)
+    -> Reconciled<Value, HostType, Replica>
+    where Replica: Hash + Eq,
+          HostType: Clone + Hash + Eq
+{
+    let context = responses.iter()
+        .fold(VectorClock::new(), |acc, (_, c, _)| acc.merge_with(c));
+
+    // The first response of each replica, with the join of its clocks.
+    let mut replicas: HashMap<&Replica, (usize, VectorClock<HostType>)> = HashMap::new();
+
+    for (i, (replica, clock, _)) in responses.iter().enumerate() {
+        replicas.entry(replica).or_insert_with(|| (i, VectorClock::new())).1.merge(clock);
+    }
+
+    let stale_at: HashSet<usize> = replicas.values()
+        .filter(|&(_, clock)| *clock != context)
+        .map(|&(i, _)| i)
+        .collect();
+
+    let mut siblings: Vec<(VectorClock<HostType>, Value)> = Vec::new();
+    let mut stale = Vec::new();
+
+    for (i, (replica, clock, value)) in responses.into_iter().enumerate() {
+        if stale_at.contains(&i) {
+            stale.push(replica);
+        }
+
+        let mut dominated = false;
+        let mut i = 0;
+
+        while i < siblings.len() {
+            match clock.temporal_relation(&siblings[i].0) {
+                TemporalRelation::Equal | TemporalRelation::Caused => {
+                    dominated = true;
+                    break;
+                },
+                TemporalRelation::EffectOf => { siblings.swap_remove(i); },
+                TemporalRelation::Concurrent => { i += 1; },
+            }
+        }
+
+        if !dominated {
+            siblings.push((clock, value));
+        }
+    }
+
+    Reconciled {
+        context,
+        siblings,
+        stale,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::reconcile;
+    use clock::VectorClock;
+
+    type StrVectorClock = VectorClock<&'static str>;
+
+    #[test]
+    fn test_all_agree() {
+        let c = StrVectorClock::new().incremented("A");
+        let r = reconcile(vec![(1, c.clone(), "x"), (2, c.clone(), "x")]);
+
+        assert_eq!(c, r.context);
+        assert_eq!(vec![(c, "x")], r.siblings);
+        assert!(r.stale.is_empty());
+    }
+
+    #[test]
+    fn test_stale_replica() {
+        let old = StrVectorClock::new().incremented("A");
+        let new = old.incremented("A");
+
+        let r = reconcile(vec![(1, old, "x"), (2, new.clone(), "y"), (3, new.clone(), "y")]);
+
+        assert_eq!(vec![(new, "y")], r.siblings);
+        assert_eq!(vec![1], r.stale);
+    }
+
+    #[test]
+    fn test_concurrent_siblings() {
+        let a = StrVectorClock::new().incremented("A");
+        let b = StrVectorClock::new().incremented("B");
+
+        let r = reconcile(vec![(1, a.clone(), "x"), (2, b.clone(), "y")]);
+
+        assert_eq!(a.merge_with(&b), r.context);
+        assert_eq!(vec![(a, "x"), (b, "y")], r.siblings);
+        assert_eq!(vec![1, 2], r.stale);
+    }
+
+    #[test]
+    fn test_replica_with_siblings() {
+        let a = StrVectorClock::new().incremented("A");
+        let b = StrVectorClock::new().incremented("B");
+
+        let r = reconcile(vec![(1, a.clone(), "x"), (1, b.clone(), "y"), (2, a.clone(), "x"), (3, b.clone(), "y"),
+                               (3, StrVectorClock::new(), "z")]);
+
+        assert_eq!(vec![(a, "x"), (b, "y")], r.siblings);
+        assert_eq!(vec![2, 3], r.stale);
+    }
+}
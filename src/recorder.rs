@@ -0,0 +1,136 @@
+use std::hash::Hash;
+
+use clock::{VectorClock, TemporalRelation};
+
+/// A clock operation captured by a `Recorder`, with its inputs.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum RecordedOp<HostType: Hash + Eq> {
+    Increment(HostType),
+    /// A merge of `remote` into the clock.
+    Merge { remote: VectorClock<HostType> },
+    /// A comparison of the clock with `remote`.
+    Compare { remote: VectorClock<HostType>, relation: TemporalRelation },
+}
+
+/// Records every operation applied to a clock so that any earlier state can
+/// be reconstructed.
+///
+/// Only the remote operand of a merge or comparison is stored; the local
+/// operand of operation `i` is `state_at(i)`.
+#[derive(Debug, Clone)]
+pub struct Recorder<HostType: Hash + Eq> {
+    initial: VectorClock<HostType>,
+    clock: VectorClock<HostType>,
+    log: Vec<RecordedOp<HostType>>,
+}
+
+impl<HostType: Clone + Hash + Eq> Recorder<HostType> {
+    pub fn new() -> Recorder<HostType> {
+        Recorder::from_clock(VectorClock::new())
+    }
+
+    pub fn from_clock(clock: VectorClock<HostType>) -> Recorder<HostType> {
+        Recorder {
+            initial: clock.clone(),
+            clock,
+            log: Vec::new(),
+        }
+    }
+
+    pub fn increment(&mut self, host: HostType) {
+        self.clock.increment(host.clone());
+        self.log.push(RecordedOp::Increment(host));
+    }
+
+    pub fn merge(&mut self, other: &VectorClock<HostType>) {
+        self.clock.merge(other);
+        self.log.push(RecordedOp::Merge { remote: other.clone() });
+    }
+
+    /// The relation of the clock to `other`, recording the comparison.
+    pub fn compare(&mut self, other: &VectorClock<HostType>) -> TemporalRelation {
+        let relation = self.clock.temporal_relation(other);
+
+        self.log.push(RecordedOp::Compare { remote: other.clone(), relation });
+
+        relation
+    }
+
+    pub fn clock(&self) -> &VectorClock<HostType> {
+        &self.clock
+    }
+
+    pub fn initial(&self) -> &VectorClock<HostType> {
+        &self.initial
+    }
+
+    pub fn log(&self) -> &[RecordedOp<HostType>] {
+        &self.log
+    }
+
+    /// The state of the clock after the first `n` recorded operations.
+    pub fn state_at(&self, n: usize) -> VectorClock<HostType> {
+        replay(&self.initial, &self.log[..::std::cmp::min(n, self.log.len())])
+    }
+}
+
+impl<HostType: Clone + Hash + Eq> Default for Recorder<HostType> {
+    fn default() -> Recorder<HostType> {
+        Recorder::new()
+    }
+}
+
+/// Apply `ops` in order, starting from `initial`.
+pub fn replay<HostType: Clone + Hash + Eq>(initial: &VectorClock<HostType>, ops: &[RecordedOp<HostType>])
+    -> VectorClock<HostType>
+{
+    ops.iter().fold(initial.clone(), |clock, op| match *op {
+        RecordedOp::Increment(ref host) => clock.incremented(host.clone()),
+        RecordedOp::Merge { ref remote } => clock.merge_with(remote),
+        RecordedOp::Compare { .. } => clock,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Recorder, RecordedOp};
+    use clock::{VectorClock, TemporalRelation};
+
+    #[test]
+    fn test_replay_reconstructs_states() {
+        let mut r = Recorder::new();
+        let mut states = vec![r.clock().clone()];
+
+        r.increment("A");
+        states.push(r.clock().clone());
+        r.merge(&VectorClock::new().incremented("B").incremented("B"));
+        states.push(r.clock().clone());
+        r.increment("A");
+        states.push(r.clock().clone());
+        r.compare(&VectorClock::new().incremented("C"));
+        states.push(r.clock().clone());
+
+        for (i, state) in states.iter().enumerate() {
+            assert_eq!(*state, r.state_at(i));
+        }
+
+        assert_eq!(*r.clock(), r.state_at(100));
+    }
+
+    #[test]
+    fn test_records_operands() {
+        let local = VectorClock::new().incremented("A").incremented("A");
+        let remote = VectorClock::new().incremented("A").incremented("B");
+
+        let mut r = Recorder::from_clock(local.clone());
+
+        assert_eq!(TemporalRelation::Concurrent, r.compare(&remote));
+        r.merge(&remote);
+
+        assert_eq!(&[
+            RecordedOp::Compare { remote: remote.clone(), relation: TemporalRelation::Concurrent },
+            RecordedOp::Merge { remote },
+        ], r.log());
+        assert_eq!(local, r.state_at(1));
+    }
+}
@@ -0,0 +1,320 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::time::SystemTime;
+
+use clock::VectorClock;
+use compact::CompactVectorClock;
+use error::{Error, Result};
+
+/// A compact host identifier handed out by a `HostRegistry`.
+pub type NodeId = u32;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct HostRecord {
+    pub id: NodeId,
+    pub name: String,
+    pub last_seen: Option<SystemTime>,
+}
+
+/// Storage for the records of a `HostRegistry`.
+///
+/// The registry keeps its lookup indices in memory and writes every change
+/// through to the backend, so a backend only needs to persist records.
+pub trait RegistryBackend {
+    fn store(&mut self, record: HostRecord);
+    fn remove(&mut self, id: NodeId);
+    fn records(&self) -> Vec<HostRecord>;
+}
+
+#[derive(Debug, Default)]
+pub struct MemoryBackend {
+    records: BTreeMap<NodeId, HostRecord>,
+}
+
+impl MemoryBackend {
+    pub fn new() -> MemoryBackend {
+        MemoryBackend {
+            records: BTreeMap::new(),
+        }
+    }
+}
+
+impl RegistryBackend for MemoryBackend {
+    fn store(&mut self, record: HostRecord) {
+        self.records.insert(record.id, record);
+    }
+
+    fn remove(&mut self, id: NodeId) {
+        self.records.remove(&id);
+    }
+
+    fn records(&self) -> Vec<HostRecord> {
+        self.records.values().cloned().collect()
+    }
+}
+
+/// Allocates small sequential `NodeId`s for named hosts.
+///
+/// Released IDs are reused before new ones are allocated, keeping the ID
+/// space dense. An ID must only be released once it has been pruned from
+/// every clock that mentions it; otherwise the next owner inherits the old
+/// host's counter.
+#[derive(Debug)]
+pub struct HostRegistry<Backend: RegistryBackend = MemoryBackend> {
+    backend: Backend,
+    records: BTreeMap<NodeId, HostRecord>,
+    by_name: HashMap<String, NodeId>,
+    free: BTreeSet<NodeId>,
+    next: NodeId,
+}
+
+impl HostRegistry<MemoryBackend> {
+    pub fn new() -> HostRegistry<MemoryBackend> {
+        HostRegistry::with_backend(MemoryBackend::new())
+    }
+}
+
+impl Default for HostRegistry<MemoryBackend> {
+    fn default() -> HostRegistry<MemoryBackend> {
+        HostRegistry::new()
+    }
+}
+
+impl<Backend: RegistryBackend> HostRegistry<Backend> {
+    /// Create a registry, restoring any records already in `backend`.
+    pub fn with_backend(backend: Backend) -> HostRegistry<Backend> {
+        let records: BTreeMap<_, _> = backend.records().into_iter()
+            .map(|r| (r.id, r))
+            .collect();
+        let next = records.keys().next_back().map_or(0, |&id| id.saturating_add(1));
+        let free = (0..next).filter(|id| !records.contains_key(id)).collect();
+        let by_name = records.values().map(|r| (r.name.clone(), r.id)).collect();
+
+        HostRegistry {
+            backend,
+            records,
+            by_name,
+            free,
+            next,
+        }
+    }
+
+    /// Return the ID for `name`, allocating one if it is not registered.
+    ///
+    /// Fails with `Error::CapacityExceeded` once every `NodeId` is in use.
+    pub fn allocate(&mut self, name: &str) -> Result<NodeId> {
+        if let Some(&id) = self.by_name.get(name) {
+            return Ok(id);
+        }
+
+        let id = match self.free.iter().next().cloned() {
+            Some(id) => { self.free.remove(&id); id },
+            None => {
+                let id = self.next;
+                self.next = id.checked_add(1).ok_or(Error::CapacityExceeded { capacity: NodeId::MAX as usize })?;
+                id
+            },
+        };
+
+        let record = HostRecord {
+            id,
+            name: name.to_owned(),
+            last_seen: None,
+        };
+
+        self.by_name.insert(record.name.clone(), id);
+        self.put(record);
+
+        Ok(id)
+    }
+
+    /// Release `id` so that it can be handed out again.
+    pub fn release(&mut self, id: NodeId) -> bool {
+        match self.records.remove(&id) {
+            Some(record) => {
+                self.by_name.remove(&record.name);
+                self.free.insert(id);
+                self.backend.remove(id);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Record that `id` was seen alive at `at`.
+    pub fn heartbeat(&mut self, id: NodeId, at: SystemTime) -> bool {
+        match self.records.get(&id).cloned() {
+            Some(mut record) => {
+                record.last_seen = Some(at);
+                self.put(record);
+                true
+            },
+            None => false,
+        }
+    }
+
+    pub fn id_of(&self, name: &str) -> Option<NodeId> {
+        self.by_name.get(name).cloned()
+    }
+
+    pub fn name_of(&self, id: NodeId) -> Option<&str> {
+        self.records.get(&id).map(|r| &r.name[..])
+    }
+
+    pub fn record(&self, id: NodeId) -> Option<&HostRecord> {
+        self.records.get(&id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// IDs that have not been seen alive since `cutoff`.
+    pub fn stale_since(&self, cutoff: SystemTime) -> Vec<NodeId> {
+        self.records.values()
+            .filter(|r| r.last_seen.is_none_or(|t| t < cutoff))
+            .map(|r| r.id)
+            .collect()
+    }
+
+    /// Replace the compact IDs in `clock` with display names.
+    ///
+    /// Entries for IDs that are not registered are dropped.
+    pub fn named(&self, clock: &VectorClock<NodeId>) -> VectorClock<String> {
+        clock.iter()
+            .filter_map(|(&id, &n)| self.name_of(id).map(|name| (name.to_owned(), n)))
+            .collect()
+    }
+
+    /// `named`, for a compact clock.
+    pub fn named_compact(&self, clock: &CompactVectorClock<NodeId>) -> CompactVectorClock<String> {
+        CompactVectorClock::from(&self.named(&clock.to_vector_clock()))
+    }
+
+    /// Replace the display names in `clock` with compact IDs, allocating
+    /// IDs for hosts that are not registered yet.
+    pub fn compact(&mut self, clock: &VectorClock<String>) -> Result<CompactVectorClock<NodeId>> {
+        let mut ids = VectorClock::new();
+
+        for (name, &n) in clock.iter() {
+            ids.set(self.allocate(name)?, n);
+        }
+
+        Ok(CompactVectorClock::from(&ids))
+    }
+
+    pub fn backend(&self) -> &Backend {
+        &self.backend
+    }
+
+    fn put(&mut self, record: HostRecord) {
+        self.backend.store(record.clone());
+        self.records.insert(record.id, record);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::{HostRegistry, NodeId, RegistryBackend};
+    use clock::VectorClock;
+    use compact::CompactVectorClock;
+    use error::Error;
+
+    #[test]
+    fn test_sequential_allocation() {
+        let mut registry = HostRegistry::new();
+
+        assert_eq!(0, registry.allocate("alpha").unwrap());
+        assert_eq!(1, registry.allocate("beta").unwrap());
+        assert_eq!(0, registry.allocate("alpha").unwrap());
+        assert_eq!(Some("beta"), registry.name_of(1));
+    }
+
+    #[test]
+    fn test_released_ids_are_recycled() {
+        let mut registry = HostRegistry::new();
+
+        registry.allocate("alpha").unwrap();
+        registry.allocate("beta").unwrap();
+
+        assert!(registry.release(0));
+        assert_eq!(None, registry.id_of("alpha"));
+        assert_eq!(0, registry.allocate("gamma").unwrap());
+        assert_eq!(2, registry.allocate("delta").unwrap());
+    }
+
+    #[test]
+    fn test_restore_from_backend() {
+        let mut registry = HostRegistry::new();
+
+        registry.allocate("alpha").unwrap();
+        registry.allocate("beta").unwrap();
+        registry.allocate("gamma").unwrap();
+        registry.release(1);
+
+        let mut backend = super::MemoryBackend::new();
+        for record in registry.backend().records() {
+            backend.store(record);
+        }
+
+        let mut restored = HostRegistry::with_backend(backend);
+
+        assert_eq!(Some(2), restored.id_of("gamma"));
+        assert_eq!(1, restored.allocate("delta").unwrap());
+        assert_eq!(3, restored.allocate("epsilon").unwrap());
+    }
+
+    #[test]
+    fn test_liveness() {
+        let mut registry = HostRegistry::new();
+        let t = UNIX_EPOCH + Duration::from_secs(100);
+
+        let a = registry.allocate("alpha").unwrap();
+        let b = registry.allocate("beta").unwrap();
+
+        registry.heartbeat(a, t);
+
+        assert_eq!(vec![b], registry.stale_since(t));
+        assert_eq!(vec![a, b], registry.stale_since(t + Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn test_named_clock() {
+        let mut registry = HostRegistry::new();
+        let a = registry.allocate("alpha").unwrap();
+
+        let c = VectorClock::new().incremented(a).incremented(a);
+
+        assert_eq!(vec![("alpha".to_owned(), 2)], registry.named(&c).into_iter().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_compact_clock() {
+        let mut registry = HostRegistry::new();
+        let named = VectorClock::new().incremented("beta".to_owned()).incremented("alpha".to_owned());
+
+        let compact = registry.compact(&named).unwrap();
+
+        assert_eq!(2, compact.len());
+        assert_eq!(1, compact.get(&registry.id_of("alpha").unwrap()));
+        assert_eq!(CompactVectorClock::from(&named), registry.named_compact(&compact));
+    }
+
+    #[test]
+    fn test_exhausted_ids() {
+        let mut registry = HostRegistry::new();
+        registry.next = NodeId::MAX - 1;
+
+        assert_eq!(Ok(NodeId::MAX - 1), registry.allocate("alpha"));
+        assert_eq!(Err(Error::CapacityExceeded { capacity: NodeId::MAX as usize }), registry.allocate("beta"));
+        assert_eq!(None, registry.id_of("beta"));
+
+        registry.release(NodeId::MAX - 1);
+        assert_eq!(Ok(NodeId::MAX - 1), registry.allocate("beta"));
+    }
+}
@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+use std::collections::hash_map::RandomState;
+use std::fmt::Display;
+use std::hash::{BuildHasher, Hash};
+use std::sync::OnceLock;
+
+use clock::{VectorClock, TemporalRelation};
+
+/// A clock together with a 128-bit digest of it, computed once up front.
+///
+/// The digest is a SipHash of the clock's sort key under two keys chosen
+/// randomly per process, so clocks that differ have equal digests with
+/// negligible probability, even when their hosts are picked by someone
+/// else. Digests are not comparable across processes; for that, see
+/// `clock_digest`. The digest is recomputed whenever the clock is changed
+/// through `update`, so a `RelationCache` never sees a stale one.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct DigestedClock<HostType: Hash + Eq> {
+    clock: VectorClock<HostType>,
+    digest: u128,
+}
+
+impl<HostType: Clone + Hash + Eq + Display> DigestedClock<HostType> {
+    pub fn new(clock: VectorClock<HostType>) -> DigestedClock<HostType> {
+        DigestedClock {
+            digest: digest(&clock),
+            clock,
+        }
+    }
+
+    pub fn clock(&self) -> &VectorClock<HostType> {
+        &self.clock
+    }
+
+    pub fn digest(&self) -> u128 {
+        self.digest
+    }
+
+    /// Change the clock with `f` and recompute the digest.
+    pub fn update<F: FnOnce(&mut VectorClock<HostType>)>(&mut self, f: F) {
+        f(&mut self.clock);
+        self.digest = digest(&self.clock);
+    }
+
+    pub fn into_inner(self) -> VectorClock<HostType> {
+        self.clock
+    }
+}
+
+fn digest<HostType: Clone + Hash + Eq + Display>(clock: &VectorClock<HostType>) -> u128 {
+    static KEYS: OnceLock<(RandomState, RandomState)> = OnceLock::new();
+
+    let (high, low) = KEYS.get_or_init(|| (RandomState::new(), RandomState::new()));
+    let key = clock.to_sort_key();
+
+    u128::from(high.hash_one(&key)) << 64 | u128::from(low.hash_one(&key))
+}
+
+/// Memoizes `temporal_relation` between clocks, keyed by their digests.
+///
+/// Meant for workloads that compare the same immutable clocks over and
+/// over, like sibling sets evaluated on every read: a hit costs one hash
+/// lookup, whatever the size of the clocks. A pair is stored once for both
+/// orders, and clocks with equal digests are taken to be equal. When the
+/// cache reaches its capacity it is cleared; a capacity of zero disables
+/// caching.
+#[derive(Debug, Clone)]
+pub struct RelationCache {
+    capacity: usize,
+    relations: HashMap<(u128, u128), TemporalRelation>,
+    hits: u64,
+    misses: u64,
+}
+
+impl RelationCache {
+    pub fn new(capacity: usize) -> RelationCache {
+        RelationCache {
+            capacity,
+            relations: HashMap::new(),
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// The relation of `a` to `b`, as by `temporal_relation`.
+    pub fn relation<HostType>(&mut self, a: &DigestedClock<HostType>, b: &DigestedClock<HostType>) -> TemporalRelation
+        where HostType: Clone + Hash + Eq + Display
+    {
+        if a.digest == b.digest {
+            self.hits += 1;
+            return TemporalRelation::Equal;
+        }
+
+        if self.capacity == 0 {
+            self.misses += 1;
+            return a.clock.temporal_relation(&b.clock);
+        }
+
+        let (low, high, swapped) = if a.digest < b.digest { (a, b, false) } else { (b, a, true) };
+        let key = (low.digest, high.digest);
+
+        let relation = match self.relations.get(&key) {
+            Some(&relation) => {
+                self.hits += 1;
+                relation
+            },
+            None => {
+                self.misses += 1;
+
+                let relation = low.clock.temporal_relation(&high.clock);
+
+                if self.relations.len() >= self.capacity {
+                    self.relations.clear();
+                }
+                self.relations.insert(key, relation);
+
+                relation
+            },
+        };
+
+        if swapped { relation.inverse() } else { relation }
+    }
+
+    pub fn len(&self) -> usize {
+        self.relations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.relations.is_empty()
+    }
+
+    /// Lookups answered from the cache and lookups that had to compare.
+    pub fn hit_counts(&self) -> (u64, u64) {
+        (self.hits, self.misses)
+    }
+
+    pub fn clear(&mut self) {
+        self.relations.clear();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{DigestedClock, RelationCache};
+    use clock::{VectorClock, TemporalRelation};
+
+    #[test]
+    fn test_cached_relations() {
+        let a = DigestedClock::new(VectorClock::new().incremented("a"));
+        let b = DigestedClock::new(a.clock().incremented("b"));
+        let mut cache = RelationCache::new(16);
+
+        assert_eq!(TemporalRelation::Caused, cache.relation(&a, &b));
+        assert_eq!(TemporalRelation::EffectOf, cache.relation(&b, &a));
+        assert_eq!(TemporalRelation::Caused, cache.relation(&a, &b));
+        assert_eq!((2, 1), cache.hit_counts());
+        assert_eq!(1, cache.len());
+    }
+
+    #[test]
+    fn test_update_changes_key() {
+        let a = DigestedClock::new(VectorClock::new().incremented("a"));
+        let mut b = DigestedClock::new(a.clock().incremented("b"));
+        let mut cache = RelationCache::new(16);
+
+        assert_eq!(TemporalRelation::Caused, cache.relation(&a, &b));
+
+        b.update(|c| *c = VectorClock::new().incremented("c"));
+
+        assert_eq!(TemporalRelation::Concurrent, cache.relation(&a, &b));
+        assert_eq!((0, 2), cache.hit_counts());
+    }
+
+    #[test]
+    fn test_equal_clocks() {
+        let a = DigestedClock::new(VectorClock::new().incremented("a").incremented("b"));
+        let b = DigestedClock::new(vec![("b", 1), ("a", 1), ("c", 0)].into_iter().collect::<VectorClock<_>>());
+        let mut cache = RelationCache::new(16);
+
+        assert_eq!(a.digest(), b.digest());
+        assert_eq!(TemporalRelation::Equal, cache.relation(&a, &b));
+        assert_eq!((1, 0), cache.hit_counts());
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_capacity() {
+        let clocks: Vec<_> = (0..4).map(|n| DigestedClock::new(vec![("a", n)].into_iter().collect::<VectorClock<_>>())).collect();
+        let mut cache = RelationCache::new(2);
+
+        cache.relation(&clocks[0], &clocks[1]);
+        cache.relation(&clocks[1], &clocks[2]);
+        cache.relation(&clocks[2], &clocks[3]);
+
+        assert_eq!(1, cache.len());
+
+        let mut cache = RelationCache::new(0);
+        assert_eq!(TemporalRelation::Caused, cache.relation(&clocks[0], &clocks[1]));
+        assert_eq!(TemporalRelation::Caused, cache.relation(&clocks[0], &clocks[1]));
+        assert!(cache.is_empty());
+        assert_eq!((0, 2), cache.hit_counts());
+    }
+}
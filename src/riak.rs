@@ -0,0 +1,475 @@
+//! Read and write Riak's vclocks, e.g. to migrate objects out of Riak.
+//!
+//! Riak hands clocks to clients as the `X-Riak-Vclock` header: the vclock
+//! term in the Erlang external term format, compressed with `zlib:zip`
+//! (raw deflate) and base64 encoded. The term is a list of
+//! `{Actor, {Counter, Timestamp}}` tuples, with binary actor ids and
+//! timestamps in Erlang's Gregorian seconds. The protocol buffers API
+//! carries the same compressed term without the base64 step.
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::str;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use flate2::Compression;
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+
+use base64;
+use clock::VectorClock;
+use error::{Error, Result};
+use wire::take;
+
+/// Seconds from year 0, where Riak's Gregorian timestamps start, to the
+/// Unix epoch.
+pub const GREGORIAN_UNIX_OFFSET: u64 = 62_167_219_200;
+
+/// The largest uncompressed vclock `from_binary` accepts, so a small
+/// compressed value can't inflate into an arbitrary amount of memory.
+pub const MAX_TERM_SIZE: usize = 1 << 20;
+
+const VERSION_MAGIC: u8 = 131;
+const SMALL_INTEGER_EXT: u8 = 97;
+const INTEGER_EXT: u8 = 98;
+const ATOM_EXT: u8 = 100;
+const SMALL_TUPLE_EXT: u8 = 104;
+const NIL_EXT: u8 = 106;
+const LIST_EXT: u8 = 108;
+const BINARY_EXT: u8 = 109;
+const SMALL_BIG_EXT: u8 = 110;
+const SMALL_ATOM_EXT: u8 = 115;
+const ATOM_UTF8_EXT: u8 = 118;
+const SMALL_ATOM_UTF8_EXT: u8 = 119;
+
+/// A timestamp in Riak's units for `time`.
+pub fn riak_timestamp(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()) + GREGORIAN_UNIX_OFFSET
+}
+
+/// The bucket properties controlling how Riak prunes vclocks, with Riak's
+/// defaults.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct RiakPruning {
+    /// Clocks with at most this many entries are never pruned.
+    pub small_vclock: usize,
+    /// Clocks with more entries than this are pruned down to it, as long
+    /// as the oldest entries aren't young.
+    pub big_vclock: usize,
+    /// Entries updated less than this many seconds ago are never pruned.
+    pub young_vclock: u64,
+    /// Entries not updated for more than this many seconds are pruned from
+    /// clocks above `small_vclock`.
+    pub old_vclock: u64,
+}
+
+impl Default for RiakPruning {
+    fn default() -> RiakPruning {
+        RiakPruning {
+            small_vclock: 50,
+            big_vclock: 50,
+            young_vclock: 20,
+            old_vclock: 86400,
+        }
+    }
+}
+
+/// A Riak vclock: a clock over actor ids, plus the time each actor's
+/// entry was last updated.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct RiakVclock {
+    clock: VectorClock<Vec<u8>>,
+    timestamps: HashMap<Vec<u8>, u64>,
+}
+
+impl RiakVclock {
+    pub fn new() -> RiakVclock {
+        RiakVclock::default()
+    }
+
+    /// Wrap `clock`, with all its entries last updated at `timestamp`.
+    pub fn from_clock(clock: VectorClock<Vec<u8>>, timestamp: u64) -> RiakVclock {
+        let timestamps = clock.iter().map(|(actor, _)| (actor.clone(), timestamp)).collect();
+
+        RiakVclock {
+            clock,
+            timestamps,
+        }
+    }
+
+    pub fn clock(&self) -> &VectorClock<Vec<u8>> {
+        &self.clock
+    }
+
+    pub fn into_clock(self) -> VectorClock<Vec<u8>> {
+        self.clock
+    }
+
+    pub fn timestamp(&self, actor: &[u8]) -> Option<u64> {
+        self.timestamps.get(actor).cloned()
+    }
+
+    pub fn increment(&mut self, actor: Vec<u8>, timestamp: u64) {
+        self.clock.increment(actor.clone());
+        self.timestamps.insert(actor, timestamp);
+    }
+
+    /// Merge `other` into `self` the way Riak does: each actor keeps the
+    /// higher counter along with its timestamp, and the later timestamp
+    /// when the counters are equal.
+    pub fn merge(&mut self, other: &RiakVclock) {
+        for (actor, &n) in other.clock.iter() {
+            let ours = self.clock.get(actor);
+            let theirs = other.timestamps.get(actor).cloned().unwrap_or(0);
+
+            if n > ours {
+                self.timestamps.insert(actor.clone(), theirs);
+            }
+            else if n == ours {
+                let timestamp = self.timestamps.entry(actor.clone()).or_insert(theirs);
+                *timestamp = (*timestamp).max(theirs);
+            }
+        }
+
+        self.clock.merge(&other.clock);
+    }
+
+    /// Drop entries following Riak's `vclock:prune`, oldest first, and
+    /// return how many were dropped. `now` is in Riak's units, see
+    /// `riak_timestamp`.
+    pub fn prune(&mut self, now: u64, pruning: &RiakPruning) -> usize {
+        let mut entries: Vec<(u64, Vec<u8>)> = self.clock.iter()
+            .map(|(actor, _)| (self.timestamps.get(actor).cloned().unwrap_or(0), actor.clone()))
+            .collect();
+        entries.sort();
+
+        let mut remaining = entries.len();
+        let mut pruned = 0;
+
+        for (timestamp, actor) in entries {
+            let age = now.saturating_sub(timestamp);
+
+            if remaining <= pruning.small_vclock || age < pruning.young_vclock {
+                break;
+            }
+
+            if remaining <= pruning.big_vclock && age <= pruning.old_vclock {
+                break;
+            }
+
+            self.clock.remove_host(&actor);
+            self.timestamps.remove(&actor);
+            remaining -= 1;
+            pruned += 1;
+        }
+
+        pruned
+    }
+
+    /// Decode an `X-Riak-Vclock` header value.
+    pub fn from_riak_vclock(value: &[u8]) -> Result<RiakVclock> {
+        let value = str::from_utf8(value).ok()
+            .and_then(|value| base64::decode(value.trim(), base64::STANDARD, true))
+            .ok_or_else(|| Error::Decode("Riak vclock is not base64".to_owned()))?;
+
+        RiakVclock::from_binary(&value)
+    }
+
+    /// Encode the clock as an `X-Riak-Vclock` header value.
+    pub fn to_riak_vclock(&self) -> Vec<u8> {
+        let mut out = String::new();
+        base64::encode(&self.to_binary(), base64::STANDARD, true, &mut out);
+        out.into_bytes()
+    }
+
+    /// Decode a compressed vclock, as carried by Riak's protocol buffers
+    /// API. Vclocks that inflate to more than `MAX_TERM_SIZE` bytes are
+    /// rejected with `Error::CapacityExceeded`.
+    pub fn from_binary(bytes: &[u8]) -> Result<RiakVclock> {
+        RiakVclock::from_binary_with_limit(bytes, MAX_TERM_SIZE)
+    }
+
+    /// Like `from_binary`, rejecting vclocks that inflate to more than
+    /// `limit` bytes.
+    pub fn from_binary_with_limit(bytes: &[u8], limit: usize) -> Result<RiakVclock> {
+        let mut term = Vec::new();
+        DeflateDecoder::new(bytes).take(limit as u64 + 1).read_to_end(&mut term)
+            .map_err(|e| Error::Decode(format!("Riak vclock is not deflate compressed: {}", e)))?;
+
+        if term.len() > limit {
+            return Err(Error::CapacityExceeded { capacity: limit });
+        }
+
+        decode_term(&term)
+    }
+
+    pub fn to_binary(&self) -> Vec<u8> {
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+
+        encoder.write_all(&self.encode_term())
+            .and_then(|_| encoder.finish())
+            .expect("compressing into memory cannot fail")
+    }
+
+    fn encode_term(&self) -> Vec<u8> {
+        let mut entries: Vec<_> = self.clock.iter().collect();
+        entries.sort();
+
+        let mut out = vec![VERSION_MAGIC];
+
+        if !entries.is_empty() {
+            out.push(LIST_EXT);
+            out.extend_from_slice(&(entries.len() as u32).to_be_bytes());
+
+            for (actor, &n) in entries {
+                out.extend_from_slice(&[SMALL_TUPLE_EXT, 2, BINARY_EXT]);
+                out.extend_from_slice(&(actor.len() as u32).to_be_bytes());
+                out.extend_from_slice(actor);
+                out.extend_from_slice(&[SMALL_TUPLE_EXT, 2]);
+                write_integer(&mut out, n);
+                write_integer(&mut out, self.timestamps.get(actor).cloned().unwrap_or(0));
+            }
+        }
+
+        out.push(NIL_EXT);
+        out
+    }
+}
+
+impl From<RiakVclock> for VectorClock<Vec<u8>> {
+    fn from(vclock: RiakVclock) -> VectorClock<Vec<u8>> {
+        vclock.into_clock()
+    }
+}
+
+fn decode_term(term: &[u8]) -> Result<RiakVclock> {
+    let mut input = term;
+    let malformed = |what: &str| Error::Decode(format!("malformed Riak vclock: {}", what));
+
+    if take(&mut input, 1)?[0] != VERSION_MAGIC {
+        return Err(malformed("not an Erlang term"));
+    }
+
+    let count = match take(&mut input, 1)?[0] {
+        NIL_EXT => 0,
+        LIST_EXT => read_u32(&mut input)? as usize,
+        _ => return Err(malformed("not a list")),
+    };
+
+    let mut vclock = RiakVclock::new();
+
+    for _ in 0..count {
+        if take(&mut input, 2)? != [SMALL_TUPLE_EXT, 2] {
+            return Err(malformed("entry is not a pair"));
+        }
+
+        let actor = read_actor(&mut input)?;
+
+        if take(&mut input, 2)? != [SMALL_TUPLE_EXT, 2] {
+            return Err(malformed("entry value is not a {counter, timestamp} pair"));
+        }
+
+        let n = read_integer(&mut input)?;
+        let timestamp = read_integer(&mut input)?;
+
+        if vclock.clock.contains_host(&actor) {
+            return Err(Error::InvalidEntry("actor listed twice in Riak vclock".to_owned()));
+        }
+
+        if n > 0 {
+            vclock.clock.extend(Some((actor.clone(), n)));
+            vclock.timestamps.insert(actor, timestamp);
+        }
+    }
+
+    if count > 0 && take(&mut input, 1)?[0] != NIL_EXT {
+        return Err(malformed("improper list"));
+    }
+
+    if !input.is_empty() {
+        return Err(malformed("trailing bytes"));
+    }
+
+    Ok(vclock)
+}
+
+/// Read an actor id. Riak uses binaries, but old `riak_core` clocks used
+/// node names, which are atoms; those are read as their name's bytes.
+fn read_actor(input: &mut &[u8]) -> Result<Vec<u8>> {
+    let len = match take(input, 1)?[0] {
+        BINARY_EXT => read_u32(input)? as usize,
+        ATOM_EXT | ATOM_UTF8_EXT => u16::from_be_bytes([take(input, 1)?[0], take(input, 1)?[0]]) as usize,
+        SMALL_ATOM_EXT | SMALL_ATOM_UTF8_EXT => take(input, 1)?[0] as usize,
+        _ => return Err(Error::Decode("malformed Riak vclock: actor is not a binary".to_owned())),
+    };
+
+    Ok(take(input, len)?.to_vec())
+}
+
+fn read_u32(input: &mut &[u8]) -> Result<u32> {
+    let bytes = take(input, 4)?;
+    Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn read_integer(input: &mut &[u8]) -> Result<u64> {
+    match take(input, 1)?[0] {
+        SMALL_INTEGER_EXT => Ok(u64::from(take(input, 1)?[0])),
+        INTEGER_EXT => {
+            let n = read_u32(input)? as i32;
+
+            if n < 0 {
+                return Err(Error::Decode("negative integer in Riak vclock".to_owned()));
+            }
+
+            Ok(n as u64)
+        },
+        SMALL_BIG_EXT => {
+            let len = take(input, 1)?[0] as usize;
+
+            if take(input, 1)?[0] != 0 {
+                return Err(Error::Decode("negative integer in Riak vclock".to_owned()));
+            }
+
+            let digits = take(input, len)?;
+
+            if digits.iter().skip(8).any(|&d| d != 0) {
+                return Err(Error::Overflow);
+            }
+
+            Ok(digits.iter().take(8).rev().fold(0, |n, &d| (n << 8) | u64::from(d)))
+        },
+        _ => Err(Error::Decode("malformed Riak vclock: expected an integer".to_owned())),
+    }
+}
+
+/// Write `n` the way `term_to_binary` does, in the smallest encoding.
+fn write_integer(out: &mut Vec<u8>, n: u64) {
+    if n < 256 {
+        out.extend_from_slice(&[SMALL_INTEGER_EXT, n as u8]);
+    }
+    else if n <= i32::MAX as u64 {
+        out.push(INTEGER_EXT);
+        out.extend_from_slice(&(n as u32).to_be_bytes());
+    }
+    else {
+        let digits = n.to_le_bytes();
+        let len = 8 - n.leading_zeros() as usize / 8;
+
+        out.extend_from_slice(&[SMALL_BIG_EXT, len as u8, 0]);
+        out.extend_from_slice(&digits[..len]);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{RiakVclock, RiakPruning, MAX_TERM_SIZE};
+    use clock::VectorClock;
+
+    // [{<<"a">>, {1, 63959196798}}, {<<"bb">>, {300, 63959196800}}], as
+    // written by Riak.
+    const HEADER: &[u8] = b"a85hYGBgymDKBVKMiRlMiYx5rAx1T5yf8EHEmJKSMpiSgHI6QPEGkHgWAA==";
+
+    fn vclock() -> RiakVclock {
+        let mut vclock = RiakVclock::new();
+        vclock.increment(b"a".to_vec(), 63959196798);
+
+        let bb = vec![(b"bb".to_vec(), 300)].into_iter().collect();
+        vclock.merge(&RiakVclock::from_clock(bb, 63959196800));
+        vclock
+    }
+
+    #[test]
+    fn test_decode_riak_header() {
+        let decoded = RiakVclock::from_riak_vclock(HEADER).unwrap();
+
+        assert_eq!(vclock(), decoded);
+        assert_eq!(Some(63959196800), decoded.timestamp(b"bb"));
+        assert_eq!(300, VectorClock::from(decoded).get(&b"bb".to_vec()));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let vclock = vclock();
+
+        assert_eq!(vclock, RiakVclock::from_riak_vclock(&vclock.to_riak_vclock()).unwrap());
+        assert_eq!(vclock, RiakVclock::from_binary(&vclock.to_binary()).unwrap());
+        assert_eq!(vec![131, 106], RiakVclock::new().encode_term());
+        assert_eq!(RiakVclock::new(), RiakVclock::from_binary(&RiakVclock::new().to_binary()).unwrap());
+    }
+
+    #[test]
+    fn test_atom_actors() {
+        let decoded = RiakVclock::from_riak_vclock(b"a85hYGBgzGBKYeDMy09JdcjILy7JYEpkymNlqHvi/IQvCwA=").unwrap();
+
+        assert_eq!(2, decoded.clock().get(&b"node@host".to_vec()));
+    }
+
+    #[test]
+    fn test_rejects_malformed() {
+        assert!(RiakVclock::from_riak_vclock(b"not base64!").is_err());
+        assert!(RiakVclock::from_riak_vclock(b"AAAA").is_err());
+        assert!(RiakVclock::from_binary(&[]).is_err());
+    }
+
+    #[test]
+    fn test_inflate_limit() {
+        use std::io::Write;
+
+        use flate2::Compression;
+        use flate2::write::DeflateEncoder;
+        use error::Error;
+
+        let binary = vclock().to_binary();
+        let size = vclock().encode_term().len();
+
+        assert_eq!(vclock(), RiakVclock::from_binary_with_limit(&binary, size).unwrap());
+        assert_eq!(Err(Error::CapacityExceeded { capacity: size - 1 }), RiakVclock::from_binary_with_limit(&binary, size - 1));
+
+        let mut bomb = DeflateEncoder::new(Vec::new(), Compression::best());
+        bomb.write_all(&vec![0; MAX_TERM_SIZE + 1]).unwrap();
+
+        assert_eq!(Err(Error::CapacityExceeded { capacity: MAX_TERM_SIZE }), RiakVclock::from_binary(&bomb.finish().unwrap()));
+    }
+
+    #[test]
+    fn test_merge_keeps_newer_timestamps() {
+        let mut a = RiakVclock::from_clock(VectorClock::new().incremented(b"x".to_vec()), 10);
+        let b = RiakVclock::from_clock(VectorClock::new().incremented(b"x".to_vec()), 20);
+        let c = RiakVclock::from_clock(VectorClock::new().incremented(b"x".to_vec()), 5);
+
+        a.merge(&b);
+        a.merge(&c);
+        assert_eq!(Some(20), a.timestamp(b"x"));
+    }
+
+    #[test]
+    fn test_prune() {
+        let pruning = RiakPruning {
+            small_vclock: 2,
+            big_vclock: 3,
+            young_vclock: 10,
+            old_vclock: 100,
+        };
+
+        let mut vclock = RiakVclock::new();
+
+        for (i, &t) in [1000u64, 1001, 1500, 1990, 1995].iter().enumerate() {
+            vclock.increment(vec![i as u8], t);
+        }
+
+        // The two oldest go because the clock is above big_vclock, and the
+        // third because it's older than old_vclock. That leaves
+        // small_vclock entries, which are kept.
+        assert_eq!(3, vclock.prune(2000, &pruning));
+        assert_eq!(2, vclock.clock().len());
+        assert_eq!(0, vclock.prune(2000, &pruning));
+
+        let mut young = RiakVclock::new();
+
+        for i in 0..5 {
+            young.increment(vec![i], 1995);
+        }
+
+        assert_eq!(0, young.prune(2000, &pruning));
+    }
+}
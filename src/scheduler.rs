@@ -0,0 +1,358 @@
+use std::collections::HashMap;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hash};
+use std::time::{Duration, Instant};
+
+use clock::VectorClock;
+use membership::{MembershipChange, MembershipView};
+
+/// A sync session the application should run with `peer`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SyncTask<Peer> {
+    pub peer: Peer,
+    /// Divergence between the local clock and the last clock known for the
+    /// peer, as measured by `VectorClock::distance`.
+    pub divergence: u64,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct SchedulerConfig {
+    /// Interval between syncs with a peer that keeps producing changes.
+    pub min_interval: Duration,
+    /// Upper bound for the interval of a peer whose syncs find nothing.
+    pub max_interval: Duration,
+    /// Divergence at which a peer is synced immediately, regardless of its
+    /// interval.
+    pub divergence_threshold: u64,
+    /// Fraction of the interval, between 0 and 1 inclusive, to randomly add
+    /// or subtract so that peers don't all sync in lockstep.
+    pub jitter: f64,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> SchedulerConfig {
+        SchedulerConfig {
+            min_interval: Duration::from_secs(1),
+            max_interval: Duration::from_secs(60),
+            divergence_threshold: 1000,
+            jitter: 0.1,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct PeerState<HostType: Hash + Eq> {
+    known: VectorClock<HostType>,
+    interval: Duration,
+    due: Instant,
+    in_flight: bool,
+    /// Whether the last session failed, in which case the peer waits for
+    /// `due` however far it diverges.
+    failed: bool,
+}
+
+/// Decides when, and with which peers, to run anti-entropy sessions.
+///
+/// A peer whose syncs keep finding nothing to exchange is backed off
+/// exponentially up to `max_interval`; one that produces changes, or whose
+/// last known clock diverges from ours by more than the threshold, is synced
+/// at the minimum interval again. A peer whose last sync failed is only
+/// retried once its interval has passed.
+#[derive(Debug)]
+pub struct AntiEntropyScheduler<Peer: Hash + Eq, HostType: Hash + Eq> {
+    config: SchedulerConfig,
+    peers: HashMap<Peer, PeerState<HostType>>,
+    random: RandomState,
+    rounds: u64,
+}
+
+impl<Peer: Clone + Hash + Eq, HostType: Clone + Hash + Eq> AntiEntropyScheduler<Peer, HostType> {
+    /// Panics if `config.jitter` is not between 0 and 1.
+    pub fn new(config: SchedulerConfig) -> AntiEntropyScheduler<Peer, HostType> {
+        assert!((0.0..=1.0).contains(&config.jitter), "scheduler jitter must be between 0 and 1, got {}", config.jitter);
+
+        AntiEntropyScheduler {
+            config,
+            peers: HashMap::new(),
+            random: RandomState::new(),
+            rounds: 0,
+        }
+    }
+
+    /// Start tracking `peer`. It becomes due immediately.
+    pub fn add_peer(&mut self, peer: Peer, now: Instant) {
+        let interval = self.config.min_interval;
+
+        self.peers.entry(peer).or_insert(PeerState {
+            known: VectorClock::new(),
+            interval,
+            due: now,
+            in_flight: false,
+            failed: false,
+        });
+    }
+
+    pub fn remove_peer(&mut self, peer: &Peer) {
+        self.peers.remove(peer);
+    }
+
+    /// Add and remove peers according to the changes reported by `view`.
+    pub fn follow<View>(&mut self, view: &mut View, now: Instant)
+        where View: MembershipView<Peer=Peer>
+    {
+        for change in view.changes() {
+            match change {
+                MembershipChange::Joined(peer) => self.add_peer(peer, now),
+                MembershipChange::Left(peer) => self.remove_peer(&peer),
+            }
+        }
+    }
+
+    /// Record the latest clock learned for `peer`, e.g. from a gossiped
+    /// digest.
+    pub fn observe(&mut self, peer: &Peer, clock: &VectorClock<HostType>) {
+        if let Some(state) = self.peers.get_mut(peer) {
+            state.known.merge(clock);
+        }
+    }
+
+    /// Return the sync sessions to start now, most divergent peer first.
+    ///
+    /// A peer is not returned again until `completed` is called for it.
+    pub fn poll(&mut self, local: &VectorClock<HostType>, now: Instant) -> Vec<SyncTask<Peer>> {
+        let threshold = self.config.divergence_threshold;
+        let mut tasks = Vec::new();
+
+        for (peer, state) in self.peers.iter_mut() {
+            if state.in_flight {
+                continue;
+            }
+
+            let divergence = local.distance(&state.known);
+
+            if now >= state.due || (!state.failed && divergence >= threshold) {
+                state.in_flight = true;
+                tasks.push(SyncTask {
+                    peer: peer.clone(),
+                    divergence,
+                });
+            }
+        }
+
+        tasks.sort_by_key(|t| ::std::cmp::Reverse(t.divergence));
+        tasks
+    }
+
+    /// Report that the session with `peer` finished, whether it exchanged
+    /// any entries, and the clock the peer acknowledged having after it,
+    /// which divergence is measured against from now on.
+    pub fn completed(&mut self, peer: &Peer, acknowledged: &VectorClock<HostType>, changed: bool, now: Instant) {
+        let (min, max) = (self.config.min_interval, self.config.max_interval);
+
+        if let Some(state) = self.peers.get_mut(peer) {
+            self.rounds += 1;
+            state.known.merge(acknowledged);
+            state.in_flight = false;
+            state.failed = false;
+            state.interval = if changed { min } else { state.interval.checked_mul(2).map_or(max, |i| i.min(max)) };
+            state.due = due_after(now, jittered(&self.random, self.rounds, self.config.jitter, state.interval));
+        }
+    }
+
+    /// Report that the session with `peer` failed. The peer is retried after
+    /// its current interval.
+    pub fn failed(&mut self, peer: &Peer, now: Instant) {
+        if let Some(state) = self.peers.get_mut(peer) {
+            self.rounds += 1;
+            state.in_flight = false;
+            state.failed = true;
+            state.due = due_after(now, jittered(&self.random, self.rounds, self.config.jitter, state.interval));
+        }
+    }
+
+    /// The next time any idle peer becomes due.
+    pub fn next_due(&self) -> Option<Instant> {
+        self.peers.values().filter(|s| !s.in_flight).map(|s| s.due).min()
+    }
+}
+
+// `now + interval`, or as far out as an `Instant` goes.
+fn due_after(now: Instant, mut interval: Duration) -> Instant {
+    loop {
+        if let Some(due) = now.checked_add(interval) {
+            return due;
+        }
+
+        interval /= 2;
+    }
+}
+
+fn jittered(random: &RandomState, round: u64, jitter: f64, interval: Duration) -> Duration {
+    if jitter == 0.0 {
+        return interval;
+    }
+
+    let sample = random.hash_one(round) as f64 / u64::MAX as f64;
+    interval.mul_f64(1.0 + jitter * (2.0 * sample - 1.0))
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use super::{AntiEntropyScheduler, SchedulerConfig, SyncTask};
+    use clock::VectorClock;
+    use membership::LocalMembership;
+
+    fn config() -> SchedulerConfig {
+        SchedulerConfig {
+            min_interval: Duration::from_secs(1),
+            max_interval: Duration::from_secs(8),
+            divergence_threshold: 10,
+            jitter: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_new_peer_is_due() {
+        let now = Instant::now();
+        let mut s = AntiEntropyScheduler::new(config());
+        s.add_peer("p1", now);
+
+        let local = VectorClock::new().incremented("A");
+
+        assert_eq!(vec![SyncTask { peer: "p1", divergence: 1 }], s.poll(&local, now));
+        assert!(s.poll(&local, now).is_empty());
+    }
+
+    #[test]
+    fn test_backoff_when_idle() {
+        let now = Instant::now();
+        let mut s = AntiEntropyScheduler::new(config());
+        let local = VectorClock::<&str>::new();
+        s.add_peer("p1", now);
+
+        for expected in &[2, 4, 8, 8] {
+            s.poll(&local, now);
+            s.completed(&"p1", &local, false, now);
+            assert_eq!(Some(now + Duration::from_secs(*expected)), s.next_due());
+        }
+
+        s.poll(&local, now + Duration::from_secs(8));
+        s.completed(&"p1", &local, true, now);
+        assert_eq!(Some(now + Duration::from_secs(1)), s.next_due());
+    }
+
+    #[test]
+    fn test_divergence_triggers_early_sync() {
+        let now = Instant::now();
+        let mut s = AntiEntropyScheduler::new(config());
+        s.add_peer("p1", now);
+
+        let mut local = VectorClock::new();
+        s.poll(&local, now);
+        s.completed(&"p1", &local, false, now);
+
+        assert!(s.poll(&local, now).is_empty());
+
+        for _ in 0..10 {
+            local = local.incremented("A");
+        }
+
+        assert_eq!(vec![SyncTask { peer: "p1", divergence: 10 }], s.poll(&local, now));
+    }
+
+    #[test]
+    fn test_failed_peer_waits_despite_divergence() {
+        let now = Instant::now();
+        let mut s = AntiEntropyScheduler::new(config());
+        s.add_peer("p1", now);
+
+        let mut local = VectorClock::new();
+
+        for _ in 0..10 {
+            local = local.incremented("A");
+        }
+
+        assert_eq!(1, s.poll(&local, now).len());
+        s.failed(&"p1", now);
+
+        assert!(s.poll(&local, now).is_empty());
+        assert_eq!(1, s.poll(&local, now + Duration::from_secs(1)).len());
+
+        s.completed(&"p1", &VectorClock::new(), true, now);
+        assert_eq!(1, s.poll(&local, now).len());
+    }
+
+    #[test]
+    fn test_backoff_saturates() {
+        let now = Instant::now();
+        let mut s = AntiEntropyScheduler::new(SchedulerConfig { max_interval: Duration::MAX, ..config() });
+        let local = VectorClock::<&str>::new();
+        s.add_peer("p1", now);
+
+        for _ in 0..70 {
+            s.poll(&local, now + Duration::from_secs(1 << 40));
+            s.completed(&"p1", &local, false, now);
+        }
+
+        assert_eq!(Duration::MAX, s.peers[&"p1"].interval);
+    }
+
+    #[test]
+    #[should_panic(expected = "jitter")]
+    fn test_rejects_invalid_jitter() {
+        AntiEntropyScheduler::<&str, &str>::new(SchedulerConfig { jitter: f64::NAN, ..config() });
+    }
+
+    #[test]
+    fn test_synced_peer_goes_quiet() {
+        let now = Instant::now();
+        let mut s = AntiEntropyScheduler::new(config());
+        s.add_peer("p1", now);
+
+        let mut local = VectorClock::new();
+
+        for _ in 0..10 {
+            local = local.incremented("A");
+        }
+
+        assert_eq!(1, s.poll(&local, now).len());
+        s.completed(&"p1", &local, true, now);
+
+        assert!(s.poll(&local, now).is_empty());
+        assert_eq!(vec![SyncTask { peer: "p1", divergence: 0 }], s.poll(&local, now + Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn test_follow_membership() {
+        let now = Instant::now();
+        let mut s = AntiEntropyScheduler::new(config());
+        let mut m = LocalMembership::new();
+
+        m.join("p1");
+        m.join("p2");
+        m.leave(&"p1");
+        s.follow(&mut m, now);
+
+        let tasks = s.poll(&VectorClock::<&str>::new(), now);
+
+        assert_eq!(vec![SyncTask { peer: "p2", divergence: 0 }], tasks);
+    }
+
+    #[test]
+    fn test_jitter_stays_in_bounds() {
+        let now = Instant::now();
+        let mut s = AntiEntropyScheduler::new(SchedulerConfig { jitter: 0.5, ..config() });
+        s.add_peer("p1", now);
+
+        for _ in 0..20 {
+            s.poll(&VectorClock::<&str>::new(), now);
+            s.completed(&"p1", &VectorClock::new(), true, now);
+
+            let due = s.next_due().unwrap();
+            assert!(due >= now + Duration::from_millis(500));
+            assert!(due <= now + Duration::from_millis(1500));
+        }
+    }
+}
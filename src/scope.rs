@@ -0,0 +1,130 @@
+//! A task-local causal context for the request being handled.
+//!
+//! `with_clock` runs a future with a clock installed for the duration of
+//! each of its polls, the way tokio's task-locals work, so that code called
+//! from the future can read and advance the clock without it being passed
+//! around. The axum `clock_layer` installs the request's clock this way and
+//! `ClockSession` picks it up for outgoing calls.
+
+use std::cell::RefCell;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll};
+
+use clock::VectorClock;
+
+type SharedClock = Arc<Mutex<VectorClock<String>>>;
+
+thread_local! {
+    static CURRENT: RefCell<Option<SharedClock>> = const { RefCell::new(None) };
+}
+
+/// A future running with a causal context installed, see `with_clock`.
+pub struct ClockScope<F: ?Sized> {
+    clock: SharedClock,
+    future: Pin<Box<F>>,
+}
+
+/// Run `future` with `clock` as the current causal context.
+pub fn with_clock<F: Future>(clock: VectorClock<String>, future: F) -> ClockScope<F> {
+    ClockScope::new(clock, Box::pin(future))
+}
+
+/// The clock of the innermost scope being polled on this thread, if any.
+pub fn current_clock() -> Option<VectorClock<String>> {
+    CURRENT.with(|current| current.borrow().as_ref().map(|clock| lock(clock).clone()))
+}
+
+/// Merge `clock` into the current scope's clock, returning whether there
+/// was a scope to merge it into.
+pub fn merge_current(clock: &VectorClock<String>) -> bool {
+    CURRENT.with(|current| match *current.borrow() {
+        Some(ref current) => {
+            lock(current).merge(clock);
+            true
+        },
+        None => false,
+    })
+}
+
+impl<F: ?Sized> ClockScope<F> {
+    pub(crate) fn new(clock: VectorClock<String>, future: Pin<Box<F>>) -> ClockScope<F> {
+        ClockScope {
+            clock: Arc::new(Mutex::new(clock)),
+            future,
+        }
+    }
+
+    /// The scope's clock as it currently stands.
+    pub fn clock(&self) -> VectorClock<String> {
+        lock(&self.clock).clone()
+    }
+}
+
+impl<F: Future + ?Sized> Future for ClockScope<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<F::Output> {
+        let _restore = Restore(CURRENT.with(|current| current.replace(Some(self.clock.clone()))));
+        self.future.as_mut().poll(cx)
+    }
+}
+
+// Puts back the enclosing scope's clock, also when the poll panics.
+struct Restore(Option<SharedClock>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        CURRENT.with(|current| *current.borrow_mut() = previous);
+    }
+}
+
+fn lock(clock: &SharedClock) -> MutexGuard<'_, VectorClock<String>> {
+    clock.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod test {
+    use std::future::{self, Future};
+    use std::pin::pin;
+    use std::task::{Context, Poll, Waker};
+
+    use super::{with_clock, current_clock, merge_current};
+    use clock::VectorClock;
+
+    fn run<F: Future>(future: F) -> F::Output {
+        match pin!(future).poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("future should not block"),
+        }
+    }
+
+    #[test]
+    fn test_scope() {
+        let outer = VectorClock::new().incremented("a".to_owned());
+        let inner = VectorClock::new().incremented("b".to_owned());
+
+        assert_eq!(None, current_clock());
+        assert!(!merge_current(&inner));
+
+        let mut scope = with_clock(outer.clone(), future::poll_fn(|_| {
+            assert_eq!(Some(outer.clone()), current_clock());
+
+            run(with_clock(inner.clone(), future::poll_fn(|_| {
+                assert_eq!(Some(inner.clone()), current_clock());
+                Poll::Ready(())
+            })));
+
+            assert_eq!(Some(outer.clone()), current_clock());
+            assert!(merge_current(&inner));
+            Poll::Ready(())
+        }));
+
+        run(&mut scope);
+
+        assert_eq!(outer.merge_with(&inner), scope.clock());
+        assert_eq!(None, current_clock());
+    }
+}
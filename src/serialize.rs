@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde::de::{self, EnumAccess, VariantAccess, Visitor};
+
+use clock::{VectorClock, TemporalRelation};
+use counter::Counter;
+
+/// Clocks serialize as a map from host to counter. For formats that only
+/// allow string keys, see the `pairs` module.
+impl<HostType, CounterType> Serialize for VectorClock<HostType, CounterType>
+    where HostType: Serialize + Clone + Hash + Eq, CounterType: Counter + Serialize
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.iter())
+    }
+}
+
+impl<'de, HostType, CounterType> Deserialize<'de> for VectorClock<HostType, CounterType>
+    where HostType: Deserialize<'de> + Clone + Hash + Eq, CounterType: Counter + Deserialize<'de>
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<VectorClock<HostType, CounterType>, D::Error> {
+        HashMap::<HostType, CounterType>::deserialize(deserializer)
+            .map(|entries| entries.into_iter().collect())
+    }
+}
+
+const VARIANTS: &[&str] = &["Equal", "Caused", "EffectOf", "Concurrent"];
+
+impl Serialize for TemporalRelation {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let index = match *self {
+            TemporalRelation::Equal => 0,
+            TemporalRelation::Caused => 1,
+            TemporalRelation::EffectOf => 2,
+            TemporalRelation::Concurrent => 3,
+        };
+
+        serializer.serialize_unit_variant("TemporalRelation", index, VARIANTS[index as usize])
+    }
+}
+
+impl<'de> Deserialize<'de> for TemporalRelation {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<TemporalRelation, D::Error> {
+        deserializer.deserialize_enum("TemporalRelation", VARIANTS, RelationVisitor)
+    }
+}
+
+struct RelationVisitor;
+
+impl<'de> Visitor<'de> for RelationVisitor {
+    type Value = TemporalRelation;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a temporal relation")
+    }
+
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<TemporalRelation, A::Error> {
+        let (variant, access) = data.variant::<Variant>()?;
+        access.unit_variant()?;
+        Ok(variant.0)
+    }
+}
+
+struct Variant(TemporalRelation);
+
+impl<'de> Deserialize<'de> for Variant {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Variant, D::Error> {
+        deserializer.deserialize_identifier(VariantVisitor)
+    }
+}
+
+struct VariantVisitor;
+
+impl<'de> Visitor<'de> for VariantVisitor {
+    type Value = Variant;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a temporal relation variant")
+    }
+
+    fn visit_u64<E: de::Error>(self, index: u64) -> Result<Variant, E> {
+        match index {
+            0 => Ok(Variant(TemporalRelation::Equal)),
+            1 => Ok(Variant(TemporalRelation::Caused)),
+            2 => Ok(Variant(TemporalRelation::EffectOf)),
+            3 => Ok(Variant(TemporalRelation::Concurrent)),
+            _ => Err(E::invalid_value(de::Unexpected::Unsigned(index), &"a variant index below 4")),
+        }
+    }
+
+    fn visit_str<E: de::Error>(self, name: &str) -> Result<Variant, E> {
+        match VARIANTS.iter().position(|&v| v == name) {
+            Some(index) => self.visit_u64(index as u64),
+            None => Err(E::unknown_variant(name, VARIANTS)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json;
+
+    use clock::{VectorClock, TemporalRelation};
+
+    #[test]
+    fn test_clock_as_map() {
+        let c = VectorClock::new().incremented("a".to_owned()).incremented("a".to_owned());
+
+        let json = serde_json::to_string(&c).unwrap();
+
+        assert_eq!(r#"{"a":2}"#, json);
+        assert_eq!(c, serde_json::from_str(&json).unwrap());
+        assert!(serde_json::from_str::<VectorClock<String>>(r#"{"a":-1}"#).is_err());
+    }
+
+    #[test]
+    fn test_non_string_hosts_in_map_keys() {
+        let c = VectorClock::new().incremented(7u32);
+
+        let json = serde_json::to_string(&c).unwrap();
+
+        assert_eq!(r#"{"7":1}"#, json);
+        assert_eq!(c, serde_json::from_str(&json).unwrap());
+    }
+
+    #[test]
+    fn test_narrow_counters() {
+        let c = VectorClock::<String, u8>::default().incremented("a".to_owned());
+
+        let json = serde_json::to_string(&c).unwrap();
+
+        assert_eq!(r#"{"a":1}"#, json);
+        assert_eq!(c, serde_json::from_str(&json).unwrap());
+        assert!(serde_json::from_str::<VectorClock<String, u8>>(r#"{"a":256}"#).is_err());
+    }
+
+    #[test]
+    fn test_relation() {
+        let json = serde_json::to_string(&TemporalRelation::EffectOf).unwrap();
+
+        assert_eq!(r#""EffectOf""#, json);
+        assert_eq!(TemporalRelation::EffectOf, serde_json::from_str(&json).unwrap());
+        assert!(serde_json::from_str::<TemporalRelation>(r#""After""#).is_err());
+    }
+}
@@ -0,0 +1,134 @@
+use std::hash::Hash;
+
+use clock::VectorClock;
+use dvv::DottedVersionVector;
+use owned::OwnedHost;
+
+/// The result of a server accepting a client write.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Assigned<HostType: Hash + Eq> {
+    /// The version to store with the write: the server's new dot and the
+    /// context the client sent.
+    pub version: DottedVersionVector<HostType>,
+    /// The context to hand back to the client for its next write.
+    pub context: VectorClock<HostType>,
+    /// Indices of the stored versions that the write supersedes.
+    pub obsolete: Vec<usize>,
+}
+
+/// Assigns versions on behalf of thin clients.
+///
+/// Clients never tick a clock themselves. They send back the opaque context
+/// they last read, and the server creates a new dot for its own entry on
+/// top of that context. Versions therefore only ever contain server IDs, no
+/// matter how many clients write through them.
+///
+/// Versions are dotted version vectors rather than plain clocks: a plain
+/// clock of a blind write would dominate the siblings it didn't replace.
+#[derive(Debug)]
+pub struct ServerDots<HostType> {
+    host: OwnedHost<HostType>,
+}
+
+impl<HostType: Clone + Hash + Eq> ServerDots<HostType> {
+    pub fn new(host: OwnedHost<HostType>) -> ServerDots<HostType> {
+        ServerDots {
+            host,
+        }
+    }
+
+    pub fn host(&self) -> &HostType {
+        self.host.host()
+    }
+
+    /// Assign a version to a write made with `context`, given the versions
+    /// currently stored for the key.
+    ///
+    /// Stored versions covered by `context` were seen by the client and are
+    /// reported as obsolete; the rest stay as siblings of the new version.
+    pub fn assign<'a, I>(&self, context: &VectorClock<HostType>, stored: I) -> Assigned<HostType>
+        where I: IntoIterator<Item=&'a DottedVersionVector<HostType>>, HostType: 'a
+    {
+        let stored: Vec<_> = stored.into_iter().collect();
+        let version = DottedVersionVector::event(context, stored.iter().cloned(), self.host.host().clone());
+
+        let obsolete = stored.iter()
+            .enumerate()
+            .filter(|&(_, v)| version.dominates(v))
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+
+        let context = DottedVersionVector::join(stored.iter()
+            .enumerate()
+            .filter(|&(i, _)| !obsolete.contains(&i))
+            .map(|(_, v)| *v)
+            .chain(Some(&version)));
+
+        Assigned {
+            version,
+            context,
+            obsolete,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ServerDots;
+    use clock::VectorClock;
+    use dot::Dot;
+    use dvv::DottedVersionVector;
+    use owned::OwnedHost;
+
+    type StrVectorClock = VectorClock<&'static str>;
+
+    #[test]
+    fn test_first_write() {
+        let server = ServerDots::new(OwnedHost::new("S"));
+        let a = server.assign(&StrVectorClock::new(), vec![]);
+
+        assert_eq!(&Dot::new("S", 1), a.version.dot());
+        assert_eq!(vec![("S", 1)], a.context.clone().into_iter().collect::<Vec<_>>());
+        assert_eq!(a.version.to_clock(), a.context);
+        assert!(a.obsolete.is_empty());
+    }
+
+    #[test]
+    fn test_write_with_context_supersedes() {
+        let server = ServerDots::new(OwnedHost::new("S"));
+        let first = server.assign(&StrVectorClock::new(), vec![]);
+        let second = server.assign(&first.context, vec![&first.version]);
+
+        assert_eq!(&Dot::new("S", 2), second.version.dot());
+        assert_eq!(vec![0], second.obsolete);
+        assert!(second.version.dominates(&first.version));
+    }
+
+    #[test]
+    fn test_blind_writes_become_siblings() {
+        let server = ServerDots::new(OwnedHost::new("S"));
+        let first = server.assign(&StrVectorClock::new(), vec![]);
+        let second = server.assign(&StrVectorClock::new(), vec![&first.version]);
+
+        assert_eq!(&Dot::new("S", 2), second.version.dot());
+        assert!(second.obsolete.is_empty());
+        assert!(!second.version.dominates(&first.version));
+        assert_eq!(vec![("S", 2)], second.context.clone().into_iter().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_blind_writes_both_survive() {
+        let server = ServerDots::new(OwnedHost::new("S"));
+        let mut stored = Vec::new();
+
+        for &value in &["a", "b"] {
+            let assigned = server.assign(&StrVectorClock::new(), stored.iter().map(|(v, _)| v));
+            assert!(assigned.obsolete.is_empty());
+            stored.push((assigned.version, value));
+        }
+
+        // A replica that only has the first write keeps both when syncing.
+        let synced = DottedVersionVector::sync(vec![stored[0].clone()], stored.clone());
+        assert_eq!(vec!["a", "b"], synced.into_iter().map(|(_, value)| value).collect::<Vec<_>>());
+    }
+}
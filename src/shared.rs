@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use clock::VectorClock;
+
+/// A vector clock that can be updated from many threads at once, e.g. by
+/// the workers of a thread pool handling a node's messages. Share it
+/// between threads with an `Arc`.
+///
+/// Each host's counter is an atomic, so incrementing a host that is
+/// already in the clock, and merging a clock whose hosts are all known,
+/// only take a shared lock and don't block each other. Adding new hosts
+/// and taking a `snapshot` take the lock exclusively, which makes
+/// snapshots consistent: they never observe half of a concurrent merge.
+#[derive(Debug)]
+pub struct SharedVectorClock<HostType: Hash + Eq> {
+    entries: RwLock<HashMap<HostType, AtomicU64>>,
+}
+
+impl<HostType: Clone + Hash + Eq> SharedVectorClock<HostType> {
+    pub fn new() -> SharedVectorClock<HostType> {
+        SharedVectorClock {
+            entries: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Increment the counter of `host`, returning its new value.
+    ///
+    /// Panics if the counter overflows, like `VectorClock::increment`.
+    pub fn increment(&self, host: HostType) -> u64 {
+        if let Some(n) = self.read().get(&host) {
+            return increment_counter(n);
+        }
+
+        increment_counter(self.write().entry(host).or_insert_with(|| AtomicU64::new(0)))
+    }
+
+    /// Raise every counter to at least the one in `other`.
+    pub fn merge(&self, other: &VectorClock<HostType>) {
+        {
+            let entries = self.read();
+
+            if other.iter().all(|(host, _)| entries.contains_key(host)) {
+                for (host, &n) in other.iter() {
+                    entries[host].fetch_max(n, Ordering::AcqRel);
+                }
+
+                return;
+            }
+        }
+
+        let mut entries = self.write();
+
+        for (host, &n) in other.iter() {
+            entries.entry(host.clone()).or_insert_with(|| AtomicU64::new(0)).fetch_max(n, Ordering::AcqRel);
+        }
+    }
+
+    pub fn get(&self, host: &HostType) -> u64 {
+        self.read().get(host).map_or(0, |n| n.load(Ordering::Acquire))
+    }
+
+    /// The clock as of a single point in time.
+    pub fn snapshot(&self) -> VectorClock<HostType> {
+        self.write().iter()
+            .map(|(host, n)| (host.clone(), n.load(Ordering::Acquire)))
+            .collect()
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, HashMap<HostType, AtomicU64>> {
+        self.entries.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, HashMap<HostType, AtomicU64>> {
+        self.entries.write().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<HostType: Clone + Hash + Eq> Default for SharedVectorClock<HostType> {
+    fn default() -> SharedVectorClock<HostType> {
+        SharedVectorClock::new()
+    }
+}
+
+impl<'a, HostType: Clone + Hash + Eq> From<&'a VectorClock<HostType>> for SharedVectorClock<HostType> {
+    fn from(clock: &'a VectorClock<HostType>) -> SharedVectorClock<HostType> {
+        SharedVectorClock {
+            entries: RwLock::new(clock.iter()
+                .map(|(host, &n)| (host.clone(), AtomicU64::new(n)))
+                .collect()),
+        }
+    }
+}
+
+fn increment_counter(n: &AtomicU64) -> u64 {
+    n.fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| n.checked_add(1))
+        .expect("vector clock counter overflow") + 1
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+    use std::thread;
+
+    use super::SharedVectorClock;
+    use clock::VectorClock;
+
+    #[test]
+    fn test_increment_and_merge() {
+        let shared = SharedVectorClock::new();
+
+        assert_eq!(1, shared.increment("A"));
+        assert_eq!(2, shared.increment("A"));
+
+        shared.merge(&VectorClock::new().incremented("A").incremented("B"));
+        shared.merge(&vec![("A", 5)].into_iter().collect());
+
+        assert_eq!(5, shared.get(&"A"));
+        assert_eq!(1, shared.get(&"B"));
+        assert_eq!(0, shared.get(&"C"));
+        assert_eq!(vec![("A", 5), ("B", 1)].into_iter().collect::<VectorClock<_>>(), shared.snapshot());
+    }
+
+    #[test]
+    fn test_concurrent_increments() {
+        let shared = Arc::new(SharedVectorClock::from(&VectorClock::new().incremented(0)));
+
+        let threads: Vec<_> = (0..4).map(|t| {
+            let shared = shared.clone();
+
+            thread::spawn(move || {
+                for _ in 0..1000 {
+                    shared.increment(0);
+                    shared.increment(t + 1);
+                }
+            })
+        }).collect();
+
+        for t in threads {
+            t.join().unwrap();
+        }
+
+        let snapshot = shared.snapshot();
+
+        assert_eq!(4001, snapshot.get(&0));
+        assert!((1..5).all(|t| snapshot.get(&t) == 1000));
+    }
+}
@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use clock::VectorClock;
+
+/// One node's part of a global snapshot.
+#[derive(Debug, PartialEq, Clone)]
+pub struct LocalSnapshot<HostType: Hash + Eq, State, Message> {
+    pub host: HostType,
+    pub state: State,
+    /// The node's clock at the moment its state was captured.
+    pub clock: VectorClock<HostType>,
+    /// Messages that were in flight on each incoming channel.
+    pub channels: HashMap<HostType, Vec<Message>>,
+}
+
+/// Runs the Chandy–Lamport snapshot algorithm on one node.
+///
+/// The node does no I/O: the methods return the peers a marker should be
+/// sent to, and the application passes incoming markers and messages in.
+/// Channels are assumed to be FIFO. State is captured through a closure
+/// the first time the node takes part in a snapshot.
+#[derive(Debug)]
+pub struct SnapshotNode<HostType: Hash + Eq, State, Message> {
+    host: HostType,
+    peers: Vec<HostType>,
+    snapshot: Option<u64>,
+    recorded: Option<LocalSnapshot<HostType, State, Message>>,
+    // Incoming channels still being recorded, i.e. without a marker yet.
+    open: Vec<HostType>,
+}
+
+impl<HostType, State, Message> SnapshotNode<HostType, State, Message>
+    where HostType: Clone + Hash + Eq, Message: Clone
+{
+    /// A node called `host`, connected in both directions to `peers`.
+    pub fn new(host: HostType, peers: Vec<HostType>) -> SnapshotNode<HostType, State, Message> {
+        SnapshotNode {
+            host,
+            peers,
+            snapshot: None,
+            recorded: None,
+            open: Vec::new(),
+        }
+    }
+
+    /// Start snapshot `id` from this node, returning the peers to send a
+    /// marker for it to.
+    pub fn start<F>(&mut self, id: u64, clock: &VectorClock<HostType>, capture: F) -> Vec<HostType>
+        where F: FnOnce() -> State
+    {
+        self.record(id, clock, capture, None)
+    }
+
+    /// Handle a marker for snapshot `id` received from `from`, returning
+    /// the peers to send markers to. Only the first marker of a snapshot
+    /// captures the local state.
+    pub fn on_marker<F>(&mut self, id: u64, from: &HostType, clock: &VectorClock<HostType>, capture: F) -> Vec<HostType>
+        where F: FnOnce() -> State
+    {
+        if self.snapshot == Some(id) {
+            self.open.retain(|p| p != from);
+            return Vec::new();
+        }
+
+        self.record(id, clock, capture, Some(from))
+    }
+
+    /// Report an application message received from `from`. It is recorded
+    /// as in flight if its channel is still open in the current snapshot.
+    pub fn on_message(&mut self, from: &HostType, message: &Message) {
+        if !self.open.contains(from) {
+            return;
+        }
+
+        if let Some(ref mut recorded) = self.recorded {
+            recorded.channels.entry(from.clone()).or_default().push(message.clone());
+        }
+    }
+
+    /// Whether markers have arrived on every incoming channel.
+    pub fn is_complete(&self) -> bool {
+        self.recorded.is_some() && self.open.is_empty()
+    }
+
+    /// Take this node's part of the snapshot once it is complete.
+    pub fn take_snapshot(&mut self) -> Option<LocalSnapshot<HostType, State, Message>> {
+        if !self.is_complete() {
+            return None;
+        }
+
+        self.recorded.take()
+    }
+
+    fn record<F>(&mut self, id: u64, clock: &VectorClock<HostType>, capture: F, from: Option<&HostType>) -> Vec<HostType>
+        where F: FnOnce() -> State
+    {
+        self.snapshot = Some(id);
+        self.open = self.peers.iter().filter(|&p| Some(p) != from).cloned().collect();
+        self.recorded = Some(LocalSnapshot {
+            host: self.host.clone(),
+            state: capture(),
+            clock: clock.clone(),
+            channels: HashMap::new(),
+        });
+
+        self.peers.clone()
+    }
+}
+
+/// Assembles the local snapshots of all nodes into a global one.
+#[derive(Debug, Clone)]
+pub struct GlobalSnapshot<HostType: Hash + Eq, State, Message> {
+    nodes: HashMap<HostType, LocalSnapshot<HostType, State, Message>>,
+}
+
+impl<HostType: Clone + Hash + Eq, State, Message> GlobalSnapshot<HostType, State, Message> {
+    pub fn new() -> GlobalSnapshot<HostType, State, Message> {
+        GlobalSnapshot {
+            nodes: HashMap::new(),
+        }
+    }
+
+    pub fn add(&mut self, snapshot: LocalSnapshot<HostType, State, Message>) {
+        self.nodes.insert(snapshot.host.clone(), snapshot);
+    }
+
+    /// Whether a local snapshot has been added for every host in `hosts`.
+    pub fn covers(&self, hosts: &[HostType]) -> bool {
+        hosts.iter().all(|h| self.nodes.contains_key(h))
+    }
+
+    pub fn get(&self, host: &HostType) -> Option<&LocalSnapshot<HostType, State, Message>> {
+        self.nodes.get(host)
+    }
+
+    /// Whether the captured clocks form a consistent cut: no node has seen
+    /// more of another node's events than that node had made when it was
+    /// captured.
+    pub fn is_consistent(&self) -> bool {
+        self.nodes.values().all(|a| {
+            self.nodes.values().all(|b| a.clock.get(&b.host) <= b.clock.get(&b.host))
+        })
+    }
+
+    /// The merge of all captured clocks.
+    pub fn cut(&self) -> VectorClock<HostType> {
+        self.nodes.values().fold(VectorClock::new(), |cut, s| cut.merge_with(&s.clock))
+    }
+}
+
+impl<HostType: Clone + Hash + Eq, State, Message> Default for GlobalSnapshot<HostType, State, Message> {
+    fn default() -> GlobalSnapshot<HostType, State, Message> {
+        GlobalSnapshot::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{SnapshotNode, GlobalSnapshot};
+    use clock::VectorClock;
+
+    #[test]
+    fn test_two_node_snapshot() {
+        let mut a = SnapshotNode::<_, u64, &str>::new("A", vec!["B"]);
+        let mut b = SnapshotNode::<_, u64, &str>::new("B", vec!["A"]);
+
+        let a_clock = VectorClock::new().incremented("A");
+        let b_clock = VectorClock::new().incremented("B");
+
+        assert_eq!(vec!["B"], a.start(1, &a_clock, || 10));
+
+        // A message from B sent before B saw the marker is in flight.
+        a.on_message(&"B", &"hello");
+        assert!(!a.is_complete());
+
+        assert_eq!(vec!["A"], b.on_marker(1, &"A", &b_clock, || 20));
+        assert!(b.is_complete());
+
+        assert!(a.on_marker(1, &"B", &a_clock, || unreachable!()).is_empty());
+        a.on_message(&"B", &"after");
+
+        let mut global = GlobalSnapshot::new();
+        global.add(a.take_snapshot().unwrap());
+        global.add(b.take_snapshot().unwrap());
+
+        assert!(global.covers(&["A", "B"]));
+        assert!(global.is_consistent());
+        assert_eq!(vec!["hello"], global.get(&"A").unwrap().channels[&"B"]);
+        assert!(global.get(&"B").unwrap().channels.is_empty());
+        assert_eq!(a_clock.merge_with(&b_clock), global.cut());
+    }
+
+    #[test]
+    fn test_inconsistent_cut() {
+        let a = VectorClock::new().incremented("A");
+        let b = a.incremented("A").incremented("B");
+
+        let mut x = SnapshotNode::<_, (), ()>::new("A", vec![]);
+        let mut y = SnapshotNode::<_, (), ()>::new("B", vec![]);
+        x.start(1, &a, || ());
+        y.start(1, &b, || ());
+
+        let mut global = GlobalSnapshot::new();
+        global.add(x.take_snapshot().unwrap());
+        global.add(y.take_snapshot().unwrap());
+
+        assert!(!global.is_consistent());
+    }
+}
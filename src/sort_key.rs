@@ -0,0 +1,146 @@
+use std::fmt::Display;
+use std::hash::Hash;
+use std::str::FromStr;
+
+use clock::VectorClock;
+use error::{Error, Result};
+
+const END: u8 = 0x00;
+const ENTRY: u8 = 0x01;
+
+impl<HostType: Clone + Hash + Eq + Display> VectorClock<HostType> {
+    /// Encode the clock as bytes suitable for use in range-store keys.
+    ///
+    /// Entries are written in the byte order of the hosts' `Display` form,
+    /// each as `0x01`, the host bytes with `0x00` escaped as `0x00 0xFF`,
+    /// the terminator `0x00 0x01`, and the counter as eight big-endian
+    /// bytes. The clock ends with a single `0x00`, and zero counters are
+    /// left out so equal clocks always encode the same.
+    ///
+    /// Comparing two encodings byte by byte compares the clocks entry by
+    /// entry, host first and counter second, with a clock that runs out of
+    /// entries sorting first. The encoding is self-delimiting, so it can be
+    /// placed between other key components. This order is deterministic
+    /// but is not the causal order.
+    pub fn to_sort_key(&self) -> Vec<u8> {
+        let mut entries: Vec<(String, u64)> = self.iter()
+            .filter(|&(_, &n)| n > 0)
+            .map(|(host, &n)| (host.to_string(), n))
+            .collect();
+        entries.sort();
+
+        let mut out = Vec::new();
+
+        for (host, n) in entries {
+            out.push(ENTRY);
+
+            for &b in host.as_bytes() {
+                out.push(b);
+                if b == 0x00 {
+                    out.push(0xFF);
+                }
+            }
+
+            out.extend_from_slice(&[0x00, 0x01]);
+            out.extend_from_slice(&n.to_be_bytes());
+        }
+
+        out.push(END);
+        out
+    }
+}
+
+impl<HostType: Clone + Hash + Eq + FromStr> VectorClock<HostType> {
+    /// Decode a clock written by `to_sort_key` from the start of `bytes`,
+    /// returning it along with the bytes that follow it.
+    pub fn from_sort_key(bytes: &[u8]) -> Result<(VectorClock<HostType>, &[u8])> {
+        let truncated = || Error::Decode("truncated sort key".to_owned());
+
+        let mut entries = Vec::new();
+        let mut pos = 0;
+
+        loop {
+            match bytes.get(pos) {
+                Some(&END) => break,
+                Some(&ENTRY) => pos += 1,
+                Some(&b) => return Err(Error::Decode(format!("unexpected sort key byte {:#04x}", b))),
+                None => return Err(truncated()),
+            }
+
+            let mut host = Vec::new();
+
+            loop {
+                match (bytes.get(pos), bytes.get(pos + 1)) {
+                    (Some(0x00), Some(0xFF)) => host.push(0x00),
+                    (Some(0x00), Some(0x01)) => break,
+                    (Some(0x00), _) => return Err(Error::Decode("malformed sort key host".to_owned())),
+                    (Some(&b), _) => { host.push(b); pos += 1; continue; },
+                    (None, _) => return Err(truncated()),
+                }
+                pos += 2;
+            }
+            pos += 2;
+
+            let counter = bytes.get(pos..pos + 8).ok_or_else(truncated)?;
+            let mut be = [0; 8];
+            be.copy_from_slice(counter);
+            pos += 8;
+
+            let host = String::from_utf8(host).ok()
+                .and_then(|h| h.parse().ok())
+                .ok_or_else(|| Error::Decode("invalid sort key host".to_owned()))?;
+
+            entries.push((host, u64::from_be_bytes(be)));
+        }
+
+        Ok((entries.into_iter().collect::<VectorClock<_>>(), &bytes[pos + 1..]))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use clock::VectorClock;
+
+    fn clock(entries: &[(&str, u64)]) -> VectorClock<String> {
+        entries.iter().map(|&(h, n)| (h.to_owned(), n)).collect::<VectorClock<_>>()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let c = clock(&[("b", 2), ("a\u{0}x", 300), ("c", 0)]);
+
+        let mut key = c.to_sort_key();
+        key.extend_from_slice(b"rest");
+
+        let (decoded, rest) = VectorClock::<String>::from_sort_key(&key).unwrap();
+
+        assert_eq!(clock(&[("b", 2), ("a\u{0}x", 300)]), decoded);
+        assert_eq!(b"rest", rest);
+    }
+
+    #[test]
+    fn test_byte_order() {
+        let ordered = [
+            clock(&[]),
+            clock(&[("a", 1)]),
+            clock(&[("a", 1), ("b", 1)]),
+            clock(&[("a", 2)]),
+            clock(&[("a\u{0}", 1)]),
+            clock(&[("ab", 1)]),
+            clock(&[("b", 1)]),
+        ];
+
+        let keys: Vec<_> = ordered.iter().map(|c| c.to_sort_key()).collect();
+        let mut sorted = keys.clone();
+        sorted.sort();
+
+        assert_eq!(keys, sorted);
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        assert!(VectorClock::<String>::from_sort_key(&[]).is_err());
+        assert!(VectorClock::<String>::from_sort_key(&[0x01, b'a', 0x00, 0x01, 0x00]).is_err());
+        assert!(VectorClock::<String>::from_sort_key(&[0x02]).is_err());
+    }
+}
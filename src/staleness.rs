@@ -0,0 +1,116 @@
+use std::hash::Hash;
+use std::time::{Duration, SystemTime};
+
+use clock::VectorClock;
+
+/// How far a replica's clock may lag behind an authoritative clock.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum StalenessBound {
+    /// At most this many events in total may be missing.
+    Events(u64),
+    /// No single host may be more than this many events behind.
+    HostLag(u64),
+    /// At most this many hosts may be behind at all.
+    LaggingHosts(usize),
+    /// No missing event may be older than this. Needs the times at which
+    /// the authoritative entries were last updated, see
+    /// `is_within_staleness_at`; without them any lag counts as stale.
+    Age(Duration),
+}
+
+impl<HostType: Clone + Hash + Eq> VectorClock<HostType> {
+    /// Events that `authoritative` has seen but this clock has not.
+    ///
+    /// Unlike `distance`, this ignores events that only this clock has seen.
+    pub fn missing_events(&self, authoritative: &Self) -> u64 {
+        authoritative.iter()
+            .map(|(host, &n)| n.saturating_sub(self.get(host)))
+            .sum()
+    }
+
+    /// Whether this clock is fresh enough compared to `authoritative`.
+    pub fn is_within_staleness(&self, authoritative: &Self, bound: StalenessBound) -> bool {
+        self.is_within_staleness_at(authoritative, bound, |_| None, SystemTime::now())
+    }
+
+    /// Like `is_within_staleness`, with `updated_at` giving the time each
+    /// host's authoritative entry was last updated, like
+    /// `BoundedVectorClock::updated_at`, for `StalenessBound::Age`.
+    ///
+    /// The age of a lagging host is measured from its latest update, so it
+    /// is a lower bound: earlier missing events may be older still. Hosts
+    /// without a time are treated as too old.
+    pub fn is_within_staleness_at<F>(&self, authoritative: &Self, bound: StalenessBound, updated_at: F, now: SystemTime) -> bool
+        where F: Fn(&HostType) -> Option<SystemTime>
+    {
+        let lags = authoritative.iter().map(|(host, &n)| (host, n.saturating_sub(self.get(host))));
+
+        match bound {
+            StalenessBound::Events(max) => lags.map(|(_, lag)| lag).sum::<u64>() <= max,
+            StalenessBound::HostLag(max) => lags.map(|(_, lag)| lag).max().unwrap_or(0) <= max,
+            StalenessBound::LaggingHosts(max) => lags.filter(|&(_, lag)| lag > 0).count() <= max,
+            StalenessBound::Age(max) => lags.filter(|&(_, lag)| lag > 0).all(|(host, _)| {
+                updated_at(host).is_some_and(|at| now.duration_since(at).unwrap_or_default() <= max)
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::StalenessBound;
+    use clock::VectorClock;
+
+    type StrVectorClock = VectorClock<&'static str>;
+
+    #[test]
+    fn test_missing_events() {
+        let replica = StrVectorClock::new().incremented("A").incremented("C");
+        let primary = StrVectorClock::new().incremented("A").incremented("B").incremented("B");
+
+        assert_eq!(2, replica.missing_events(&primary));
+        assert_eq!(1, primary.missing_events(&replica));
+    }
+
+    #[test]
+    fn test_bounds() {
+        let replica = StrVectorClock::new().incremented("A");
+        let primary = replica.incremented("A").incremented("A").incremented("B");
+
+        assert!(replica.is_within_staleness(&primary, StalenessBound::Events(3)));
+        assert!(!replica.is_within_staleness(&primary, StalenessBound::Events(2)));
+
+        assert!(replica.is_within_staleness(&primary, StalenessBound::HostLag(2)));
+        assert!(!replica.is_within_staleness(&primary, StalenessBound::HostLag(1)));
+
+        assert!(replica.is_within_staleness(&primary, StalenessBound::LaggingHosts(2)));
+        assert!(!replica.is_within_staleness(&primary, StalenessBound::LaggingHosts(1)));
+
+        assert!(primary.is_within_staleness(&replica, StalenessBound::Events(0)));
+    }
+
+    #[test]
+    fn test_age_bound() {
+        use std::time::{Duration, UNIX_EPOCH};
+
+        let at = |secs| UNIX_EPOCH + Duration::from_secs(secs);
+        let replica = StrVectorClock::new().incremented("A");
+        let primary = replica.incremented("B").incremented("C");
+
+        let updated_at = |host: &&str| match *host {
+            "A" => Some(at(10)),
+            "B" => Some(at(90)),
+            "C" => Some(at(95)),
+            _ => None,
+        };
+
+        let bound = StalenessBound::Age(Duration::from_secs(10));
+
+        assert!(replica.is_within_staleness_at(&primary, bound, updated_at, at(100)));
+        assert!(!replica.is_within_staleness_at(&primary, bound, updated_at, at(101)));
+        assert!(!replica.is_within_staleness_at(&primary.incremented("D"), bound, updated_at, at(100)));
+
+        assert!(primary.is_within_staleness(&replica, bound));
+        assert!(!replica.is_within_staleness(&primary, bound));
+    }
+}
@@ -0,0 +1,45 @@
+use std::hash::Hash;
+
+use clock::{VectorClock, TemporalRelation};
+
+/// A value together with the clock of the event that produced it.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Stamped<T, HostType: Hash + Eq> {
+    pub value: T,
+    pub clock: VectorClock<HostType>,
+}
+
+impl<T, HostType: Clone + Hash + Eq> Stamped<T, HostType> {
+    pub fn new(value: T, clock: VectorClock<HostType>) -> Stamped<T, HostType> {
+        Stamped {
+            value,
+            clock,
+        }
+    }
+
+    pub fn relation_to<U>(&self, other: &Stamped<U, HostType>) -> TemporalRelation {
+        self.clock.temporal_relation(&other.clock)
+    }
+
+    pub fn map<U, F: FnOnce(T) -> U>(self, f: F) -> Stamped<U, HostType> {
+        Stamped {
+            value: f(self.value),
+            clock: self.clock,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Stamped;
+    use clock::{VectorClock, TemporalRelation};
+
+    #[test]
+    fn test_relation_and_map() {
+        let a = Stamped::new(1, VectorClock::new().incremented("A"));
+        let b = Stamped::new("x", a.clock.incremented("B"));
+
+        assert_eq!(TemporalRelation::Caused, a.relation_to(&b));
+        assert_eq!(Stamped::new(2, a.clock.clone()), a.map(|n| n + 1));
+    }
+}
@@ -0,0 +1,161 @@
+use std::hash::Hash;
+use std::time::Instant;
+
+use clock::{VectorClock, TemporalRelation};
+
+/// Activity counters collected by a `TrackedClock`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ClockStats {
+    pub since: Instant,
+    pub increments: u64,
+    pub merges: u64,
+    /// Merges with a clock that was concurrent with the local one.
+    pub concurrent_merges: u64,
+    /// Hosts added to the clock, by either increments or merges.
+    pub entries_added: u64,
+    pub entries: usize,
+    pub peak_entries: usize,
+}
+
+impl ClockStats {
+    fn new(entries: usize) -> ClockStats {
+        ClockStats {
+            since: Instant::now(),
+            increments: 0,
+            merges: 0,
+            concurrent_merges: 0,
+            entries_added: 0,
+            entries,
+            peak_entries: entries,
+        }
+    }
+
+    /// Hosts added per second since tracking started.
+    pub fn growth_rate(&self, now: Instant) -> f64 {
+        let secs = now.saturating_duration_since(self.since).as_secs_f64();
+
+        if secs > 0.0 { self.entries_added as f64 / secs } else { 0.0 }
+    }
+
+    /// The statistics as flat `(name, value)` pairs, for metrics exporters.
+    pub fn to_metrics(&self, now: Instant) -> Vec<(&'static str, f64)> {
+        vec![
+            ("increments", self.increments as f64),
+            ("merges", self.merges as f64),
+            ("concurrent_merges", self.concurrent_merges as f64),
+            ("entries_added", self.entries_added as f64),
+            ("entries", self.entries as f64),
+            ("peak_entries", self.peak_entries as f64),
+            ("growth_rate", self.growth_rate(now)),
+        ]
+    }
+
+    fn resized(&mut self, before: usize, after: usize) {
+        self.entries_added += after.saturating_sub(before) as u64;
+        self.entries = after;
+        self.peak_entries = ::std::cmp::max(self.peak_entries, after);
+    }
+}
+
+/// A `VectorClock` that keeps `ClockStats` about how it is used.
+#[derive(Debug, Clone)]
+pub struct TrackedClock<HostType: Hash + Eq> {
+    clock: VectorClock<HostType>,
+    stats: ClockStats,
+}
+
+impl<HostType: Clone + Hash + Eq> TrackedClock<HostType> {
+    pub fn new() -> TrackedClock<HostType> {
+        TrackedClock::from_clock(VectorClock::new())
+    }
+
+    pub fn from_clock(clock: VectorClock<HostType>) -> TrackedClock<HostType> {
+        let stats = ClockStats::new(clock.len());
+
+        TrackedClock {
+            clock,
+            stats,
+        }
+    }
+
+    pub fn increment(&mut self, host: HostType) {
+        let before = self.clock.len();
+
+        self.clock.increment(host);
+        self.stats.increments += 1;
+        self.stats.resized(before, self.clock.len());
+    }
+
+    pub fn merge(&mut self, other: &VectorClock<HostType>) {
+        let before = self.clock.len();
+
+        if self.clock.temporal_relation(other) == TemporalRelation::Concurrent {
+            self.stats.concurrent_merges += 1;
+        }
+
+        self.clock.merge(other);
+        self.stats.merges += 1;
+        self.stats.resized(before, self.clock.len());
+    }
+
+    pub fn clock(&self) -> &VectorClock<HostType> {
+        &self.clock
+    }
+
+    pub fn stats(&self) -> &ClockStats {
+        &self.stats
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.stats = ClockStats::new(self.clock.len());
+    }
+
+    pub fn into_inner(self) -> VectorClock<HostType> {
+        self.clock
+    }
+}
+
+impl<HostType: Clone + Hash + Eq> Default for TrackedClock<HostType> {
+    fn default() -> TrackedClock<HostType> {
+        TrackedClock::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::TrackedClock;
+    use clock::VectorClock;
+
+    #[test]
+    fn test_counts_operations() {
+        let mut c = TrackedClock::new();
+
+        c.increment("A");
+        c.increment("A");
+        c.merge(&VectorClock::new().incremented("B"));
+        c.merge(&VectorClock::new().incremented("A"));
+
+        let stats = c.stats();
+
+        assert_eq!(2, stats.increments);
+        assert_eq!(2, stats.merges);
+        assert_eq!(1, stats.concurrent_merges);
+        assert_eq!(2, stats.entries_added);
+        assert_eq!(2, stats.entries);
+    }
+
+    #[test]
+    fn test_growth_rate() {
+        let mut c = TrackedClock::new();
+
+        c.increment("A");
+        c.increment("B");
+
+        let later = c.stats().since + Duration::from_secs(4);
+
+        assert_eq!(0.5, c.stats().growth_rate(later));
+        assert!(c.stats().to_metrics(later).contains(&("growth_rate", 0.5)));
+    }
+}
@@ -0,0 +1,157 @@
+use std::collections::VecDeque;
+use std::hash::Hash;
+
+use clock::{VectorClock, TemporalRelation};
+
+/// Counters kept by a `StreamAnalyzer`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct StreamStats {
+    pub events: u64,
+    /// Events already covered by earlier ones, e.g. redeliveries or events
+    /// that arrived after a later event from the same host.
+    pub out_of_order: u64,
+    /// Events that skipped over counters of their own host not seen yet.
+    pub gaps: u64,
+    /// Events that arrived before some event they depend on from another
+    /// host.
+    pub premature: u64,
+    /// Frontier clocks dropped to stay within the frontier limit.
+    pub evicted: u64,
+}
+
+/// Maintains the causal frontier, per-host high-water marks and ordering
+/// statistics over an unbounded stream of events, in bounded memory.
+///
+/// Memory is proportional to the number of hosts plus the frontier limit.
+/// When more concurrent events than the limit are live at once, the
+/// oldest frontier clock is dropped and counted in `StreamStats::evicted`.
+#[derive(Debug, Clone)]
+pub struct StreamAnalyzer<HostType: Hash + Eq> {
+    max_frontier: usize,
+    frontier: VecDeque<VectorClock<HostType>>,
+    high_water: VectorClock<HostType>,
+    stats: StreamStats,
+}
+
+impl<HostType: Clone + Hash + Eq> StreamAnalyzer<HostType> {
+    pub fn new(max_frontier: usize) -> StreamAnalyzer<HostType> {
+        StreamAnalyzer {
+            max_frontier,
+            frontier: VecDeque::new(),
+            high_water: VectorClock::new(),
+            stats: StreamStats::default(),
+        }
+    }
+
+    /// Ingest an event made by `host` and stamped with `clock`.
+    pub fn ingest(&mut self, host: &HostType, clock: VectorClock<HostType>) {
+        self.stats.events += 1;
+
+        let own = clock.get(host);
+        let seen = self.high_water.get(host);
+
+        if own <= seen {
+            self.stats.out_of_order += 1;
+        }
+        else if own > seen + 1 {
+            self.stats.gaps += 1;
+        }
+
+        if clock.iter().any(|(h, &n)| h != host && n > self.high_water.get(h)) {
+            self.stats.premature += 1;
+        }
+
+        self.high_water.merge(&clock);
+
+        let mut dominated = false;
+        self.frontier.retain(|f| match clock.temporal_relation(f) {
+            TemporalRelation::EffectOf => false,
+            TemporalRelation::Caused | TemporalRelation::Equal => { dominated = true; true },
+            TemporalRelation::Concurrent => true,
+        });
+
+        if !dominated {
+            self.frontier.push_back(clock);
+
+            if self.frontier.len() > self.max_frontier {
+                self.frontier.pop_front();
+                self.stats.evicted += 1;
+            }
+        }
+    }
+
+    /// The clocks of the latest concurrent events, oldest first.
+    pub fn frontier(&self) -> impl Iterator<Item=&VectorClock<HostType>> {
+        self.frontier.iter()
+    }
+
+    /// The highest counter seen for every host, i.e. the merge of all
+    /// ingested clocks.
+    pub fn high_water(&self) -> &VectorClock<HostType> {
+        &self.high_water
+    }
+
+    pub fn stats(&self) -> StreamStats {
+        self.stats
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::StreamAnalyzer;
+    use clock::VectorClock;
+
+    #[test]
+    fn test_frontier_and_high_water() {
+        let mut s = StreamAnalyzer::new(8);
+
+        let a1 = VectorClock::new().incremented("A");
+        let b1 = VectorClock::new().incremented("B");
+        let a2 = a1.merge_with(&b1).incremented("A");
+
+        s.ingest(&"A", a1.clone());
+        s.ingest(&"B", b1.clone());
+
+        assert_eq!(vec![&a1, &b1], s.frontier().collect::<Vec<_>>());
+
+        s.ingest(&"A", a2.clone());
+
+        assert_eq!(vec![&a2], s.frontier().collect::<Vec<_>>());
+        assert_eq!(&a2, s.high_water());
+        assert_eq!(0, s.stats().out_of_order + s.stats().premature + s.stats().gaps);
+    }
+
+    #[test]
+    fn test_ordering_stats() {
+        let mut s = StreamAnalyzer::new(8);
+
+        let a1 = VectorClock::new().incremented("A");
+        let a2 = a1.incremented("A");
+        let a3 = a2.incremented("A");
+        let b1 = a3.incremented("B");
+
+        s.ingest(&"B", b1);
+        s.ingest(&"A", a3);
+        s.ingest(&"A", a1);
+
+        let stats = s.stats();
+
+        assert_eq!(3, stats.events);
+        assert_eq!(1, stats.premature);
+        assert_eq!(2, stats.out_of_order);
+        assert_eq!(0, stats.gaps);
+        assert_eq!(1, s.frontier().count());
+    }
+
+    #[test]
+    fn test_frontier_limit() {
+        let mut s = StreamAnalyzer::new(2);
+
+        for host in &["A", "B", "C"] {
+            s.ingest(host, VectorClock::new().incremented(*host));
+        }
+
+        assert_eq!(2, s.frontier().count());
+        assert_eq!(1, s.stats().evicted);
+    }
+}
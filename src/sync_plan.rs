@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::hash::Hash;
+
+use clock::VectorClock;
+
+/// What to send a peer to bring it up to date with the local clock.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum SyncMessage<HostType: Hash + Eq> {
+    /// The whole clock, for peers without an acknowledged baseline or when
+    /// a delta would not be smaller.
+    Full(VectorClock<HostType>),
+    /// Only the entries that advanced since the peer's acknowledged
+    /// baseline. Merging it into any clock that covers the baseline
+    /// yields the local clock.
+    Delta(VectorClock<HostType>),
+    /// Nothing changed since the baseline: just its `clock_digest`, which
+    /// the peer can check against its own clock.
+    Digest(u64),
+}
+
+impl<HostType: Clone + Hash + Eq + Display> SyncMessage<HostType> {
+    /// The size of the message's payload in the packed encoding.
+    pub fn encoded_len(&self) -> usize {
+        match *self {
+            SyncMessage::Full(ref clock) | SyncMessage::Delta(ref clock) => clock.to_packed().len(),
+            SyncMessage::Digest(_) => 8,
+        }
+    }
+}
+
+/// A stable 64-bit FNV-1a digest of a clock's sort key, identical across
+/// processes and platforms.
+pub fn clock_digest<HostType>(clock: &VectorClock<HostType>) -> u64
+    where HostType: Clone + Hash + Eq + Display
+{
+    clock.to_sort_key().iter().fold(0xcbf2_9ce4_8422_2325, |hash, &b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Chooses between sending the full clock, a delta or a digest to each
+/// peer, based on the clock each peer last acknowledged and the encoded
+/// size of the alternatives.
+#[derive(Debug, Clone)]
+pub struct SyncPlanner<Peer: Hash + Eq, HostType: Hash + Eq> {
+    baselines: HashMap<Peer, VectorClock<HostType>>,
+}
+
+impl<Peer, HostType> SyncPlanner<Peer, HostType>
+    where Peer: Hash + Eq, HostType: Clone + Hash + Eq + Display
+{
+    pub fn new() -> SyncPlanner<Peer, HostType> {
+        SyncPlanner {
+            baselines: HashMap::new(),
+        }
+    }
+
+    pub fn plan(&self, peer: &Peer, local: &VectorClock<HostType>) -> SyncMessage<HostType> {
+        let baseline = match self.baselines.get(peer) {
+            Some(baseline) => baseline,
+            None => return SyncMessage::Full(local.clone()),
+        };
+
+        let delta = local.diff(baseline);
+
+        if delta.is_empty() {
+            return SyncMessage::Digest(clock_digest(local));
+        }
+
+        let delta = SyncMessage::Delta(delta.into_clock());
+        let full = SyncMessage::Full(local.clone());
+
+        if delta.encoded_len() < full.encoded_len() { delta } else { full }
+    }
+
+    /// Record that `peer` confirmed holding at least `clock`.
+    pub fn acknowledged(&mut self, peer: Peer, clock: &VectorClock<HostType>) {
+        let baseline = self.baselines.entry(peer).or_default();
+        baseline.merge(clock);
+    }
+
+    /// Drop the baseline of `peer`, e.g. after it restarted and may have
+    /// lost state, so the next plan sends the full clock.
+    pub fn forget(&mut self, peer: &Peer) {
+        self.baselines.remove(peer);
+    }
+
+    pub fn baseline(&self, peer: &Peer) -> Option<&VectorClock<HostType>> {
+        self.baselines.get(peer)
+    }
+}
+
+impl<Peer, HostType> Default for SyncPlanner<Peer, HostType>
+    where Peer: Hash + Eq, HostType: Clone + Hash + Eq + Display
+{
+    fn default() -> SyncPlanner<Peer, HostType> {
+        SyncPlanner::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{SyncPlanner, SyncMessage, clock_digest};
+    use clock::VectorClock;
+
+    fn clock(entries: &[(&str, u64)]) -> VectorClock<String> {
+        entries.iter().map(|&(h, n)| (h.to_owned(), n)).collect::<VectorClock<_>>()
+    }
+
+    #[test]
+    fn test_plan() {
+        let mut planner = SyncPlanner::new();
+        let local = clock(&[("a", 10), ("b", 20), ("c", 30)]);
+
+        assert_eq!(SyncMessage::Full(local.clone()), planner.plan(&1, &local));
+
+        planner.acknowledged(1, &local);
+        assert_eq!(SyncMessage::Digest(clock_digest(&local)), planner.plan(&1, &local));
+
+        let local = local.incremented("b".to_owned());
+        assert_eq!(SyncMessage::Delta(clock(&[("b", 21)])), planner.plan(&1, &local));
+
+        planner.forget(&1);
+        assert_eq!(SyncMessage::Full(local.clone()), planner.plan(&1, &local));
+    }
+
+    #[test]
+    fn test_full_when_delta_is_not_smaller() {
+        let mut planner = SyncPlanner::new();
+        planner.acknowledged("p", &clock(&[("a", 1)]));
+
+        let local = clock(&[("a", 2)]);
+
+        assert_eq!(SyncMessage::Full(local.clone()), planner.plan(&"p", &local));
+    }
+
+    #[test]
+    fn test_digest_is_stable() {
+        let a = clock(&[("a", 1), ("b", 2)]);
+        let b = clock(&[("b", 2), ("a", 1), ("c", 0)]);
+
+        assert_eq!(clock_digest(&a), clock_digest(&b));
+        assert!(clock_digest(&a) != clock_digest(&clock(&[("a", 1)])));
+    }
+}
@@ -0,0 +1,168 @@
+use std::prelude::v1::*;
+use std::fmt::{self, Display, Write};
+use std::hash::Hash;
+use std::str::FromStr;
+
+use clock::VectorClock;
+use counter::Counter;
+use error::{Error, Result};
+
+/// Writes the clock as `host:counter` entries separated by commas, e.g.
+/// `A:3,B:1`, sorted by the hosts' `Display` text so that equal clocks
+/// always print the same. The empty clock is the empty string.
+///
+/// `%`, `,`, `:` and every byte outside printable ASCII in host names are
+/// percent-escaped, so the output always parses back with `FromStr` and
+/// can be used as a header value or a whitespace-separated log token.
+/// `VectorClock::format` offers more control over the layout.
+impl<HostType, CounterType> Display for VectorClock<HostType, CounterType>
+    where HostType: Clone + Hash + Eq + Display,
+          CounterType: Counter + Display
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut entries: Vec<(String, CounterType)> = self.iter()
+            .map(|(host, &n)| (host.to_string(), n))
+            .collect();
+        entries.sort();
+
+        let mut out = String::new();
+
+        for (i, (host, n)) in entries.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+
+            escape_host(host, &[], &mut out);
+            let _ = write!(out, ":{}", n);
+        }
+
+        f.write_str(&out)
+    }
+}
+
+/// Append `host` to `out`, percent-escaping `%`, `,`, `:`, the bytes in
+/// `extra` and every byte outside printable ASCII.
+pub(crate) fn escape_host(host: &str, extra: &[u8], out: &mut String) {
+    for &b in host.as_bytes() {
+        match b {
+            b'%' | b',' | b':' => { let _ = write!(out, "%{:02X}", b); },
+            _ if extra.contains(&b) => { let _ = write!(out, "%{:02X}", b); },
+            0x21..=0x7E => out.push(b as char),
+            _ => { let _ = write!(out, "%{:02X}", b); },
+        }
+    }
+}
+
+/// Undo `escape_host`, or any other percent-escaping. Each `%` must be
+/// followed by exactly two hex digits.
+pub(crate) fn unescape_host(s: &str) -> Option<String> {
+    let mut out = Vec::with_capacity(s.len());
+    let mut bytes = s.bytes();
+
+    while let Some(b) = bytes.next() {
+        if b == b'%' {
+            let high = (bytes.next()? as char).to_digit(16)?;
+            let low = (bytes.next()? as char).to_digit(16)?;
+            out.push((high << 4 | low) as u8);
+        }
+        else {
+            out.push(b);
+        }
+    }
+
+    String::from_utf8(out).ok()
+}
+
+impl<HostType, CounterType> VectorClock<HostType, CounterType>
+    where HostType: Clone + Hash + Eq + FromStr,
+          CounterType: Counter + FromStr
+{
+    /// Parse a clock in the text form written by `Display`.
+    ///
+    /// Whitespace around entries is ignored, and the counter is taken to
+    /// start after the last colon of an entry, so unescaped colons in hosts
+    /// are accepted too. Hosts listed more than once are rejected.
+    pub fn try_parse(s: &str) -> Result<VectorClock<HostType, CounterType>> {
+        let mut clock = VectorClock::default();
+
+        if s.trim().is_empty() {
+            return Ok(clock);
+        }
+
+        for entry in s.split(',').map(str::trim) {
+            let malformed = || Error::Decode(format!("malformed clock entry {:?}", entry));
+
+            let sep = entry.rfind(':').ok_or_else(malformed)?;
+            let host: HostType = unescape_host(entry[..sep].trim()).ok_or_else(malformed)?
+                .parse().map_err(|_| malformed())?;
+            let n = entry[sep + 1..].trim().parse().map_err(|_| malformed())?;
+
+            if clock.contains_host(&host) {
+                return Err(Error::InvalidEntry(format!("host {:?} listed twice", entry[..sep].trim())));
+            }
+
+            clock.extend(Some((host, n)));
+        }
+
+        Ok(clock)
+    }
+}
+
+impl<HostType, CounterType> FromStr for VectorClock<HostType, CounterType>
+    where HostType: Clone + Hash + Eq + FromStr,
+          CounterType: Counter + FromStr
+{
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<VectorClock<HostType, CounterType>> {
+        VectorClock::try_parse(s)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::prelude::v1::*;
+    use clock::VectorClock;
+    use error::Error;
+
+    #[test]
+    fn test_display() {
+        let c = vec![("B", 1), ("A", 3)].into_iter().collect::<VectorClock<_>>();
+
+        assert_eq!("A:3,B:1", c.to_string());
+        assert_eq!("", VectorClock::<&str>::new().to_string());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let c = vec![("db:1".to_owned(), 7), ("web".to_owned(), 2)].into_iter().collect::<VectorClock<_>>();
+
+        assert_eq!(c, c.to_string().parse().unwrap());
+        assert_eq!(VectorClock::<String>::new(), "".parse().unwrap());
+
+        let c = vec![("a,b".to_owned(), 1), ("zürich 1".to_owned(), 2), ("100%".to_owned(), 3)]
+            .into_iter().collect::<VectorClock<_>>();
+
+        assert_eq!("100%25:3,a%2Cb:1,z%C3%BCrich%201:2", c.to_string());
+        assert_eq!(c, c.to_string().parse().unwrap());
+        assert_eq!(Ok(VectorClock::new().incremented("db:1".to_owned())), VectorClock::try_parse("db:1:1"));
+
+        let narrow: VectorClock<u32, u8> = " 2:1 , 10:255 ".parse().unwrap();
+        assert_eq!("10:255,2:1", narrow.to_string());
+    }
+
+    #[test]
+    fn test_rejects_malformed() {
+        assert!("A".parse::<VectorClock<String>>().is_err());
+        assert!("A:-1".parse::<VectorClock<String>>().is_err());
+        assert!("A:1,".parse::<VectorClock<String>>().is_err());
+        assert!("A:256".parse::<VectorClock<String, u8>>().is_err());
+        assert!("A%+1:1".parse::<VectorClock<String>>().is_err());
+        assert!("A%4:1".parse::<VectorClock<String>>().is_err());
+
+        match "A:1,A:2".parse::<VectorClock<String>>() {
+            Err(Error::InvalidEntry(_)) => {},
+            other => panic!("expected a duplicate host error, got {:?}", other),
+        }
+    }
+}
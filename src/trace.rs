@@ -0,0 +1,172 @@
+use std::fmt::{Display, Write};
+use std::hash::Hash;
+
+use clock::VectorClock;
+use dag::CausalDag;
+use history::{quoted, write_clock};
+
+/// An event recorded by a `CausalRecorder`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TraceEvent<HostType: Hash + Eq> {
+    pub id: String,
+    pub host: HostType,
+    pub clock: VectorClock<HostType>,
+}
+
+/// Collects events from a distributed trace and exports their
+/// happened-before graph, e.g. to look at when debugging concurrency bugs.
+///
+/// Edges are computed from the events' clocks, with transitive edges left
+/// out: an event only links to the events that directly follow it. Event
+/// ids are used as node names in the exports and should be unique.
+#[derive(Debug, Clone, Default)]
+pub struct CausalRecorder<HostType: Hash + Eq> {
+    events: Vec<TraceEvent<HostType>>,
+}
+
+impl<HostType: Clone + Hash + Eq + Ord + Display> CausalRecorder<HostType> {
+    pub fn new() -> CausalRecorder<HostType> {
+        CausalRecorder {
+            events: Vec::new(),
+        }
+    }
+
+    /// Record that `host` performed the event `id` at `clock`, returning
+    /// the event's node in `dag`.
+    pub fn record(&mut self, id: &str, host: HostType, clock: VectorClock<HostType>) -> usize {
+        self.events.push(TraceEvent {
+            id: id.to_owned(),
+            host,
+            clock,
+        });
+
+        self.events.len() - 1
+    }
+
+    pub fn events(&self) -> &[TraceEvent<HostType>] {
+        &self.events
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// The happened-before graph, with the events as nodes in the order
+    /// they were recorded.
+    pub fn dag(&self) -> CausalDag {
+        CausalDag::build(self.events.iter().map(|e| &e.clock))
+    }
+
+    /// The graph in Graphviz DOT, with each host's events grouped in a
+    /// cluster and labelled with their clocks.
+    pub fn to_dot(&self) -> String {
+        let mut hosts: Vec<&HostType> = self.events.iter().map(|e| &e.host).collect();
+        hosts.sort();
+        hosts.dedup();
+
+        let mut out = String::from("digraph causal {\n");
+
+        for (i, host) in hosts.into_iter().enumerate() {
+            let _ = writeln!(out, "  subgraph cluster_{} {{", i);
+            let _ = writeln!(out, "    label={};", quoted(host));
+
+            for event in self.events.iter().filter(|e| e.host == *host) {
+                let _ = writeln!(out, "    {} [label={}];",
+                                 quoted(&event.id), quoted(&format!("{}\n{}", event.id, event.clock)));
+            }
+
+            out.push_str("  }\n");
+        }
+
+        for (from, to) in self.dag().edges() {
+            let _ = writeln!(out, "  {} -> {};", quoted(&self.events[from].id), quoted(&self.events[to].id));
+        }
+
+        out.push_str("}\n");
+        out
+    }
+
+    /// The graph as a JSON object with the `events`, each with its `id`,
+    /// `host` and `clock`, and the `edges` as `[from, to]` pairs of ids.
+    pub fn to_json(&self) -> String {
+        let mut out = String::from("{\"events\":[");
+
+        for (i, event) in self.events.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+
+            let _ = write!(out, "{{\"id\":{},\"host\":{},\"clock\":{{", quoted(&event.id), quoted(&event.host));
+            write_clock(&mut out, &event.clock, ":", ",");
+            out.push_str("}}");
+        }
+
+        out.push_str("],\"edges\":[");
+
+        for (i, (from, to)) in self.dag().edges().into_iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+
+            let _ = write!(out, "[{},{}]", quoted(&self.events[from].id), quoted(&self.events[to].id));
+        }
+
+        out.push_str("]}");
+        out
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::CausalRecorder;
+    use clock::VectorClock;
+
+    fn recorder() -> CausalRecorder<&'static str> {
+        let a1 = VectorClock::new().incremented("A");
+        let b1 = VectorClock::new().incremented("B");
+        let a2 = a1.merge_with(&b1).incremented("A");
+        let b2 = a2.incremented("B");
+
+        let mut r = CausalRecorder::new();
+        r.record("a1", "A", a1);
+        r.record("b1", "B", b1);
+        r.record("a2", "A", a2);
+        r.record("b2", "B", b2);
+        r
+    }
+
+    #[test]
+    fn test_json_collapses_transitive_edges() {
+        assert_eq!(concat!(
+            r#"{"events":[{"id":"a1","host":"A","clock":{"A":1}},{"id":"b1","host":"B","clock":{"B":1}},"#,
+            r#"{"id":"a2","host":"A","clock":{"A":2,"B":1}},{"id":"b2","host":"B","clock":{"A":2,"B":2}}],"#,
+            r#""edges":[["a1","a2"],["b1","a2"],["a2","b2"]]}"#), recorder().to_json());
+
+        assert_eq!(r#"{"events":[],"edges":[]}"#, CausalRecorder::<&str>::new().to_json());
+    }
+
+    #[test]
+    fn test_dot() {
+        assert_eq!("\
+digraph causal {
+  subgraph cluster_0 {
+    label=\"A\";
+    \"a1\" [label=\"a1\\nA:1\"];
+    \"a2\" [label=\"a2\\nA:2,B:1\"];
+  }
+  subgraph cluster_1 {
+    label=\"B\";
+    \"b1\" [label=\"b1\\nB:1\"];
+    \"b2\" [label=\"b2\\nA:2,B:2\"];
+  }
+  \"a1\" -> \"a2\";
+  \"b1\" -> \"a2\";
+  \"a2\" -> \"b2\";
+}
+", recorder().to_dot());
+    }
+}
@@ -0,0 +1,124 @@
+use std::prelude::v1::*;
+use std::hash::Hash;
+
+use clock::VectorClock;
+use stamped::Stamped;
+
+/// The frontier of a value's versions: every version that no other version
+/// in the set supersedes, e.g. the siblings stored for a key in a
+/// Dynamo-style store.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct VersionSet<HostType: Hash + Eq, T> {
+    versions: Vec<Stamped<T, HostType>>,
+}
+
+impl<HostType: Clone + Hash + Eq, T> VersionSet<HostType, T> {
+    pub fn new() -> VersionSet<HostType, T> {
+        VersionSet {
+            versions: Vec::new(),
+        }
+    }
+
+    /// Add a version, discarding every version its clock supersedes.
+    ///
+    /// Returns `false`, leaving the set unchanged, if a version with an
+    /// equal or newer clock is already present.
+    pub fn insert(&mut self, clock: VectorClock<HostType>, value: T) -> bool {
+        if self.versions.iter().any(|v| v.clock.dominates(&clock)) {
+            return false;
+        }
+
+        self.versions.retain(|v| !clock.strictly_dominates(&v.clock));
+        self.versions.push(Stamped::new(value, clock));
+        true
+    }
+
+    /// The versions in the set, all mutually concurrent.
+    pub fn concurrent_versions(&self) -> &[Stamped<T, HostType>] {
+        &self.versions
+    }
+
+    pub fn values(&self) -> Vec<&T> {
+        self.versions.iter().map(|v| &v.value).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.versions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.versions.is_empty()
+    }
+
+    /// The clock covering every version in the set, to use as the context
+    /// of a write that replaces them all.
+    pub fn clock(&self) -> VectorClock<HostType> {
+        let mut clock = VectorClock::new();
+
+        for version in &self.versions {
+            clock.merge(&version.clock);
+        }
+
+        clock
+    }
+
+    pub fn into_versions(self) -> Vec<Stamped<T, HostType>> {
+        self.versions
+    }
+}
+
+impl<HostType: Clone + Hash + Eq, T: Clone> VersionSet<HostType, T> {
+    /// Insert every version of `other`, e.g. to combine the sibling sets
+    /// of two replicas.
+    pub fn merge(&mut self, other: &VersionSet<HostType, T>) {
+        for version in &other.versions {
+            self.insert(version.clock.clone(), version.value.clone());
+        }
+    }
+}
+
+impl<HostType: Clone + Hash + Eq, T> Default for VersionSet<HostType, T> {
+    fn default() -> VersionSet<HostType, T> {
+        VersionSet::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::VersionSet;
+    use clock::VectorClock;
+
+    #[test]
+    fn test_insert_discards_dominated() {
+        let base = VectorClock::new().incremented("A");
+        let mut set = VersionSet::new();
+
+        assert!(set.insert(base.clone(), "base"));
+        assert!(set.insert(base.incremented("A"), "a"));
+        assert!(set.insert(base.incremented("B"), "b"));
+        assert_eq!(vec![&"a", &"b"], set.values());
+
+        assert!(!set.insert(base.clone(), "stale"));
+        assert!(!set.insert(base.incremented("B"), "duplicate"));
+
+        assert!(set.insert(set.clock().incremented("C"), "resolved"));
+        assert_eq!(vec![&"resolved"], set.values());
+    }
+
+    #[test]
+    fn test_merge() {
+        let base = VectorClock::new().incremented("A");
+        let mut left = VersionSet::new();
+        let mut right = VersionSet::new();
+
+        left.insert(base.clone(), 0);
+        left.insert(base.incremented("L"), 1);
+        right.insert(base.clone(), 0);
+        right.insert(base.incremented("R"), 2);
+
+        left.merge(&right);
+
+        assert_eq!(vec![&1, &2], left.values());
+        assert_eq!(2, left.concurrent_versions().len());
+    }
+}
@@ -0,0 +1,99 @@
+use std::hash::Hash;
+
+use clock::{VectorClock, TemporalRelation};
+
+/// A value that carries its own vector clock.
+///
+/// Implementors provide access to the clock and a `resolve` function that
+/// merges the data of two concurrent versions; the remaining methods are
+/// derived from those. With the `derive` feature, `#[derive(CausallyVersioned)]`
+/// generates the implementation from a `VectorClock` field the struct
+/// declares and marks `#[clock]`, and a `#[causally_versioned(merge = "path")]`
+/// attribute naming the resolver.
+pub trait CausallyVersioned<HostType: Clone + Hash + Eq>: Clone {
+    fn clock(&self) -> &VectorClock<HostType>;
+
+    fn clock_mut(&mut self) -> &mut VectorClock<HostType>;
+
+    /// Merge the data of a concurrent version `other` into `self`. The
+    /// clocks are merged afterwards and need not be touched.
+    fn resolve(&mut self, other: &Self);
+
+    /// Record a local change made by `host`.
+    fn tick(&mut self, host: HostType) {
+        self.clock_mut().increment(host);
+    }
+
+    fn relation_to(&self, other: &Self) -> TemporalRelation {
+        self.clock().temporal_relation(other.clock())
+    }
+
+    /// Bring `self` up to date with another version, returning how the two
+    /// versions were related beforehand.
+    ///
+    /// A newer version replaces `self`, an older or equal one is ignored,
+    /// and a concurrent one is merged with `resolve`.
+    fn observe(&mut self, other: &Self) -> TemporalRelation {
+        let relation = self.relation_to(other);
+
+        match relation {
+            TemporalRelation::Caused => *self = other.clone(),
+            TemporalRelation::Concurrent => {
+                self.resolve(other);
+                self.clock_mut().merge(other.clock());
+            },
+            _ => {},
+        }
+
+        relation
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::CausallyVersioned;
+    use clock::{VectorClock, TemporalRelation};
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Counter {
+        clock: VectorClock<&'static str>,
+        value: u64,
+    }
+
+    impl CausallyVersioned<&'static str> for Counter {
+        fn clock(&self) -> &VectorClock<&'static str> {
+            &self.clock
+        }
+
+        fn clock_mut(&mut self) -> &mut VectorClock<&'static str> {
+            &mut self.clock
+        }
+
+        fn resolve(&mut self, other: &Counter) {
+            self.value = ::std::cmp::max(self.value, other.value);
+        }
+    }
+
+    #[test]
+    fn test_observe() {
+        let mut a = Counter { clock: VectorClock::new(), value: 1 };
+        a.tick("A");
+
+        let mut b = a.clone();
+        b.value = 5;
+        b.tick("B");
+
+        let mut c = a.clone();
+        c.value = 3;
+        c.tick("C");
+
+        assert_eq!(TemporalRelation::Caused, a.observe(&b));
+        assert_eq!(b, a);
+
+        assert_eq!(TemporalRelation::Concurrent, a.observe(&c));
+        assert_eq!(5, a.value);
+        assert_eq!(TemporalRelation::EffectOf, a.relation_to(&c));
+
+        assert_eq!(TemporalRelation::EffectOf, a.observe(&c));
+    }
+}
@@ -0,0 +1,191 @@
+use std::prelude::v1::*;
+use std::fmt::Display;
+use std::hash::Hash;
+use std::str::FromStr;
+
+use clock::VectorClock;
+use counter::Counter;
+use error::{Error, Result};
+
+impl<HostType: Clone + Hash + Eq + Display, CounterType: Counter> VectorClock<HostType, CounterType> {
+    /// Encode the clock in the canonical wire format.
+    ///
+    /// The format is a varint entry count followed by each entry as a
+    /// varint host length, the host's `Display` bytes and a varint counter.
+    /// Entries are sorted by host bytes and zero counters are left out, so
+    /// equal clocks always encode to the same bytes, which makes the
+    /// encoding suitable for hashing and signing. Varints are unsigned
+    /// LEB128, and counters are written as `u64` whatever their type.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut entries: Vec<(String, u64)> = self.iter()
+            .filter(|&(_, &n)| n > CounterType::ZERO)
+            .map(|(host, &n)| (host.to_string(), n.to_u64()))
+            .collect();
+        entries.sort();
+
+        let mut out = Vec::new();
+        write_varint(&mut out, entries.len() as u64);
+
+        for (host, n) in entries {
+            write_varint(&mut out, host.len() as u64);
+            out.extend_from_slice(host.as_bytes());
+            write_varint(&mut out, n);
+        }
+
+        out
+    }
+}
+
+impl<HostType: Clone + Hash + Eq + FromStr, CounterType: Counter> VectorClock<HostType, CounterType> {
+    /// Decode a clock written by `encode`.
+    ///
+    /// Input that is not in canonical form, i.e. with unsorted or duplicate
+    /// hosts, zero counters, overlong varints or trailing bytes, is
+    /// rejected, so every accepted input re-encodes to itself. A counter
+    /// too large for `CounterType` fails with `Error::Overflow`.
+    pub fn decode(bytes: &[u8]) -> Result<VectorClock<HostType, CounterType>> {
+        decode_with(bytes, |host| host.parse().ok())
+    }
+}
+
+/// `VectorClock::decode`, with hosts made from their names by `make_host`.
+pub(crate) fn decode_with<HostType, CounterType, F>(bytes: &[u8], mut make_host: F)
+    -> Result<VectorClock<HostType, CounterType>>
+    where HostType: Clone + Hash + Eq,
+          CounterType: Counter,
+          F: FnMut(&str) -> Option<HostType>
+{
+    let mut input = bytes;
+    let non_canonical = |what: &str| Error::Decode(format!("non-canonical clock encoding: {}", what));
+
+    let count = read_varint(&mut input)?;
+    let mut entries = Vec::with_capacity((count as usize).min(input.len()));
+    let mut previous: Option<&[u8]> = None;
+
+    for _ in 0..count {
+        let len = read_varint(&mut input)? as usize;
+        let name = take(&mut input, len)?;
+
+        if previous.is_some_and(|p| p >= name) {
+            return Err(non_canonical("hosts out of order"));
+        }
+        previous = Some(name);
+
+        let n = read_varint(&mut input)?;
+
+        if n == 0 {
+            return Err(non_canonical("zero counter"));
+        }
+
+        let n = CounterType::from_u64(n).ok_or(Error::Overflow)?;
+
+        let host = ::std::str::from_utf8(name).ok()
+            .and_then(&mut make_host)
+            .ok_or_else(|| Error::Decode("invalid host in clock encoding".to_owned()))?;
+
+        entries.push((host, n));
+    }
+
+    if !input.is_empty() {
+        return Err(Error::Decode("trailing bytes after clock".to_owned()));
+    }
+
+    Ok(entries.into_iter().collect())
+}
+
+pub(crate) fn write_varint(out: &mut Vec<u8>, mut n: u64) {
+    while n >= 0x80 {
+        out.push((n as u8) | 0x80);
+        n >>= 7;
+    }
+
+    out.push(n as u8);
+}
+
+/// Read an unsigned LEB128 varint, rejecting overlong encodings.
+pub(crate) fn read_varint(input: &mut &[u8]) -> Result<u64> {
+    let mut n = 0u64;
+
+    for shift in (0..64).step_by(7) {
+        let b = take(input, 1)?[0];
+
+        if shift == 63 && b > 1 {
+            return Err(Error::Overflow);
+        }
+
+        n |= u64::from(b & 0x7F) << shift;
+
+        if b & 0x80 == 0 {
+            if b == 0 && shift > 0 {
+                return Err(Error::Decode("overlong varint".to_owned()));
+            }
+
+            return Ok(n);
+        }
+    }
+
+    Err(Error::Overflow)
+}
+
+pub(crate) fn take<'a>(input: &mut &'a [u8], n: usize) -> Result<&'a [u8]> {
+    if input.len() < n {
+        return Err(Error::Decode("truncated clock encoding".to_owned()));
+    }
+
+    let (head, tail) = input.split_at(n);
+    *input = tail;
+    Ok(head)
+}
+
+#[cfg(test)]
+mod test {
+    use std::prelude::v1::*;
+    use super::{write_varint, read_varint};
+    use clock::VectorClock;
+    use error::Error;
+
+    #[test]
+    fn test_round_trip_is_canonical() {
+        let a = vec![("b".to_owned(), 300), ("a".to_owned(), 1), ("c".to_owned(), 0)].into_iter().collect::<VectorClock<_>>();
+        let b = vec![("a".to_owned(), 1), ("b".to_owned(), 300)].into_iter().collect::<VectorClock<_>>();
+
+        let bytes = a.encode();
+
+        assert_eq!(vec![2, 1, b'a', 1, 1, b'b', 0xAC, 0x02], bytes);
+        assert_eq!(bytes, b.encode());
+        assert_eq!(b, VectorClock::<String>::decode(&bytes).unwrap());
+    }
+
+    #[test]
+    fn test_rejects_non_canonical() {
+        assert!(VectorClock::<String>::decode(&[2, 1, b'b', 1, 1, b'a', 1]).is_err());
+        assert!(VectorClock::<String>::decode(&[2, 1, b'a', 1, 1, b'a', 1]).is_err());
+        assert!(VectorClock::<String>::decode(&[1, 1, b'a', 0]).is_err());
+        assert!(VectorClock::<String>::decode(&[1, 1, b'a', 0x81, 0x00]).is_err());
+        assert!(VectorClock::<String>::decode(&[0, 0]).is_err());
+        assert!(VectorClock::<String>::decode(&[1, 1]).is_err());
+    }
+
+    #[test]
+    fn test_narrow_counters() {
+        let small = vec![("a".to_owned(), 300u16)].into_iter().collect::<VectorClock<_, u16>>();
+        let wide = vec![("a".to_owned(), 1u64 << 32)].into_iter().collect::<VectorClock<_>>();
+
+        assert_eq!(small, VectorClock::<String, u16>::decode(&small.encode()).unwrap());
+        assert_eq!(VectorClock::<String>::decode(&small.encode()).unwrap(),
+                   vec![("a".to_owned(), 300)].into_iter().collect::<VectorClock<_>>());
+        assert_eq!(Err(Error::Overflow), VectorClock::<String, u32>::decode(&wide.encode()));
+    }
+
+    #[test]
+    fn test_varint_limits() {
+        let mut out = Vec::new();
+        write_varint(&mut out, u64::MAX);
+
+        assert_eq!(10, out.len());
+        assert_eq!(u64::MAX, read_varint(&mut &out[..]).unwrap());
+
+        out[9] = 0x02;
+        assert_eq!(Err(Error::Overflow), read_varint(&mut &out[..]));
+    }
+}
@@ -0,0 +1,38 @@
+#![cfg(feature = "derive")]
+
+extern crate vectorclock;
+
+use vectorclock::{CausallyVersioned, TemporalRelation, VectorClock};
+
+#[derive(Clone, Debug, CausallyVersioned)]
+#[causally_versioned(merge = "union")]
+struct Tags {
+    #[clock]
+    version: VectorClock<String>,
+    tags: Vec<String>,
+}
+
+fn union(a: &mut Tags, b: &Tags) {
+    for tag in &b.tags {
+        if !a.tags.contains(tag) {
+            a.tags.push(tag.clone());
+        }
+    }
+}
+
+#[test]
+fn test_derived_observe() {
+    let mut a = Tags { version: VectorClock::new(), tags: vec!["x".to_owned()] };
+    a.tick("A".to_owned());
+
+    let mut b = a.clone();
+    b.tags.push("y".to_owned());
+    b.tick("B".to_owned());
+
+    a.tags.push("z".to_owned());
+    a.tick("A".to_owned());
+
+    assert_eq!(TemporalRelation::Concurrent, a.observe(&b));
+    assert_eq!(vec!["x", "z", "y"], a.tags);
+    assert_eq!(TemporalRelation::EffectOf, a.relation_to(&b));
+}
@@ -0,0 +1,122 @@
+//! `#[derive(CausallyVersioned)]` for vectorclock-rs. Use it through the
+//! `derive` feature of the `vectorclock-rs` crate rather than directly.
+
+extern crate proc_macro;
+extern crate proc_macro2;
+#[macro_use]
+extern crate quote;
+extern crate syn;
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use syn::{Data, DeriveInput, Error, Fields, GenericArgument, LitStr, Path, PathArguments, Type};
+
+/// Implement `vectorclock::CausallyVersioned` for a struct.
+///
+/// The struct must declare a `VectorClock<HostType>` field itself and mark
+/// it `#[clock]`, since a derive can't add fields. It also needs a
+/// `#[causally_versioned(merge = "path::to::fn")]` attribute naming a
+/// `fn(&mut Self, &Self)` that merges concurrent versions.
+#[proc_macro_derive(CausallyVersioned, attributes(clock, causally_versioned))]
+pub fn derive_causally_versioned(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as DeriveInput);
+
+    expand(&input).unwrap_or_else(|e| e.to_compile_error()).into()
+}
+
+fn expand(input: &DeriveInput) -> Result<proc_macro2::TokenStream, Error> {
+    let fields = match input.data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Named(ref fields) => &fields.named,
+            _ => return Err(Error::new(Span::call_site(), "CausallyVersioned requires named fields")),
+        },
+        _ => return Err(Error::new(Span::call_site(), "CausallyVersioned can only be derived for structs")),
+    };
+
+    let mut clock_fields = fields.iter().filter(|f| f.attrs.iter().any(|a| a.path().is_ident("clock")));
+    let clock_field = clock_fields.next()
+        .ok_or_else(|| Error::new(Span::call_site(), "missing a field marked #[clock]"))?;
+
+    if let Some(extra) = clock_fields.next() {
+        return Err(Error::new_spanned(extra, "only one field can be marked #[clock]"));
+    }
+
+    let clock_name = clock_field.ident.as_ref().unwrap();
+    let host_type = host_type(&clock_field.ty)?;
+    let merge = merge_fn(input)?;
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::vectorclock::CausallyVersioned<#host_type> for #name #ty_generics #where_clause {
+            fn clock(&self) -> &::vectorclock::VectorClock<#host_type> {
+                &self.#clock_name
+            }
+
+            fn clock_mut(&mut self) -> &mut ::vectorclock::VectorClock<#host_type> {
+                &mut self.#clock_name
+            }
+
+            fn resolve(&mut self, other: &Self) {
+                #merge(self, other)
+            }
+        }
+    })
+}
+
+fn host_type(ty: &Type) -> Result<&Type, Error> {
+    let not_a_clock = || Error::new_spanned(ty, "the #[clock] field must be a VectorClock<HostType>");
+
+    let segment = match *ty {
+        Type::Path(ref p) => p.path.segments.last().ok_or_else(not_a_clock)?,
+        _ => return Err(not_a_clock()),
+    };
+
+    if segment.ident != "VectorClock" {
+        return Err(not_a_clock());
+    }
+
+    match segment.arguments {
+        PathArguments::AngleBracketed(ref args) if args.args.len() == 1 => match args.args[0] {
+            GenericArgument::Type(ref host) => Ok(host),
+            _ => Err(not_a_clock()),
+        },
+        _ => Err(not_a_clock()),
+    }
+}
+
+fn merge_fn(input: &DeriveInput) -> Result<Path, Error> {
+    let mut merge = None;
+
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("causally_versioned")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("merge") {
+                let lit: LitStr = meta.value()?.parse()?;
+                merge = Some(lit.parse()?);
+                Ok(())
+            }
+            else {
+                Err(meta.error("unknown causally_versioned option"))
+            }
+        })?;
+    }
+
+    merge.ok_or_else(|| Error::new(Span::call_site(), "missing #[causally_versioned(merge = \"...\")]"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::host_type;
+
+    #[test]
+    fn test_host_type() {
+        let host = |ty: &str| host_type(&syn::parse_str(ty).unwrap()).map(|h| quote!(#h).to_string());
+
+        assert_eq!("String", host("VectorClock<String>").unwrap());
+        assert_eq!("u32", host("::vectorclock::VectorClock<u32>").unwrap());
+        assert!(host("Vec<String>").is_err());
+        assert!(host("Option<VectorClock<String>>").is_err());
+        assert!(host("VectorClock").is_err());
+    }
+}